@@ -0,0 +1,58 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// bakes the commit, build date and compiler into the binary for `cs version`.
+// anything we can't find out (e.g. building from a tarball without .git) is "unknown"
+fn main() {
+    let git_hash = output("git", &["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=CAWA_GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=CAWA_BUILD_DATE={}", build_date());
+    println!("cargo:rustc-env=CAWA_RUSTC_VERSION={}", rustc_version);
+
+    // rebuild when HEAD moves. only watch files that exist, cargo reruns every
+    // time for missing ones
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=build.rs");
+    let git = Path::new(".git");
+    for file in ["HEAD", "packed-refs"] {
+        if git.join(file).exists() {
+            println!("cargo:rerun-if-changed=.git/{}", file);
+        }
+    }
+    if let Ok(head) = std::fs::read_to_string(git.join("HEAD"))
+        && let Some(r) = head.trim().strip_prefix("ref: ")
+        && git.join(r).exists()
+    {
+        println!("cargo:rerun-if-changed=.git/{}", r);
+    }
+}
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    let text = String::from_utf8(out.stdout).ok()?.trim().to_string();
+    (out.status.success() && !text.is_empty()).then_some(text)
+}
+
+// YYYY-MM-DD in UTC. SOURCE_DATE_EPOCH wins so reproducible builds stay reproducible
+fn build_date() -> String {
+    let secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0));
+
+    // days since 1970 to a civil date (Howard Hinnant's algorithm)
+    let z = secs.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
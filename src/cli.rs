@@ -1,13 +1,100 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// what --version prints; the build details come from build.rs
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("CAWA_GIT_HASH"),
+    " ",
+    env!("CAWA_BUILD_DATE"),
+    ", ",
+    env!("CAWA_RUSTC_VERSION"),
+    ")"
+);
 
 #[derive(Parser)]
-#[command(name = "cs", disable_help_subcommand = true)]
+#[command(name = "cs", version, long_version = LONG_VERSION, disable_help_subcommand = true)]
 #[command(about = "Context-Aware Workspace Automation")]
 pub struct Cli {
-    #[arg(long, global = true)]
-    pub notify: bool,
+    // --notify uses the config's notify_mode, --notify=terminal etc. overrides it
+    #[arg(long, global = true, value_name = "MODE", num_args = 0..=1, require_equals = true)]
+    pub notify: Option<Option<NotifyMode>>,
     #[arg(long, global = true)]
     pub dry_run: bool,
+    // let parallel commands read from the terminal (they get no stdin by default)
+    #[arg(long, global = true)]
+    pub inherit_stdin: bool,
+    // feed this file to the commands' stdin. with `add`, stored on the alias
+    #[arg(long, global = true, value_name = "PATH")]
+    pub stdin_file: Option<String>,
+    // drop the command's stdout / stderr. with `add`, stored on the alias instead
+    #[arg(long, global = true)]
+    pub quiet_out: bool,
+    #[arg(long, global = true)]
+    pub quiet_err: bool,
+    // drop runtime args for aliases that don't take any, instead of erroring
+    #[arg(long, global = true)]
+    pub ignore_args: bool,
+    // run an alias even though it's been turned off with `cs disable`
+    #[arg(long, global = true)]
+    pub include_disabled: bool,
+    // buffer each parallel command's output and print it as one block once all finish
+    #[arg(long, global = true)]
+    pub group: bool,
+    // run aliases even when their condition / require_files say to skip
+    #[arg(long, global = true)]
+    pub force: bool,
+    // start the alias in the background, output goes to .cawa_logs/<alias>.log
+    #[arg(long, global = true)]
+    pub detach: bool,
+    // explain where the alias came from and what exactly will run (on stderr)
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+    // print the fully resolved alias as JSON instead of running it
+    #[arg(long, global = true)]
+    pub dump_resolved: bool,
+    // run at most N parallel commands at a time
+    #[arg(short, long, global = true, value_name = "N", value_parser = clap::value_parser!(usize))]
+    pub jobs: Option<usize>,
+    // wait this long between starting each parallel command
+    #[arg(long, global = true, value_name = "MS")]
+    pub stagger: Option<u64>,
+    // run commands in a login shell so ~/.profile and friends are sourced first.
+    // with `add`, stored on the alias instead
+    #[arg(long, global = true)]
+    pub login: bool,
+    // run commands at lower priority, 0-19 like `nice -n`. with `add`, stored on the alias
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(i32).range(0..=19))]
+    pub nice: Option<i32>,
+    // load KEY=VALUE variables from this file for the commands (instead of the
+    // config's env_file)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub env_file: Option<String>,
+    // set a variable for the commands; wins over the env file and the environment
+    #[arg(long = "env", global = true, value_name = "KEY=VALUE")]
+    pub env: Vec<String>,
+    // diagnostics about cs itself, on stderr (also settable with CAWA_LOG)
+    #[arg(long, global = true, value_enum, value_name = "LEVEL")]
+    pub log_level: Option<LogLevel>,
+    // plain "cs:" instead of the 🐙 in front of cs's own output
+    #[arg(long, global = true)]
+    pub no_emoji: bool,
+    // don't warn about config keys cs doesn't know (e.g. ones from a newer version)
+    #[arg(long, global = true)]
+    pub allow_unknown_fields: bool,
+    // json: newline-delimited progress events on stdout instead of the usual output
+    #[arg(long, global = true, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Human)]
+    pub output: OutputFormat,
+    // use this config file instead of .cawa_cfg.json in the current directory
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    // look for this file name in the current directory instead of .cawa_cfg.json
+    // (also settable with CAWA_CONFIG_NAME). --config wins when both are given
+    #[arg(long, global = true, value_name = "NAME")]
+    pub config_name: Option<String>,
 
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -24,8 +111,33 @@ pub enum Commands {
         timeout: Option<u64>,
         #[arg(short = 'g', long)]
         global: bool,
+        // refuse runtime args instead of appending them to the command
+        #[arg(long)]
+        no_append_args: bool,
+        // post-process the output, appended as `| <cmd>` after any runtime args
+        #[arg(long, value_name = "CMD")]
+        pipe_to: Option<String>,
+        // store the contents of a script file, frozen at add time
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["parallel", "commands"])]
+        file: Option<PathBuf>,
+        // make this another name for an existing alias instead of storing a command
+        #[arg(long, value_name = "ALIAS", conflicts_with_all = ["parallel", "commands", "file"])]
+        alias_of: Option<String>,
+        // leave it out of `cs list` and the tui; it still runs by name
+        #[arg(long)]
+        hidden: bool,
+        // have the tui ask y/n before running it
+        #[arg(long)]
+        confirm: bool,
+        // label it, repeat for several (`cs tui --tag` picks them out)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        // make {NAME} in the command a parameter, given as --NAME when run. repeat
+        // for several; undeclared braces are left alone
+        #[arg(long = "param", value_name = "NAME")]
+        params: Vec<String>,
         alias: String,
-        #[arg(required = true, num_args = 1..)]
+        #[arg(required_unless_present_any = ["file", "alias_of"], num_args = 1..)]
         commands: Vec<String>,
     },
     Remove {
@@ -33,6 +145,38 @@ pub enum Commands {
         global: bool,
         alias: String,
     },
+    // turn an alias off without deleting it, and back on
+    Disable {
+        #[arg(short = 'g', long)]
+        global: bool,
+        alias: String,
+    },
+    Enable {
+        #[arg(short = 'g', long)]
+        global: bool,
+        alias: String,
+    },
+    SetVar {
+        #[arg(short = 'g', long)]
+        global: bool,
+        key: String,
+        value: String,
+    },
+    // the alias a bare `cs` runs
+    SetDefault {
+        #[arg(short = 'g', long)]
+        global: bool,
+        // go back to printing help
+        #[arg(long, conflicts_with = "alias")]
+        clear: bool,
+        #[arg(required_unless_present = "clear")]
+        alias: Option<String>,
+    },
+    // shared command fragments aliases pull in with {{template:name}}
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
     Rename {
         old_alias: String,
         new_alias: String,
@@ -50,10 +194,234 @@ pub enum Commands {
         #[arg(required = true, num_args = 1..)]
         commands: Vec<String>,
     },
+    // run an alias repeatedly and report timing stats
+    Bench {
+        #[arg(long, default_value_t = 10)]
+        runs: u32,
+        // untimed runs first, to warm caches
+        #[arg(long, default_value_t = 0)]
+        warmup: u32,
+        alias: String,
+        #[arg(allow_hyphen_values = true, num_args = 0..)]
+        args: Vec<String>,
+    },
+    // run an alias, then again whenever a file under the watched paths changes.
+    // r or SIGHUP reruns straight away, q or ctrl-c stops
+    Watch {
+        // a file or directory to watch, repeat for several (default: .)
+        #[arg(short = 'w', long = "path", value_name = "PATH")]
+        paths: Vec<PathBuf>,
+        // how long the files must stay quiet before a change triggers a run
+        #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, default_value = "300ms")]
+        debounce: Duration,
+        alias: String,
+        #[arg(allow_hyphen_values = true, num_args = 0..)]
+        args: Vec<String>,
+    },
+    // run several aliases one after another, stopping at the first failure
+    Batch {
+        // run them all at once instead
+        #[arg(short, long)]
+        parallel: bool,
+        // names or globs like 'test-*', which run every matching alias in name order
+        #[arg(required = true)]
+        aliases: Vec<String>,
+    },
+    // repeat the last alias invocation, args included
+    Rerun,
+    // show the end of an alias's log (the most recently run alias by default)
+    Last {
+        alias: Option<String>,
+        #[arg(short = 'n', long, default_value_t = 40)]
+        lines: usize,
+        // open the whole log in $PAGER instead
+        #[arg(long)]
+        pager: bool,
+    },
+    // list aliases started with --detach
+    Ps,
+    // kill an alias started with --detach
+    Stop {
+        alias: String,
+    },
+    // the aliases in another tool's format, on stdout or into a file
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Make)]
+        format: ExportFormat,
+        // write here instead of stdout
+        #[arg(short = 'o', long = "out", value_name = "PATH")]
+        out: Option<PathBuf>,
+        // include hidden aliases
+        #[arg(short, long)]
+        all: bool,
+    },
+    // trim the history log, delete old logs and forget exited detached processes.
+    // --dry-run only says what would go
+    Clean {
+        // runs to keep in the history log, newest first (history_keep, or 1000)
+        #[arg(long, value_name = "N")]
+        keep: Option<usize>,
+        // delete logs not written to for this long, e.g. 3d or 12h (log_max_age, or 7d)
+        #[arg(long, value_name = "AGE", value_parser = humantime::parse_duration)]
+        older_than: Option<Duration>,
+    },
     Init,
+    // check the environment for common setup problems
+    Doctor,
+    // version plus the commit, build date and compiler, for bug reports
+    Version,
+    // a short overview of the config: where it is, what's in it, what's turned on
+    Info,
+    // questions about the config file itself
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
     // Interactive mode
-    Tui,
-    List,
+    Tui {
+        // start at the top with the default sort instead of where you left off
+        #[arg(long)]
+        fresh: bool,
+        // only list the aliases with this tag
+        #[arg(long, value_name = "TAG")]
+        tag: Option<String>,
+    },
+    List {
+        // render multi-command aliases as a tree showing how they run
+        #[arg(long)]
+        tree: bool,
+        #[arg(long, value_enum, default_value_t = SortOrder::Name)]
+        sort: SortOrder,
+        // skip reading the history log for the last-run dot (faster with a huge log)
+        #[arg(long)]
+        no_status: bool,
+        // also show when each alias was created and last changed
+        #[arg(short, long)]
+        long: bool,
+        // include hidden aliases
+        #[arg(short, long)]
+        all: bool,
+        // man: a troff man page of the aliases, for `| man -l -`
+        #[arg(long, value_enum, default_value_t = ListFormat::Text)]
+        format: ListFormat,
+    },
     #[command(external_subcommand)]
     External(Vec<String>),
 }
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    // print the config file cs reads and writes here, and whether it exists
+    Path {
+        #[arg(short = 'g', long)]
+        global: bool,
+    },
+    // open the whole config file in $EDITOR, checked before it's saved
+    Edit {
+        #[arg(short = 'g', long)]
+        global: bool,
+    },
+    // check the config file without running anything
+    Validate {
+        #[arg(short = 'g', long)]
+        global: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TemplateAction {
+    Add {
+        #[arg(short = 'g', long)]
+        global: bool,
+        name: String,
+        command: String,
+    },
+    Remove {
+        #[arg(short = 'g', long)]
+        global: bool,
+        name: String,
+    },
+    // every template reachable from here, global ones included
+    List,
+}
+
+// how `cs list` prints the aliases
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    Text,
+    Man,
+}
+
+// what `cs export` writes
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    // a Makefile with a target per alias and `make help`
+    Make,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+        }
+    }
+}
+
+// where --notify sends its message: a desktop popup, a line on stderr (for ssh
+// and tmux, where popups never show up), or both
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyMode {
+    #[default]
+    Desktop,
+    Terminal,
+    Both,
+}
+
+// how alias listings are ordered; recent/usage come from the run history
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Name,
+    Recent,
+    Usage,
+}
+
+impl SortOrder {
+    // cycle order for the tui toggle
+    pub fn next(self) -> SortOrder {
+        match self {
+            SortOrder::Name => SortOrder::Recent,
+            SortOrder::Recent => SortOrder::Usage,
+            SortOrder::Usage => SortOrder::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Name => "name",
+            SortOrder::Recent => "recent",
+            SortOrder::Usage => "usage",
+        }
+    }
+}
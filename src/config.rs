@@ -1,45 +1,326 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions, TryLockError};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use colored::Colorize;
+
+use crate::cli::{NotifyMode, SortOrder};
+use crate::exit_codes::ConfigError;
+use crate::output::prefix;
+use crate::toml_format;
 
 pub const CONFIG_FILE: &str = ".cawa_cfg.json";
+// used instead when it's there and the json one isn't
+pub const TOML_CONFIG_FILE: &str = ".cawa_cfg.toml";
+pub const CONFIG_NAME_ENV: &str = "CAWA_CONFIG_NAME";
 // kept separate from the config so committing the config doesn't leak run timestamps
 const STATE_FILE: &str = ".cawa_state.json";
+// tracks when we last popped a notification so repeated runs don't flood the desktop
+const NOTIFY_STATE_FILE: &str = ".cawa_notify_state.json";
+// the most recent alias invocation, replayed by cs rerun
+const LAST_RUN_FILE: &str = ".cawa_last_run.json";
+const DETACHED_FILE: &str = ".cawa_detached.json";
+// where the tui was left, restored on the next launch
+const TUI_STATE_FILE: &str = ".cawa_tui_state.json";
 
-fn global_config_path() -> PathBuf {
+pub fn global_config_path() -> PathBuf {
     let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
     PathBuf::from(home).join(".config").join("cawa").join("config.json")
 }
 
+// the local config's file name: --config-name, then CAWA_CONFIG_NAME, then the
+// default. only a bare name, a path belongs in --config
+pub fn config_file_name(flag: Option<&str>) -> Result<String> {
+    let name = match flag {
+        Some(name) => name.to_string(),
+        None => match std::env::var(CONFIG_NAME_ENV) {
+            Ok(name) if !name.is_empty() => name,
+            _ if !Path::new(CONFIG_FILE).exists() && Path::new(TOML_CONFIG_FILE).exists() => {
+                return Ok(TOML_CONFIG_FILE.to_string());
+            }
+            _ => return Ok(CONFIG_FILE.to_string()),
+        },
+    };
+    if name.contains(['/', '\\']) || name == "." || name == ".." {
+        bail!(crate::exit_codes::UsageError(format!(
+            "config name '{}' should be a file name, use --config for a path",
+            name
+        )));
+    }
+    Ok(name)
+}
+
+// expand a leading ~ and $VAR / ${VAR} in a user-supplied path. ~user isn't
+// supported and is left alone, as are variables that aren't set, so the eventual
+// "file not found" error shows exactly what was written
+pub fn expand_path(raw: &str) -> PathBuf {
+    let mut s = raw.to_string();
+    if (s == "~" || s.starts_with("~/"))
+        && let Ok(home) = std::env::var("HOME")
+    {
+        s = format!("{}{}", home, &s[1..]);
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s.as_str();
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => out.push_str(&value),
+            _ => out.push_str(&rest[pos..pos + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+    out.push_str(rest);
+    PathBuf::from(out)
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum AliasEntry {
     Single(String),
     Parallel(Vec<String>),
+    // another name for an existing alias. only ever stored in the object form
+    // ({ "alias_of": "build" }) since a bare string already means Single
+    #[serde(skip)]
+    Alias(String),
+    // commands run one after another under a label each, stopping at the first
+    // failure. object form only too ({ "steps": [...] })
+    #[serde(skip)]
+    Steps(Vec<Step>),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Step {
+    pub name: String,
+    pub command: String,
+}
+
+impl Step {
+    // the same label with another command, for the expansions that rewrite commands
+    pub fn with(&self, command: String) -> Step {
+        Step { name: self.name.clone(), command }
+    }
 }
 
 // wraps an alias entry with an optional description
 // supports both the old bare format and the new object format
+// lowering priority only: going below 0 needs root
+pub const NICE_RANGE: std::ops::RangeInclusive<i32> = 0..=19;
+
+// secret_env when the config doesn't say
+pub const DEFAULT_SECRET_ENV: &[&str] = &["*_TOKEN", "*_SECRET", "*_PASSWORD", "*_KEY", "*_CREDENTIALS"];
+
+// max_capture_bytes when the config doesn't say, per stream of each command
+pub const DEFAULT_MAX_CAPTURE_BYTES: u64 = 10 * 1024 * 1024;
+
+// retry_delay_ms / retry_max_delay_ms when the alias doesn't say
+pub const DEFAULT_RETRY_DELAY_MS: u64 = 1000;
+pub const DEFAULT_RETRY_MAX_DELAY_MS: u64 = 60_000;
+
 #[derive(Clone)]
 pub struct AliasConfig {
     pub entry: AliasEntry,
     pub description: Option<String>,
     pub timeout_secs: Option<u64>,
+    // drop the command's stdout/stderr instead of passing them through
+    pub suppress_stdout: bool,
+    pub suppress_stderr: bool,
+    // false for aliases that must never get runtime args glued onto the command
+    pub append_args: bool,
+    pub pipe_to: Option<String>,
+    // preconditions: a shell test that must exit 0 and files that must exist,
+    // otherwise the run is skipped (not failed)
+    pub condition: Option<String>,
+    pub require_files: Vec<String>,
+    // programs that must be on PATH; if any are missing the run is refused up front
+    pub requires: Vec<String>,
+    // always start in the background, as if --detach was passed
+    pub detached: bool,
+    // each command containing {item} runs once per entry here, in parallel
+    pub items: Vec<String>,
+    // the {name} placeholders that are parameters (--name value at run time). any
+    // other {word} is left alone, it's more likely awk or a json body
+    pub params: Vec<String>,
+    // values for {name} parameters that weren't given at run time; these count as
+    // declared too
+    pub defaults: HashMap<String, String>,
+    // milliseconds between starting each parallel command
+    pub stagger_ms: Option<u64>,
+    // run through a login shell so the user's profile sets up PATH etc.
+    pub login_shell: bool,
+    // run at lower priority, 0 (unchanged) to 19 (lowest), like `nice -n`
+    pub nice: Option<i32>,
+    // file fed to the command's stdin, relative to where cs runs
+    pub stdin_file: Option<String>,
+    // give the command a pseudo-terminal when cs captures its output, so it keeps
+    // its colors. stdout and stderr arrive merged
+    pub pty: bool,
+    // run as this user through sudo (plain sudo for "root"), see runner::launcher
+    pub run_as: Option<String>,
+    // rlimits for each process the alias starts (linux only), see runner::apply_limits
+    pub memory_limit_mb: Option<u64>,
+    pub cpu_seconds: Option<u64>,
+    // the tui asks y/n before running it, for the ones that delete or deploy things
+    pub confirm: bool,
+    // regexes checked against each command's output, for tools whose exit code
+    // can't be trusted. see runner::OutputMatchers for which one wins
+    pub success_pattern: Option<String>,
+    pub failure_pattern: Option<String>,
+    // let a success_pattern match turn a failed exit into a success
+    pub success_overrides_exit: bool,
+    // run a failed command again up to this many times, waiting in between. see
+    // runner::retry_delay for how the wait grows
+    pub retries: u32,
+    pub retry_delay_ms: Option<u64>,
+    pub retry_backoff: RetryBackoff,
+    pub retry_jitter: bool,
+    pub retry_max_delay_ms: Option<u64>,
+    // false after `cs disable`: kept in the config but refused when run
+    pub enabled: bool,
+    // left out of `cs list` and the tui, for helpers only other aliases call. runs as usual
+    pub hidden: bool,
+    // free-form labels for grouping, e.g. "deploy". `cs tui --tag` shows just those
+    pub tags: Vec<String>,
+    // RFC 3339 times set by add / edit / rename; missing on aliases written by hand
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    // free-form "_comment" / "_comments" notes, ignored but kept on save
+    pub comment: Option<Value>,
+    pub comments: Option<Value>,
+}
+
+impl AliasConfig {
+    // equal apart from the created_at / updated_at stamps
+    pub fn same_as(&self, other: &AliasConfig) -> bool {
+        let unstamped = |ac: &AliasConfig| serde_json::to_value(AliasConfig { created_at: None, updated_at: None, ..ac.clone() }).ok();
+        unstamped(self) == unstamped(other)
+    }
+
+    pub fn new(entry: AliasEntry) -> AliasConfig {
+        AliasConfig {
+            entry,
+            description: None,
+            timeout_secs: None,
+            suppress_stdout: false,
+            suppress_stderr: false,
+            append_args: true,
+            pipe_to: None,
+            condition: None,
+            require_files: Vec::new(),
+            requires: Vec::new(),
+            params: Vec::new(),
+            defaults: HashMap::new(),
+            tags: Vec::new(),
+            detached: false,
+            items: Vec::new(),
+            stagger_ms: None,
+            login_shell: false,
+            nice: None,
+            stdin_file: None,
+            pty: false,
+            run_as: None,
+            memory_limit_mb: None,
+            cpu_seconds: None,
+            confirm: false,
+            success_pattern: None,
+            failure_pattern: None,
+            success_overrides_exit: false,
+            retries: 0,
+            retry_delay_ms: None,
+            retry_backoff: RetryBackoff::Fixed,
+            retry_jitter: false,
+            retry_max_delay_ms: None,
+            enabled: true,
+            hidden: false,
+            created_at: None,
+            updated_at: None,
+            comment: None,
+            comments: None,
+        }
+    }
+
+    // anything beyond the command itself means we need the object form on disk
+    fn has_extras(&self) -> bool {
+        self.description.is_some()
+            || self.timeout_secs.is_some()
+            || self.suppress_stdout
+            || self.suppress_stderr
+            || !self.append_args
+            || self.pipe_to.is_some()
+            || self.condition.is_some()
+            || !self.require_files.is_empty()
+            || !self.requires.is_empty()
+            || !self.tags.is_empty()
+            || self.detached
+            || matches!(self.entry, AliasEntry::Alias(_) | AliasEntry::Steps(_))
+            || !self.items.is_empty()
+            || !self.params.is_empty()
+            || !self.defaults.is_empty()
+            || self.stagger_ms.is_some()
+            || self.login_shell
+            || self.nice.is_some()
+            || self.stdin_file.is_some()
+            || self.pty
+            || self.run_as.is_some()
+            || self.memory_limit_mb.is_some()
+            || self.cpu_seconds.is_some()
+            || self.confirm
+            || self.success_pattern.is_some()
+            || self.failure_pattern.is_some()
+            || self.success_overrides_exit
+            || self.retries > 0
+            || self.retry_delay_ms.is_some()
+            || self.retry_backoff != RetryBackoff::Fixed
+            || self.retry_jitter
+            || self.retry_max_delay_ms.is_some()
+            || !self.enabled
+            || self.hidden
+            || self.created_at.is_some()
+            || self.updated_at.is_some()
+            || self.comment.is_some()
+            || self.comments.is_some()
+    }
 }
 
 impl Serialize for AliasConfig {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         // use the object form only when there are extra fields to store
-        if self.description.is_some() || self.timeout_secs.is_some() {
+        if self.has_extras() {
             let mut map = serializer.serialize_map(None)?;
+            // notes first, so they read as a header for the alias
+            if let Some(c) = &self.comment {
+                map.serialize_entry("_comment", c)?;
+            }
+            if let Some(c) = &self.comments {
+                map.serialize_entry("_comments", c)?;
+            }
             match &self.entry {
                 AliasEntry::Single(cmd) => map.serialize_entry("run", cmd)?,
                 AliasEntry::Parallel(cmds) => map.serialize_entry("parallel", cmds)?,
+                AliasEntry::Alias(target) => map.serialize_entry("alias_of", target)?,
+                AliasEntry::Steps(steps) => map.serialize_entry("steps", steps)?,
             }
             if let Some(desc) = &self.description {
                 map.serialize_entry("description", desc)?;
@@ -47,6 +328,105 @@ impl Serialize for AliasConfig {
             if let Some(t) = self.timeout_secs {
                 map.serialize_entry("timeout_secs", &t)?;
             }
+            if self.suppress_stdout {
+                map.serialize_entry("suppress_stdout", &true)?;
+            }
+            if self.suppress_stderr {
+                map.serialize_entry("suppress_stderr", &true)?;
+            }
+            if !self.append_args {
+                map.serialize_entry("append_args", &false)?;
+            }
+            if let Some(pipe) = &self.pipe_to {
+                map.serialize_entry("pipe_to", pipe)?;
+            }
+            if let Some(cond) = &self.condition {
+                map.serialize_entry("condition", cond)?;
+            }
+            if !self.require_files.is_empty() {
+                map.serialize_entry("require_files", &self.require_files)?;
+            }
+            if !self.requires.is_empty() {
+                map.serialize_entry("requires", &self.requires)?;
+            }
+            if self.detached {
+                map.serialize_entry("detached", &true)?;
+            }
+            if !self.items.is_empty() {
+                map.serialize_entry("items", &self.items)?;
+            }
+            if !self.params.is_empty() {
+                map.serialize_entry("params", &self.params)?;
+            }
+            if !self.defaults.is_empty() {
+                map.serialize_entry("defaults", &self.defaults.iter().collect::<BTreeMap<_, _>>())?;
+            }
+            if let Some(ms) = self.stagger_ms {
+                map.serialize_entry("stagger_ms", &ms)?;
+            }
+            if self.login_shell {
+                map.serialize_entry("login_shell", &true)?;
+            }
+            if let Some(n) = self.nice {
+                map.serialize_entry("nice", &n)?;
+            }
+            if let Some(path) = &self.stdin_file {
+                map.serialize_entry("stdin_file", path)?;
+            }
+            if self.pty {
+                map.serialize_entry("pty", &true)?;
+            }
+            if let Some(user) = &self.run_as {
+                map.serialize_entry("run_as", user)?;
+            }
+            if let Some(mb) = self.memory_limit_mb {
+                map.serialize_entry("memory_limit_mb", &mb)?;
+            }
+            if let Some(secs) = self.cpu_seconds {
+                map.serialize_entry("cpu_seconds", &secs)?;
+            }
+            if !self.tags.is_empty() {
+                map.serialize_entry("tags", &self.tags)?;
+            }
+            if self.confirm {
+                map.serialize_entry("confirm", &true)?;
+            }
+            if let Some(p) = &self.success_pattern {
+                map.serialize_entry("success_pattern", p)?;
+            }
+            if let Some(p) = &self.failure_pattern {
+                map.serialize_entry("failure_pattern", p)?;
+            }
+            if self.success_overrides_exit {
+                map.serialize_entry("success_overrides_exit", &true)?;
+            }
+            if self.retries > 0 {
+                map.serialize_entry("retries", &self.retries)?;
+            }
+            if let Some(ms) = self.retry_delay_ms {
+                map.serialize_entry("retry_delay_ms", &ms)?;
+            }
+            if self.retry_backoff != RetryBackoff::Fixed {
+                map.serialize_entry("retry_backoff", &self.retry_backoff)?;
+            }
+            if self.retry_jitter {
+                map.serialize_entry("retry_jitter", &true)?;
+            }
+            if let Some(ms) = self.retry_max_delay_ms {
+                map.serialize_entry("retry_max_delay_ms", &ms)?;
+            }
+            if !self.enabled {
+                map.serialize_entry("enabled", &false)?;
+            }
+            if self.hidden {
+                map.serialize_entry("hidden", &true)?;
+            }
+            if let Some(t) = &self.created_at {
+                map.serialize_entry("created_at", t)?;
+            }
+            if let Some(t) = &self.updated_at {
+                map.serialize_entry("updated_at", t)?;
+            }
             map.end()
         } else {
             // no extra fields, keep the old bare format so existing configs don't change
@@ -63,7 +443,7 @@ impl<'de> Deserialize<'de> for AliasConfig {
             Value::String(_) | Value::Array(_) => {
                 let entry: AliasEntry =
                     serde_json::from_value(val).map_err(serde::de::Error::custom)?;
-                Ok(AliasConfig { entry, description: None, timeout_secs: None })
+                Ok(AliasConfig::new(entry))
             }
             // new format: { "run": "...", "description": "...", "timeout_secs": 60 }
             Value::Object(obj) => {
@@ -73,6 +453,20 @@ impl<'de> Deserialize<'de> for AliasConfig {
                     .map(|s| s.to_string());
 
                 let timeout_secs = obj.get("timeout_secs").and_then(|v| v.as_u64());
+                let flag = |key: &str| obj.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+                // checked here so a typo in a regex is a config error, not a surprise mid-run
+                let pattern = |key: &str| match obj.get(key).and_then(|v| v.as_str()) {
+                    Some(p) => match regex::Regex::new(p) {
+                        Ok(_) => Ok(Some(p.to_string())),
+                        // the regex crate's message draws a caret under the problem over several lines
+                        Err(e) => {
+                            let e = e.to_string();
+                            let reason = e.lines().last().unwrap_or_default().trim_start_matches("error: ");
+                            Err(serde::de::Error::custom(format!("{} '{}' isn't a valid regex: {}", key, p, reason)))
+                        }
+                    },
+                    None => Ok(None),
+                };
 
                 let entry = if let Some(run) = obj.get("run") {
                     let cmd: String =
@@ -82,77 +476,1066 @@ impl<'de> Deserialize<'de> for AliasConfig {
                     let cmds: Vec<String> = serde_json::from_value(parallel.clone())
                         .map_err(serde::de::Error::custom)?;
                     AliasEntry::Parallel(cmds)
+                } else if let Some(target) = obj.get("alias_of").and_then(|v| v.as_str()) {
+                    AliasEntry::Alias(target.to_string())
+                } else if let Some(steps) = obj.get("steps") {
+                    let steps: Vec<Step> =
+                        serde_json::from_value(steps.clone()).map_err(serde::de::Error::custom)?;
+                    if steps.is_empty() {
+                        return Err(serde::de::Error::custom("'steps' needs at least one step"));
+                    }
+                    AliasEntry::Steps(steps)
                 } else {
                     return Err(serde::de::Error::custom(
-                        "alias config must have a 'run' or 'parallel' field",
+                        "alias config must have a 'run', 'parallel', 'steps' or 'alias_of' field",
                     ));
                 };
 
-                Ok(AliasConfig { entry, description, timeout_secs })
+                let nice = match obj.get("nice") {
+                    Some(v) => Some(
+                        v.as_i64()
+                            .and_then(|n| i32::try_from(n).ok())
+                            .filter(|n| NICE_RANGE.contains(n))
+                            .ok_or_else(|| serde::de::Error::custom(format!("nice must be a number from 0 to 19, got {}", v)))?,
+                    ),
+                    None => None,
+                };
+                // a limit of 0 would kill the command before it starts
+                let limit = |key: &str| match obj.get(key) {
+                    Some(v) => v.as_u64().filter(|n| *n > 0).map(Some).ok_or_else(|| {
+                        serde::de::Error::custom(format!("{} must be a whole number above 0, got {}", key, v))
+                    }),
+                    None => Ok(None),
+                };
+                let millis = |key: &str| match obj.get(key) {
+                    Some(v) => v.as_u64().map(Some).ok_or_else(|| {
+                        serde::de::Error::custom(format!("{} must be a whole number, got {}", key, v))
+                    }),
+                    None => Ok(None),
+                };
+
+                Ok(AliasConfig {
+                    entry,
+                    description,
+                    timeout_secs,
+                    suppress_stdout: flag("suppress_stdout"),
+                    suppress_stderr: flag("suppress_stderr"),
+                    append_args: obj.get("append_args").and_then(|v| v.as_bool()).unwrap_or(true),
+                    pipe_to: obj.get("pipe_to").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    condition: obj.get("condition").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    require_files: match obj.get("require_files") {
+                        Some(files) => serde_json::from_value(files.clone()).map_err(serde::de::Error::custom)?,
+                        None => Vec::new(),
+                    },
+                    requires: match obj.get("requires") {
+                        Some(bins) => serde_json::from_value(bins.clone()).map_err(serde::de::Error::custom)?,
+                        None => Vec::new(),
+                    },
+                    detached: flag("detached"),
+                    items: match obj.get("items") {
+                        Some(items) => serde_json::from_value(items.clone()).map_err(serde::de::Error::custom)?,
+                        None => Vec::new(),
+                    },
+                    params: match obj.get("params") {
+                        Some(params) => serde_json::from_value(params.clone()).map_err(serde::de::Error::custom)?,
+                        None => Vec::new(),
+                    },
+                    defaults: match obj.get("defaults") {
+                        Some(defaults) => serde_json::from_value(defaults.clone()).map_err(serde::de::Error::custom)?,
+                        None => HashMap::new(),
+                    },
+                    stagger_ms: obj.get("stagger_ms").and_then(|v| v.as_u64()),
+                    login_shell: flag("login_shell"),
+                    nice,
+                    stdin_file: obj.get("stdin_file").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    pty: flag("pty"),
+                    run_as: obj.get("run_as").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    memory_limit_mb: limit("memory_limit_mb")?,
+                    cpu_seconds: limit("cpu_seconds")?,
+                    confirm: flag("confirm"),
+                    success_pattern: pattern("success_pattern")?,
+                    failure_pattern: pattern("failure_pattern")?,
+                    success_overrides_exit: flag("success_overrides_exit"),
+                    retries: match obj.get("retries") {
+                        Some(v) => v.as_u64().and_then(|n| u32::try_from(n).ok()).ok_or_else(|| {
+                            serde::de::Error::custom(format!("retries must be a whole number, got {}", v))
+                        })?,
+                        None => 0,
+                    },
+                    retry_delay_ms: millis("retry_delay_ms")?,
+                    retry_backoff: match obj.get("retry_backoff") {
+                        Some(v) => serde_json::from_value(v.clone()).map_err(|_| {
+                            serde::de::Error::custom(format!("retry_backoff must be fixed, linear or exponential, got {}", v))
+                        })?,
+                        None => RetryBackoff::Fixed,
+                    },
+                    retry_jitter: flag("retry_jitter"),
+                    retry_max_delay_ms: millis("retry_max_delay_ms")?,
+                    enabled: obj.get("enabled").and_then(|v| v.as_bool()).unwrap_or(true),
+                    hidden: flag("hidden"),
+                    tags: match obj.get("tags") {
+                        Some(tags) => serde_json::from_value(tags.clone()).map_err(serde::de::Error::custom)?,
+                        None => Vec::new(),
+                    },
+                    created_at: obj.get("created_at").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    updated_at: obj.get("updated_at").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    comment: obj.get("_comment").cloned(),
+                    comments: obj.get("_comments").cloned(),
+                })
             }
             _ => Err(serde::de::Error::custom("invalid alias format")),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+// whether cs's own output is colored. auto follows NO_COLOR and whether stdout is a tty
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    Always,
+    Never,
+    Auto,
+}
+
+// how the wait between retries grows: the same every time, by the first wait
+// each time, or doubling
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RetryBackoff {
+    #[default]
+    Fixed,
+    Linear,
+    Exponential,
+}
+
+// how run durations are printed: "1.234s", "1234ms", or "1m 3s"
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TimingFormat {
+    S,
+    Ms,
+    #[default]
+    Human,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Config {
+    // json has no comments, so notes go in "_comment" / "_comments" (string or list).
+    // nothing reads them, they're just kept when the file is rewritten
+    #[serde(rename = "_comment", default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<Value>,
+    #[serde(rename = "_comments", default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub identifier: Option<String>,
     #[serde(default)]
     pub enable_timing: Option<bool>,
-    #[serde(default)]
+    // with timing on, only report runs slower than this (0 or unset = report every run)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_threshold_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_format: Option<TimingFormat>,
+    // cap on output kept in memory or on disk per captured stream, 0 = no cap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_capture_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_min_interval_secs: Option<u64>,
+    // where --notify goes, desktop when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_mode: Option<NotifyMode>,
+    // exit status a command uses to say "nothing to do", 75 when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_exit_code: Option<i32>,
+    // run by a bare `cs`, like make's default target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_alias: Option<String>,
+    // gets a JSON summary POSTed after every run, see webhook.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorMode>,
+    // false swaps the 🐙 in front of cs's own output for a plain "cs:"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<bool>,
+    // env variables whose values --verbose and --dump-resolved print as ***, as
+    // glob patterns. DEFAULT_SECRET_ENV when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_env: Option<Vec<String>>,
+    // what cs clean keeps: this many runs of history, and logs younger than this
+    // (a duration like "7d" or "12h")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_keep: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_max_age: Option<String>,
+    // ask before every run from the tui, not just the aliases marked confirm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tui_confirm: Option<bool>,
+    // what commands are handed to, e.g. "bash -c" (default "sh -c")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    // KEY=VALUE file whose variables are passed to every command, relative to the
+    // config's directory. auto_env loads .env from there when env_file isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_env: Option<bool>,
+    // more config files to merge in, relative to this one. only read when running,
+    // `cs add` and friends edit this file alone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    // values substituted for ${name} in alias commands before they reach the shell
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted")]
+    pub variables: HashMap<String, String>,
+    // shared command fragments, pasted in for {{template:name}} before variables
+    // are expanded
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted")]
+    pub templates: HashMap<String, String>,
+    #[serde(default, serialize_with = "sorted")]
     pub aliases: HashMap<String, AliasConfig>,
+    // which file each alias came from; only filled in by load_merged_config
+    #[serde(skip)]
+    pub sources: HashMap<String, PathBuf>,
+}
+
+// HashMap order changes from run to run. writing the keys sorted keeps the file,
+// and the git diffs of it, stable
+fn sorted<V: Serialize, S: Serializer>(map: &HashMap<String, V>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+impl Config {
+    // None when timing is off, otherwise the threshold a run has to exceed to be reported
+    pub fn timing_threshold(&self) -> Option<u64> {
+        if self.enable_timing.unwrap_or(false) {
+            Some(self.timing_threshold_ms.unwrap_or(0))
+        } else {
+            None
+        }
+    }
+
+    // the value as it may be shown: *** for names matching secret_env
+    pub fn mask_env(&self, name: &str, value: &str) -> String {
+        let secret = match &self.secret_env {
+            Some(patterns) => patterns.iter().any(|p| glob_match(p, name)),
+            None => DEFAULT_SECRET_ENV.iter().any(|p| glob_match(p, name)),
+        };
+        if secret { "***".to_string() } else { value.to_string() }
+    }
+
+    // follow alias_of pointers to the alias that actually runs, returning its name
+    // too. None if `name` isn't defined at all; a dangling pointer or a loop is a
+    // config error
+    pub fn resolve(&self, name: &str) -> Result<Option<(&str, &AliasConfig)>> {
+        let Some((mut key, mut current)) = self.aliases.get_key_value(name) else {
+            return Ok(None);
+        };
+        let mut seen = vec![name];
+        while let AliasEntry::Alias(target) = &current.entry {
+            if seen.contains(&target.as_str()) {
+                seen.push(target);
+                bail!(ConfigError(format!("Alias loop: {}", seen.join(" → "))));
+            }
+            seen.push(target);
+            (key, current) = self.aliases.get_key_value(target).ok_or_else(|| {
+                ConfigError(format!("Alias '{}' points to '{}', which doesn't exist.", key, target))
+            })?;
+        }
+        Ok(Some((key, current)))
+    }
+
+    // aliases with an empty parallel list or a blank command in one (hand-edited
+    // ones, say), in name order. they'd start nothing and report success, so
+    // they're refused instead
+    pub fn empty_parallel(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .aliases
+            .iter()
+            .filter(|(_, ac)| {
+                matches!(&ac.entry, AliasEntry::Parallel(cmds) if cmds.is_empty() || cmds.iter().any(|c| c.trim().is_empty()))
+            })
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+        names
+    }
 }
 
-pub fn load_config() -> Result<Config> {
-    if !Path::new(CONFIG_FILE).exists() {
+// a config file ending in .toml is TOML, anything else JSON
+pub fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+}
+
+// the file's content as the json value it stands for, whichever format it's in
+pub fn parse_raw(path: &Path, content: &str) -> Result<Value> {
+    if is_toml(path) { toml_format::to_json(content) } else { Ok(serde_json::from_str(content)?) }
+}
+
+pub fn parse_config(path: &Path, content: &str) -> Result<Config> {
+    if is_toml(path) {
+        Ok(serde_json::from_value(toml_format::to_json(content)?)?)
+    } else {
+        Ok(serde_json::from_str(content)?)
+    }
+}
+
+// what save_config writes: pretty json, or toml with the keys in the order
+// they're listed in CONFIG_KEYS / ALIAS_KEYS. an existing toml file is updated
+// in place instead, so its comments survive
+pub fn render_config(path: &Path, config: &Config) -> Result<String> {
+    if !is_toml(path) {
+        return Ok(serde_json::to_string_pretty(config)?);
+    }
+    let rank = |at: &[&str], key: &str| {
+        let known = match at {
+            [] => CONFIG_KEYS,
+            ["aliases", _] => ALIAS_KEYS,
+            _ => return 0,
+        };
+        known.iter().position(|k| *k == key).unwrap_or(known.len())
+    };
+    let fresh = toml_format::from_json(&serde_json::to_value(config)?, &rank)?;
+    match fs::read_to_string(path) {
+        Ok(existing) => toml_format::update(&existing, &fresh).or(Ok(fresh)),
+        Err(_) => Ok(fresh),
+    }
+}
+
+pub fn load_config(path: &Path) -> Result<Config> {
+    if !path.exists() {
+        log::debug!("no config at {}, using defaults", path.display());
         return Ok(Config::default());
     }
-    let content = fs::read_to_string(CONFIG_FILE)?;
-    serde_json::from_str(&content).context("Failed to parse config file")
+    log::debug!("loading config {}", path.display());
+    let content = fs::read_to_string(path)
+        .with_context(|| ConfigError(format!("Failed to read config file {}", path.display())))?;
+    let config = parse_config(path, &content)
+        .with_context(|| ConfigError(format!("Failed to parse config file {}", path.display())))?;
+    warn_unknown_once(path, &content);
+    Ok(config)
 }
 
-pub fn save_config(config: &Config) -> Result<()> {
-    let content = serde_json::to_string_pretty(config)?;
-    fs::write(CONFIG_FILE, content).context("Failed to write config file")
+// every key the config and the object form of an alias read. serde skips anything
+// else without a word, so a typo like "timing" would just do nothing
+const CONFIG_KEYS: &[&str] = &[
+    "_comment", "_comments", "identifier", "enable_timing", "timing_threshold_ms", "timing_format",
+    "max_capture_bytes", "notify_min_interval_secs", "notify_mode", "skip_exit_code", "default_alias", "webhook_url", "color", "emoji", "secret_env", "history_keep", "log_max_age", "tui_confirm", "shell", "env_file",
+    "auto_env", "include", "variables", "templates", "aliases",
+];
+const ALIAS_KEYS: &[&str] = &[
+    "run", "parallel", "steps", "alias_of", "description", "timeout_secs", "suppress_stdout", "suppress_stderr",
+    "append_args", "pipe_to", "condition", "require_files", "requires", "detached", "items", "params", "defaults", "stagger_ms",
+    "login_shell", "nice", "stdin_file", "pty", "run_as", "memory_limit_mb", "cpu_seconds", "tags", "confirm", "success_pattern", "failure_pattern",
+    "success_overrides_exit", "retries", "retry_delay_ms", "retry_backoff", "retry_jitter", "retry_max_delay_ms", "enabled", "hidden", "created_at", "updated_at", "_comment",
+    "_comments",
+];
+
+// unknown keys are warnings rather than errors so a config written for a newer cs
+// still loads; --allow-unknown-fields turns them off. each file warns once per run
+static WARN_UNKNOWN: AtomicBool = AtomicBool::new(true);
+static WARNED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+pub fn set_warn_unknown_fields(on: bool) {
+    WARN_UNKNOWN.store(on, Ordering::Relaxed);
+}
+
+// keys nothing reads, as "aliases.build.timing" style paths, each with the known
+// key it was probably meant to be
+pub fn unknown_fields(raw: &Value) -> Vec<(String, Option<&'static str>)> {
+    let mut found = Vec::new();
+    let mut check = |obj: &serde_json::Map<String, Value>, known: &[&'static str], at: &str| {
+        for key in obj.keys().filter(|k| !known.contains(&k.as_str())) {
+            found.push((format!("{}{}", at, key), closest_key(key, known)));
+        }
+    };
+    let Some(top) = raw.as_object() else {
+        return found;
+    };
+    check(top, CONFIG_KEYS, "");
+    if let Some(aliases) = top.get("aliases").and_then(|a| a.as_object()) {
+        let mut names: Vec<&String> = aliases.keys().collect();
+        names.sort();
+        for name in names {
+            if let Some(obj) = aliases[name].as_object() {
+                check(obj, ALIAS_KEYS, &format!("aliases.{}.", name));
+            }
+        }
+    }
+    found
+}
+
+// a known key that contains the typo ("timing" → "enable_timing") or is a couple
+// of edits away from it
+fn closest_key(key: &str, known: &[&'static str]) -> Option<&'static str> {
+    let key = key.to_lowercase();
+    known
+        .iter()
+        .filter(|k| !k.starts_with('_'))
+        .map(|k| (if k.contains(key.as_str()) { 0 } else { edit_distance(&key, k) }, *k))
+        .filter(|&(d, _)| d <= 2)
+        .min_by_key(|&(d, _)| d)
+        .map(|(_, k)| k)
+}
+
+// the same file is loaded several times in one run, it only needs saying once
+fn warn_unknown_once(path: &Path, content: &str) {
+    if let Ok(mut warned) = WARNED.lock() {
+        if warned.iter().any(|p| p == path) {
+            return;
+        }
+        warned.push(path.to_path_buf());
+    }
+    warn_unknown_fields(path, content);
+}
+
+pub fn warn_unknown_fields(path: &Path, content: &str) {
+    if !WARN_UNKNOWN.load(Ordering::Relaxed) {
+        return;
+    }
+    let Ok(raw) = parse_raw(path, content) else {
+        return;
+    };
+    for (field, suggestion) in unknown_fields(&raw) {
+        let hint = suggestion.map(|s| format!(" (did you mean \"{}\"?)", s)).unwrap_or_default();
+        eprintln!("{} {} {}: unknown field \"{}\"{}, it's ignored", prefix(), "⚠".yellow(), path.display(), field, hint);
+    }
+}
+// edit distance where swapping two neighbouring letters counts as one typo
+// (optimal string alignment), so "biuld" is as close to "build" as "buld" is
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// alias names that look like shell globs: *, ? or a [...] class
+pub fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+// shell-style matching of a whole name: * is any run of characters, ? any one,
+// [abc] / [a-z] one of those and [!abc] anything else
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // where to resume after the last *: pattern index past it, name index it's up to
+    let (mut pi, mut ni, mut star) = (0, 0, None);
+    while ni < n.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi + 1, ni));
+                pi += 1;
+                continue;
+            }
+            Some('?') => {
+                pi += 1;
+                ni += 1;
+                continue;
+            }
+            Some('[') => match class_match(&p[pi..], n[ni]) {
+                Some((true, len)) => {
+                    pi += len;
+                    ni += 1;
+                    continue;
+                }
+                Some((false, _)) => {}
+                // unclosed, so a plain [
+                None if n[ni] == '[' => {
+                    pi += 1;
+                    ni += 1;
+                    continue;
+                }
+                None => {}
+            },
+            Some(&c) if c == n[ni] => {
+                pi += 1;
+                ni += 1;
+                continue;
+            }
+            _ => {}
+        }
+        // mismatch: let the last * swallow one more character, or give up
+        match star {
+            Some((after, taken)) => {
+                pi = after;
+                ni = taken + 1;
+                star = Some((after, taken + 1));
+            }
+            None => return false,
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+// a [...] class at the start of p: whether c is in it and how long the class is.
+// None when there's no closing ], then the [ is just a character
+fn class_match(p: &[char], c: char) -> Option<(bool, usize)> {
+    let negated = matches!(p.get(1), Some('!') | Some('^'));
+    let start = if negated { 2 } else { 1 };
+    // a ] right at the start is part of the class, not its end
+    let end = start + 1 + p.get(start + 1..)?.iter().position(|&ch| ch == ']')?;
+    let set = &p[start..end];
+    let mut found = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            found |= (set[i]..=set[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= set[i] == c;
+            i += 1;
+        }
+    }
+    Some((found != negated, end + 1))
+}
+
+
+// whether the file at path already holds this config. compared as parsed values,
+// so key order and formatting on disk don't count as a difference
+fn unchanged_on_disk(config: &Config, path: &Path) -> bool {
+    let Some(on_disk) = fs::read_to_string(path).ok().and_then(|s| parse_config(path, &s).ok()) else {
+        return false;
+    };
+    matches!((serde_json::to_value(&on_disk), serde_json::to_value(config)), (Ok(a), Ok(b)) if a == b)
+}
+
+// returns false when nothing had to be written. skipping no-op writes keeps the
+// mtime still, so file watchers and git don't see churn
+pub fn save_config(config: &Config, path: &Path) -> Result<bool> {
+    if unchanged_on_disk(config, path) {
+        log::debug!("{} unchanged, not rewriting it", path.display());
+        return Ok(false);
+    }
+    let content = render_config(path, config)?;
+    fs::write(path, content).context("Failed to write config file")?;
+    forget_merged_config();
+    Ok(true)
 }
 
 pub fn load_global_config() -> Result<Config> {
     let path = global_config_path();
     if !path.exists() {
+        log::debug!("no global config at {}", path.display());
         return Ok(Config::default());
     }
-    let content = fs::read_to_string(&path)?;
-    serde_json::from_str(&content).context("Failed to parse global config file")
+    log::debug!("loading global config {}", path.display());
+    let content = fs::read_to_string(&path)
+        .with_context(|| ConfigError(format!("Failed to read global config file {}", path.display())))?;
+    let config = serde_json::from_str(&content).context(ConfigError("Failed to parse global config file".to_string()))?;
+    warn_unknown_once(&path, &content);
+    Ok(config)
 }
 
-pub fn save_global_config(config: &Config) -> Result<()> {
+pub fn save_global_config(config: &Config) -> Result<bool> {
     let path = global_config_path();
+    if unchanged_on_disk(config, &path) {
+        log::debug!("{} unchanged, not rewriting it", path.display());
+        return Ok(false);
+    }
     // create ~/.config/cawa/ if it doesn't exist yet
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
     let content = serde_json::to_string_pretty(config)?;
-    fs::write(&path, content).context("Failed to write global config file")
+    fs::write(&path, content).context("Failed to write global config file")?;
+    forget_merged_config();
+    Ok(true)
 }
 
-pub fn load_merged_config() -> Result<Config> {
+// how long a change waits for another cs that's changing the same config
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// held by the commands that read a config, change it and write it back, so two
+// of them at once can't drop each other's change. take it before loading the
+// config; it's let go when dropped, or by the OS if cs dies
+pub struct ConfigLock {
+    _file: File,
+}
+
+// the lock is a file of its own next to the config (.cawa_cfg.lock), shared by
+// the json and toml configs and left in place afterwards
+pub fn lock_config(path: &Path) -> Result<ConfigLock> {
+    let lock_path = path.with_extension("lock");
+    if let Some(parent) = lock_path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open lock file {}", lock_path.display()))?;
+    let start = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(ConfigLock { _file: file }),
+            Err(TryLockError::WouldBlock) if start.elapsed() < LOCK_TIMEOUT => {
+                log::debug!("{} is locked, waiting", lock_path.display());
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(TryLockError::WouldBlock) => bail!(
+                "Another {} has been changing {} for over {}s, giving up",
+                crate::output::get_program_name(),
+                path.display(),
+                LOCK_TIMEOUT.as_secs()
+            ),
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", lock_path.display()));
+            }
+        }
+    }
+}
+
+// the merged config is read once per invocation: everything that needs it after
+// that gets the same snapshot, even if the files change while an alias runs.
+// writes made by cs itself drop it so they're seen right away
+static MERGED: Mutex<Option<(PathBuf, Config)>> = Mutex::new(None);
+
+pub fn load_merged_config(path: &Path) -> Result<Config> {
+    if let Ok(cached) = MERGED.lock()
+        && let Some((cached_path, config)) = cached.as_ref()
+        && cached_path == path
+    {
+        return Ok(config.clone());
+    }
+    reload_merged_config(path)
+}
+
+// read the files again no matter what's cached, for things that are meant to
+// follow edits (the tui)
+pub fn reload_merged_config(path: &Path) -> Result<Config> {
+    let merged = read_merged_config(path)?;
+    if let Ok(mut cached) = MERGED.lock() {
+        *cached = Some((path.to_path_buf(), merged.clone()));
+    }
+    Ok(merged)
+}
+
+pub fn forget_merged_config() {
+    if let Ok(mut cached) = MERGED.lock() {
+        *cached = None;
+    }
+}
+
+fn read_merged_config(path: &Path) -> Result<Config> {
     // start with global aliases, then overlay local ones so local always wins
-    let mut merged = load_global_config().unwrap_or_default();
-    let local = load_config()?;
-    for (k, v) in local.aliases {
-        merged.aliases.insert(k, v);
+    let global_path = global_config_path();
+    let mut merged = load_with_includes(&global_path, &mut Vec::new()).unwrap_or_else(|e| {
+        log::warn!("ignoring global config: {:#}", e);
+        Config::default()
+    });
+    let local = load_with_includes(path, &mut Vec::new())?;
+    overlay(&mut merged, local);
+    Ok(merged)
+}
+
+// a config file with the files it includes merged in. each include is loaded
+// (with its own includes) in order, later ones winning, and the file itself
+// goes on top. `stack` is the chain of files being loaded, to catch loops
+fn load_with_includes(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Config> {
+    let mut config = load_config(path)?;
+    config.sources = config.aliases.keys().map(|k| (k.clone(), path.to_path_buf())).collect();
+    if config.include.is_empty() {
+        return Ok(config);
     }
-    if local.identifier.is_some() {
-        merged.identifier = local.identifier;
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&key) {
+        let chain: Vec<String> = stack.iter().chain([&key]).map(|p| p.display().to_string()).collect();
+        bail!(ConfigError(format!("Include loop: {}", chain.join(" → "))));
     }
-    if local.enable_timing.is_some() {
-        merged.enable_timing = local.enable_timing;
+    stack.push(key);
+    let mut merged = Config::default();
+    for include in &config.include {
+        // relative to the file that includes it, not wherever cs was started
+        let include_path = path.parent().unwrap_or(Path::new("")).join(expand_path(include));
+        if !include_path.exists() {
+            bail!(ConfigError(format!(
+                "{} includes {}, which doesn't exist",
+                path.display(),
+                include_path.display()
+            )));
+        }
+        log::debug!("{} includes {}", path.display(), include_path.display());
+        let included = load_with_includes(&include_path, stack)?;
+        overlay(&mut merged, included);
     }
+    stack.pop();
+    overlay(&mut merged, config);
     Ok(merged)
 }
 
+// put `top` over `merged`: its aliases and variables replace ones with the same
+// name, and any setting it has replaces merged's
+fn overlay(merged: &mut Config, top: Config) {
+    for (k, v) in top.aliases {
+        if let Some(old) = merged.sources.get(&k)
+            && let Some(new) = top.sources.get(&k)
+        {
+            log::debug!("alias '{}' from {} overrides the one from {}", k, new.display(), old.display());
+        }
+        if let Some(source) = top.sources.get(&k) {
+            merged.sources.insert(k.clone(), source.clone());
+        }
+        merged.aliases.insert(k, v);
+    }
+    for (k, v) in top.variables {
+        merged.variables.insert(k, v);
+    }
+    for (k, v) in top.templates {
+        merged.templates.insert(k, v);
+    }
+    if top.identifier.is_some() {
+        merged.identifier = top.identifier;
+    }
+    if top.enable_timing.is_some() {
+        merged.enable_timing = top.enable_timing;
+    }
+    if top.timing_threshold_ms.is_some() {
+        merged.timing_threshold_ms = top.timing_threshold_ms;
+    }
+    if top.timing_format.is_some() {
+        merged.timing_format = top.timing_format;
+    }
+    if top.max_capture_bytes.is_some() {
+        merged.max_capture_bytes = top.max_capture_bytes;
+    }
+    if top.notify_min_interval_secs.is_some() {
+        merged.notify_min_interval_secs = top.notify_min_interval_secs;
+    }
+    if top.notify_mode.is_some() {
+        merged.notify_mode = top.notify_mode;
+    }
+    if top.skip_exit_code.is_some() {
+        merged.skip_exit_code = top.skip_exit_code;
+    }
+    if top.default_alias.is_some() {
+        merged.default_alias = top.default_alias;
+    }
+    if top.webhook_url.is_some() {
+        merged.webhook_url = top.webhook_url;
+    }
+    if top.shell.is_some() {
+        merged.shell = top.shell;
+    }
+    if top.color.is_some() {
+        merged.color = top.color;
+    }
+    if top.emoji.is_some() {
+        merged.emoji = top.emoji;
+    }
+    if top.secret_env.is_some() {
+        merged.secret_env = top.secret_env;
+    }
+    if top.history_keep.is_some() {
+        merged.history_keep = top.history_keep;
+    }
+    if top.log_max_age.is_some() {
+        merged.log_max_age = top.log_max_age;
+    }
+    if top.tui_confirm.is_some() {
+        merged.tui_confirm = top.tui_confirm;
+    }
+    if top.env_file.is_some() {
+        merged.env_file = top.env_file;
+    }
+    if top.auto_env.is_some() {
+        merged.auto_env = top.auto_env;
+    }
+}
+
+// KEY=VALUE lines from a .env file. blank lines and #comments are skipped and an
+// `export ` prefix is allowed. '...' values are taken literally, "..." ones
+// understand \n, \" and \\, and unquoted values end at a ` #` comment
+pub fn parse_env_file(content: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    for (n, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let bad = |why: &str| ConfigError(format!("line {}: {}", n + 1, why));
+        let Some((key, raw)) = line.split_once('=') else {
+            bail!(bad("expected KEY=VALUE"));
+        };
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            bail!(bad(&format!("'{}' isn't a valid variable name", key)));
+        }
+
+        let raw = raw.trim();
+        let value = if let Some(rest) = raw.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = rest.chars();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some(c @ ('"' | '\\')) => value.push(c),
+                        Some(c) => {
+                            value.push('\\');
+                            value.push(c);
+                        }
+                        None => bail!(bad("unterminated \" quote")),
+                    },
+                    Some(c) => value.push(c),
+                    None => bail!(bad("unterminated \" quote")),
+                }
+            }
+            value
+        } else if let Some(rest) = raw.strip_prefix('\'') {
+            match rest.split_once('\'') {
+                Some((value, _)) => value.to_string(),
+                None => bail!(bad("unterminated ' quote")),
+            }
+        } else {
+            raw.split(" #").next().unwrap_or_default().trim_end().to_string()
+        };
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
+// expand ${name} references using the config variables. values may reference other
+// variables. names that aren't config variables but are set in the environment are left
+// for the shell, anything else is an error so typos don't silently run the wrong thing.
+pub fn expand_variables(input: &str, vars: &HashMap<String, String>) -> Result<String> {
+    expand_with_stack(input, vars, &mut Vec::new())
+}
+
+fn expand_with_stack(input: &str, vars: &HashMap<String, String>, stack: &mut Vec<String>) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            // unterminated, nothing more to expand
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+        let name = &after[..end];
+
+        // only plain names are ours; ${VAR:-default} and friends belong to the shell
+        let is_plain = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_plain {
+            out.push_str(&rest[start..start + 2 + end + 1]);
+        } else if let Some(value) = vars.get(name) {
+            if stack.iter().any(|n| n == name) {
+                bail!(ConfigError(format!("Variable cycle detected: {} -> {}", stack.join(" -> "), name)));
+            }
+            stack.push(name.to_string());
+            out.push_str(&expand_with_stack(value, vars, stack)?);
+            stack.pop();
+        } else if crate::runner::env_is_set(name) {
+            out.push_str(&rest[start..start + 2 + end + 1]);
+        } else {
+            bail!(ConfigError(format!("Unknown variable '{}'. Define it with `cs set-var {} <value>`.", name, name)));
+        }
+        rest = &after[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+// paste in {{template:name}} references. templates may use other templates, and
+// an unknown name is an error like an unknown variable
+pub fn expand_templates(input: &str, templates: &HashMap<String, String>) -> Result<String> {
+    expand_templates_with_stack(input, templates, &mut Vec::new())
+}
+
+fn expand_templates_with_stack(input: &str, templates: &HashMap<String, String>, stack: &mut Vec<String>) -> Result<String> {
+    const OPEN: &str = "{{template:";
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+        let name = after[..end].trim();
+        let Some(body) = templates.get(name) else {
+            bail!(ConfigError(format!(
+                "Unknown template '{}'. Define it with `cs template add {} <command>`.",
+                name, name
+            )));
+        };
+        if stack.iter().any(|n| n == name) {
+            bail!(ConfigError(format!("Template cycle detected: {} -> {}", stack.join(" -> "), name)));
+        }
+        stack.push(name.to_string());
+        out.push_str(&expand_templates_with_stack(body, templates, stack)?);
+        stack.pop();
+        rest = &after[end + 2..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+// a command written as `$env:NAME` is read from that environment variable at run
+// time, so it never has to be stored in the config. the value is used as-is
+fn expand_command(cmd: &str, config: &Config) -> Result<String> {
+    let Some(name) = cmd.trim().strip_prefix("$env:") else {
+        return expand_variables(&expand_templates(cmd, &config.templates)?, &config.variables);
+    };
+    std::env::var(name).map_err(|_| {
+        ConfigError(format!("The command comes from ${}, which isn't set.", name)).into()
+    })
+}
+
+// templates first, then variables, so a template can use ${name} too
+pub fn expand_entry(entry: &AliasEntry, config: &Config) -> Result<AliasEntry> {
+    Ok(match entry {
+        AliasEntry::Single(cmd) => AliasEntry::Single(expand_command(cmd, config)?),
+        AliasEntry::Parallel(cmds) => AliasEntry::Parallel(
+            cmds.iter().map(|c| expand_command(c, config)).collect::<Result<_>>()?,
+        ),
+        AliasEntry::Alias(target) => AliasEntry::Alias(target.clone()),
+        AliasEntry::Steps(steps) => AliasEntry::Steps(
+            steps.iter().map(|s| Ok(s.with(expand_command(&s.command, config)?))).collect::<Result<_>>()?,
+        ),
+    })
+}
+
+// turn {item} templates into one concrete command per item. commands without the
+// placeholder are kept once, and a templated single command becomes a parallel one
+pub fn expand_items(entry: &AliasEntry, items: &[String]) -> AliasEntry {
+    if items.is_empty() {
+        return entry.clone();
+    }
+    let expand = |cmd: &String| -> Vec<String> {
+        if cmd.contains("{item}") {
+            items.iter().map(|item| cmd.replace("{item}", item)).collect()
+        } else {
+            vec![cmd.clone()]
+        }
+    };
+    match entry {
+        AliasEntry::Single(cmd) if cmd.contains("{item}") => AliasEntry::Parallel(expand(cmd)),
+        AliasEntry::Parallel(cmds) => AliasEntry::Parallel(cmds.iter().flat_map(expand).collect()),
+        other => other.clone(),
+    }
+}
+
+// filled in by cs itself at run time, so they're never parameters
+pub const BUILTIN_TOKENS: [&str; 5] = ["cwd", "date", "time", "alias", "host"];
+
+// the value of a built-in token, unquoted. date and time are UTC
+fn builtin_token(name: &str, alias: &str) -> Option<String> {
+    match name {
+        "cwd" => std::env::current_dir().ok().map(|d| d.display().to_string()),
+        "date" => rfc3339_now().split_once('T').map(|(date, _)| date.to_string()),
+        "time" => rfc3339_now().split_once('T').map(|(_, time)| time.trim_end_matches('Z').to_string()),
+        "alias" => Some(alias.to_string()),
+        "host" => Some(crate::runner::hostname()),
+        _ => None,
+    }
+}
+
+// every {name} that isn't ${name}
+pub fn scan_braces(cmd: &str, mut fill: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(cmd.len());
+    let mut rest = cmd;
+    while let Some(start) = rest.find('{') {
+        let (before, after) = rest.split_at(start);
+        out.push_str(before);
+        let name = after[1..].split_once('}').map(|(n, _)| n).unwrap_or_default();
+        let is_param = !before.ends_with('$')
+            && name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        match if is_param { fill(name) } else { None } {
+            Some(value) => {
+                out.push_str(&value);
+                rest = &after[name.len() + 2..];
+            }
+            None => {
+                out.push('{');
+                rest = &after[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// the named parameters an alias takes, in the order they first appear in `entry`
+// (the alias's own, or what it expands to). only names the alias declares in
+// params or defaults count; {item} and the built-in tokens never do
+pub fn param_names(ac: &AliasConfig, entry: &AliasEntry) -> Vec<String> {
+    let declared = |name: &str| {
+        name != "item"
+            && !BUILTIN_TOKENS.contains(&name)
+            && (ac.params.iter().any(|p| p == name) || ac.defaults.contains_key(name))
+    };
+    let cmds: Vec<&String> = match entry {
+        AliasEntry::Single(cmd) => vec![cmd],
+        AliasEntry::Parallel(cmds) => cmds.iter().collect(),
+        AliasEntry::Steps(steps) => steps.iter().map(|s| &s.command).collect(),
+        AliasEntry::Alias(_) => Vec::new(),
+    };
+    let mut names: Vec<String> = Vec::new();
+    for cmd in cmds {
+        scan_braces(cmd, |name| {
+            if declared(name) && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+            // only collecting, leave the command alone
+            None
+        });
+    }
+    names
+}
+
+// substitute {name} placeholders and the built-in tokens with their (shell-quoted)
+// values. values only holds declared parameters, so other braces stay as they
+// are. alias is the name the user typed, for {alias}
+pub fn fill_params(entry: &AliasEntry, values: &HashMap<String, String>, alias: &str) -> AliasEntry {
+    let fill = |cmd: &String| {
+        scan_braces(cmd, |name| match name {
+            "item" => None,
+            _ if BUILTIN_TOKENS.contains(&name) => builtin_token(name, alias).map(|v| shell_quote(&v)),
+            _ => values.get(name).map(|v| shell_quote(v)),
+        })
+    };
+    match entry {
+        AliasEntry::Single(cmd) => AliasEntry::Single(fill(cmd)),
+        AliasEntry::Parallel(cmds) => AliasEntry::Parallel(cmds.iter().map(fill).collect()),
+        AliasEntry::Steps(steps) => AliasEntry::Steps(steps.iter().map(|s| s.with(fill(&s.command))).collect()),
+        AliasEntry::Alias(target) => AliasEntry::Alias(target.clone()),
+    }
+}
+
+// single-quote a value for sh unless it's plainly safe as it is
+pub fn shell_quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c)) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
 // last-run timestamps live in a separate file so they don't pollute the committed config
 pub fn load_state() -> HashMap<String, u64> {
     fs::read_to_string(STATE_FILE)
@@ -166,6 +1549,92 @@ pub fn save_state(state: &HashMap<String, u64>) -> Result<()> {
     fs::write(STATE_FILE, content).context("Failed to write state file")
 }
 
+#[derive(Serialize, Deserialize, Default)]
+pub struct NotifyState {
+    pub last_sent: u64,
+    // failures swallowed by the rate limit, reported together with the next notification
+    pub suppressed_failures: u32,
+}
+
+pub fn load_notify_state() -> NotifyState {
+    fs::read_to_string(NOTIFY_STATE_FILE)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_notify_state(state: &NotifyState) -> Result<()> {
+    let content = serde_json::to_string_pretty(state)?;
+    fs::write(NOTIFY_STATE_FILE, content).context("Failed to write notification state file")
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct TuiState {
+    pub selected: Option<String>,
+    // position of the selection, used when that alias is gone by next time
+    #[serde(default)]
+    pub index: usize,
+    #[serde(default)]
+    pub sort: SortOrder,
+}
+
+pub fn load_tui_state() -> TuiState {
+    fs::read_to_string(TUI_STATE_FILE)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_tui_state(state: &TuiState) -> Result<()> {
+    let content = serde_json::to_string_pretty(state)?;
+    fs::write(TUI_STATE_FILE, content).context("Failed to write tui state file")
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LastRun {
+    pub alias: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+pub fn load_last_run() -> Option<LastRun> {
+    fs::read_to_string(LAST_RUN_FILE)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+pub fn save_last_run(last: &LastRun) -> Result<()> {
+    let content = serde_json::to_string_pretty(last)?;
+    fs::write(LAST_RUN_FILE, content).context("Failed to write last run file")
+}
+
+// a background process started with --detach, so cs ps / cs stop can find it later
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DetachedProcess {
+    pub alias: String,
+    pub pid: u32,
+    pub started: u64,
+    pub command: String,
+    pub log: PathBuf,
+}
+
+pub fn load_detached() -> Vec<DetachedProcess> {
+    fs::read_to_string(DETACHED_FILE)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_detached(procs: &[DetachedProcess]) -> Result<()> {
+    let content = serde_json::to_string_pretty(procs)?;
+    fs::write(DETACHED_FILE, content).context("Failed to write detached process file")
+}
+
+// for created_at / updated_at, e.g. 2024-05-01T12:00:00Z
+pub fn rfc3339_now() -> String {
+    humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+}
+
 pub fn unix_now() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
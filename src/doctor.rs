@@ -0,0 +1,138 @@
+use anyhow::Result;
+use colored::*;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+use crate::config::{global_config_path, load_config, load_global_config, load_merged_config};
+use crate::exit_codes;
+use crate::output::prefix;
+use crate::runner::{find_on_path, missing_programs, shell_argv};
+
+enum Check {
+    Pass,
+    Warn(String),
+    Fail(String),
+}
+
+fn report(label: &str, check: &Check) {
+    match check {
+        Check::Pass => println!("  {} {}", "✔".green(), label),
+        Check::Warn(why) => println!("  {} {} {}", "!".yellow(), label, format!("— {}", why).dimmed()),
+        Check::Fail(why) => println!("  {} {} {}", "✘".red(), label, format!("— {}", why).red()),
+    }
+}
+
+fn check_config(path: &Path, load: impl Fn() -> Result<crate::config::Config>) -> Check {
+    if !path.exists() {
+        return Check::Warn(format!("{} doesn't exist yet", path.display()));
+    }
+    match load() {
+        Ok(_) => Check::Pass,
+        Err(e) => Check::Fail(format!("{:#}", e)),
+    }
+}
+
+// can we write the config file (or create it, if it isn't there yet)?
+fn check_writable(path: &Path) -> Check {
+    if path.exists() {
+        return match OpenOptions::new().append(true).open(path) {
+            Ok(_) => Check::Pass,
+            Err(e) => Check::Fail(format!("can't write {}: {}", path.display(), e)),
+        };
+    }
+
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if !dir.exists() {
+        // save_global_config creates the directory on first use
+        return Check::Warn(format!("{} will be created on first save", dir.display()));
+    }
+    let probe = dir.join(".cawa_doctor_probe");
+    match fs::write(&probe, b"") {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Check::Pass
+        }
+        Err(e) => Check::Fail(format!("can't create files in {}: {}", dir.display(), e)),
+    }
+}
+
+fn check_shell(program: &str) -> Check {
+    match find_on_path(program) {
+        Some(_) => Check::Pass,
+        None => Check::Fail(format!("`{}` not found on PATH; aliases can't run. Set \"shell\" in the config", program)),
+    }
+}
+
+// notifications are nice-to-have, so problems here are only warnings
+fn check_notifications() -> Check {
+    if cfg!(target_os = "macos") {
+        match find_on_path("osascript") {
+            Some(_) => Check::Pass,
+            None => Check::Warn("osascript not found; --notify won't show anything".to_string()),
+        }
+    } else if cfg!(target_os = "linux") {
+        if std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some() {
+            Check::Pass
+        } else {
+            Check::Warn("no D-Bus session bus; --notify needs a desktop session".to_string())
+        }
+    } else {
+        Check::Pass
+    }
+}
+
+// `env` is how loading the env file and --env went, which only happens when an
+// alias runs
+pub fn run_doctor(config_path: &Path, env: Result<()>) -> Result<i32> {
+    println!("{} Checking your environment", prefix());
+
+    let global_path = global_config_path();
+    let shell = shell_argv();
+    let mut checks = vec![
+        (format!("config {} parses", config_path.display()), check_config(config_path, || load_config(config_path))),
+        (format!("config {} is writable", config_path.display()), check_writable(config_path)),
+        (format!("global config {} parses", global_path.display()), check_config(&global_path, load_global_config)),
+        (format!("global config {} is writable", global_path.display()), check_writable(&global_path)),
+        (format!("shell `{}` is available", shell.join(" ")), check_shell(&shell[0])),
+        ("desktop notifications".to_string(), check_notifications()),
+        (
+            "env file loads".to_string(),
+            match env {
+                Ok(()) => Check::Pass,
+                Err(e) => Check::Fail(format!("{:#}", e)),
+            },
+        ),
+    ];
+
+    // every alias with a `requires` list gets its programs looked up
+    if let Ok(config) = load_merged_config(config_path) {
+        let mut names: Vec<&String> = config.aliases.iter().filter(|(_, ac)| !ac.requires.is_empty()).map(|(n, _)| n).collect();
+        names.sort();
+        for name in names {
+            let missing = missing_programs(&config.aliases[name].requires);
+            let check = if missing.is_empty() {
+                Check::Pass
+            } else {
+                Check::Fail(format!("not on PATH: {}", missing.join(", ")))
+            };
+            checks.push((format!("programs required by '{}'", name), check));
+        }
+    }
+
+    let mut failed = false;
+    for (label, check) in &checks {
+        report(label, check);
+        failed |= matches!(check, Check::Fail(_));
+    }
+
+    if failed {
+        println!("{} Some checks failed.", prefix());
+        Ok(exit_codes::FAILURE)
+    } else {
+        println!("{} All good.", prefix());
+        Ok(exit_codes::SUCCESS)
+    }
+}
@@ -0,0 +1,33 @@
+use serde::Serialize;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// --output json: progress as newline-delimited JSON on stdout, one object per
+// event, for editors and other tools to consume. alias is null for `cs run`
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    RunStart { alias: Option<&'a str>, commands: &'a [String] },
+    CommandStart { alias: Option<&'a str>, index: usize, command: &'a str },
+    OutputLine { alias: Option<&'a str>, index: usize, stream: &'a str, line: &'a str },
+    CommandEnd { alias: Option<&'a str>, index: usize, exit_code: i32, duration_ms: u64 },
+    RunEnd { alias: Option<&'a str>, exit_code: i32, duration_ms: u64, timed_out: bool, skipped: bool },
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+    // unix time in milliseconds
+    timestamp: u64,
+}
+
+pub fn emit(event: &Event) {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    if let Ok(line) = serde_json::to_string(&Envelope { event, timestamp }) {
+        // one write per event so lines from parallel commands never interleave
+        let mut out = io::stdout().lock();
+        let _ = writeln!(out, "{}", line);
+        let _ = out.flush();
+    }
+}
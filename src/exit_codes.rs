@@ -0,0 +1,54 @@
+use std::fmt;
+
+// documented process exit codes so scripts can tell failure modes apart
+pub const SUCCESS: i32 = 0;
+// the alias ran and failed. when the command's own exit code is known we pass that
+// through instead, so this is only the fallback
+pub const FAILURE: i32 = 1;
+pub const CONFIG_ERROR: i32 = 2;
+pub const UNKNOWN_ALIAS: i32 = 3;
+pub const USAGE_ERROR: i32 = 4;
+// the command said there was nothing to do by exiting with the config's
+// skip_exit_code (this one, EX_TEMPFAIL, by default). reported as skipped and
+// never counted as a failure
+pub const SKIPPED: i32 = 75;
+// the alias hit its timeout_secs and was killed, same code GNU timeout uses
+pub const TIMED_OUT: i32 = 124;
+pub const INTERRUPTED: i32 = 130;
+
+// the config couldn't be read, parsed, or resolved
+#[derive(Debug)]
+pub struct ConfigError(pub String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// bad arguments or input that failed validation
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+// pick the exit code for an error that made it all the way up to main
+pub fn for_error(err: &anyhow::Error) -> i32 {
+    // downcast_ref sees the marker when it was attached with .context(),
+    // the chain walk finds it when it's the root cause further down
+    if err.downcast_ref::<ConfigError>().is_some() || err.chain().any(|e| e.is::<ConfigError>()) {
+        CONFIG_ERROR
+    } else if err.downcast_ref::<UsageError>().is_some() || err.chain().any(|e| e.is::<UsageError>()) {
+        USAGE_ERROR
+    } else {
+        FAILURE
+    }
+}
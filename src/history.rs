@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use crate::cli::SortOrder;
+
+// one line per run, appended so we never have to rewrite the whole file
+const HISTORY_FILE: &str = ".cawa_history.jsonl";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryRecord {
+    pub alias: String,
+    pub timestamp: u64,
+    pub success: bool,
+    pub duration_ms: u64,
+    // killed by its timeout (success is false too)
+    #[serde(default)]
+    pub timed_out: bool,
+    // the command exited with skip_exit_code (success is true, it didn't fail)
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+pub fn append_history(record: &HistoryRecord) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(HISTORY_FILE)
+        .context("Failed to open history file")?;
+    writeln!(file, "{}", serde_json::to_string(record)?).context("Failed to write history file")
+}
+
+// unreadable lines are skipped so one bad write doesn't hide the rest
+pub fn load_history() -> Vec<HistoryRecord> {
+    fs::read_to_string(HISTORY_FILE)
+        .map(|s| s.lines().filter_map(|l| serde_json::from_str(l).ok()).collect())
+        .unwrap_or_default()
+}
+
+// cs clean: keep only the newest `keep` runs, returning how many lines went and
+// how many there were. rewritten through a temp file so a crash halfway can't
+// lose the whole log
+pub fn trim_history(keep: usize, dry_run: bool) -> Result<(usize, usize)> {
+    let Ok(content) = fs::read_to_string(HISTORY_FILE) else {
+        return Ok((0, 0));
+    };
+    let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+    let removed = lines.len().saturating_sub(keep);
+    if removed > 0 && !dry_run {
+        let kept: String = lines[removed..].iter().map(|l| format!("{}\n", l)).collect();
+        let tmp = format!("{}.tmp", HISTORY_FILE);
+        fs::write(&tmp, kept).context("Failed to write history file")?;
+        fs::rename(&tmp, HISTORY_FILE).context("Failed to write history file")?;
+    }
+    Ok((removed, lines.len()))
+}
+
+// per-alias numbers derived from the history log
+#[derive(Default)]
+pub struct UsageStats {
+    pub last_run: HashMap<String, u64>,
+    pub runs: HashMap<String, u32>,
+    // whether the most recent run succeeded
+    pub last_success: HashMap<String, bool>,
+    // failed runs (skips don't count) and time spent across all runs
+    pub failures: HashMap<String, u32>,
+    pub total_ms: HashMap<String, u64>,
+}
+
+impl UsageStats {
+    pub fn from_history(history: &[HistoryRecord]) -> UsageStats {
+        let mut stats = UsageStats::default();
+        for rec in history {
+            let last = stats.last_run.entry(rec.alias.clone()).or_insert(0);
+            // >= so the later line wins when two runs land in the same second
+            if rec.timestamp >= *last {
+                *last = rec.timestamp;
+                stats.last_success.insert(rec.alias.clone(), rec.success);
+            }
+            *stats.runs.entry(rec.alias.clone()).or_insert(0) += 1;
+            if !rec.success {
+                *stats.failures.entry(rec.alias.clone()).or_insert(0) += 1;
+            }
+            *stats.total_ms.entry(rec.alias.clone()).or_insert(0) += rec.duration_ms;
+        }
+        stats
+    }
+
+    // sort names in place. callers pass them in name order so ties stay alphabetical,
+    // and aliases with no history sink to the bottom for recent/usage
+    pub fn sort_names<T>(&self, items: &mut [T], order: SortOrder, name: impl Fn(&T) -> &str) {
+        match order {
+            SortOrder::Name => items.sort_by(|a, b| name(a).cmp(name(b))),
+            SortOrder::Recent => items.sort_by_key(|i| std::cmp::Reverse(self.last_run.get(name(i)).copied().unwrap_or(0))),
+            SortOrder::Usage => items.sort_by_key(|i| std::cmp::Reverse(self.runs.get(name(i)).copied().unwrap_or(0))),
+        }
+    }
+}
@@ -1,56 +1,177 @@
 mod cli;
 mod config;
+mod doctor;
+mod events;
+mod exit_codes;
+mod history;
+mod makefile;
+mod manpage;
 mod notifications;
+mod output;
+mod resolve;
 mod runner;
+mod toml_format;
 mod tui;
+mod watch;
+mod webhook;
 mod wizard;
 
-use anyhow::Result;
-use clap::{CommandFactory, Parser};
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, ValueEnum};
 use colored::*;
-use std::path::Path;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::cli::{Cli, Commands};
-use crate::config::{AliasConfig, AliasEntry, load_config, load_global_config, load_merged_config, load_state, save_config, save_global_config, save_state, unix_now};
-use crate::runner::execute_command;
-
-fn get_program_name() -> String {
-    std::env::args()
-        .next()
-        .and_then(|s| {
-            Path::new(&s)
-                .file_name()
-                .map(|s| s.to_string_lossy().into_owned())
-        })
-        .unwrap_or_else(|| "cs".to_string())
+use crate::cli::{Cli, Commands, ConfigAction, ExportFormat, ListFormat, LogLevel, NotifyMode, OutputFormat, SortOrder, TemplateAction};
+use crate::config::{AliasConfig, AliasEntry, Config, ConfigLock, Step, DetachedProcess, config_file_name, edit_distance, DEFAULT_MAX_CAPTURE_BYTES, DEFAULT_RETRY_DELAY_MS, DEFAULT_RETRY_MAX_DELAY_MS, RetryBackoff, ColorMode, LastRun, expand_path, expand_variables, global_config_path, parse_env_file, load_config, load_detached, load_global_config, load_last_run, load_merged_config, load_state, lock_config, rfc3339_now, save_config, save_detached, save_global_config, save_last_run, save_state, unix_now};
+use crate::events::Event;
+use crate::exit_codes::{ConfigError, UsageError};
+use crate::notifications::Outcome;
+use crate::output::{format_duration, get_program_name, prefix, stopwatch, time_ago};
+use crate::resolve::{ResolvedRun, build_command};
+use crate::history::{HistoryRecord, UsageStats, append_history, load_history};
+use crate::runner::{EnvSource, EnvVar, ExecOptions, Executor, LineSink, MatchingExecutor, OutputMatchers, QuietExecutor, RetryPolicy, RetryingExecutor, ShellExecutor, is_alive, missing_programs, spawn_detached, stop_detached};
+
+// --log-level beats CAWA_LOG; warn by default so problems like a notification
+// that couldn't be shown still surface, while the chatty levels stay opt-in
+fn init_logging(level: Option<LogLevel>) {
+    let filter = level.map(LogLevel::filter).unwrap_or_else(|| {
+        std::env::var("CAWA_LOG")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(log::LevelFilter::Warn)
+    });
+    env_logger::Builder::new()
+        .filter_level(filter)
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
+}
+
+fn main() {
+    // clap would exit 2 on bad usage, which we reserve for config errors
+    let args = Cli::try_parse().unwrap_or_else(|e| {
+        let code = if e.use_stderr() { exit_codes::USAGE_ERROR } else { exit_codes::SUCCESS };
+        let _ = e.print();
+        std::process::exit(code);
+    });
+    init_logging(args.log_level);
+    runner::install_interrupt_handler();
+
+    let code = match run(args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            exit_codes::for_error(&e)
+        }
+    };
+    std::process::exit(code);
 }
 
-fn main() -> Result<()> {
-    let args = Cli::parse();
+fn run(args: Cli) -> Result<i32> {
     let program_name = get_program_name();
-    let mut success = true;
-    let mut should_notify = args.notify;
-    let mut dry_run = args.dry_run;
+    let mut exit_code = exit_codes::SUCCESS;
+    let should_notify = args.notify.is_some();
+    // --notify=MODE, None for a bare --notify (the config decides)
+    let notify_mode = args.notify.flatten();
+    let mut watching = false;
+    let mut opts = RunOptions {
+        dry_run: args.dry_run,
+        inherit_stdin: args.inherit_stdin,
+        stdin_file: args.stdin_file.clone(),
+        suppress_stdout: args.quiet_out,
+        suppress_stderr: args.quiet_err,
+        ignore_args: args.ignore_args,
+        include_disabled: args.include_disabled,
+        group: args.group,
+        force: args.force,
+        detach: args.detach,
+        verbose: args.verbose,
+        dump_resolved: args.dump_resolved,
+        jobs: args.jobs,
+        stagger_ms: args.stagger,
+        login: args.login,
+        nice: args.nice,
+        // json mode replaces the human-readable banners with events
+        quiet: args.output == OutputFormat::Json,
+        json: args.output == OutputFormat::Json,
+        // need the config, filled in below
+        max_capture_bytes: None,
+        skip_exit_code: None,
+    };
+    let config_path = match &args.config {
+        Some(p) => expand_path(&p.to_string_lossy()),
+        None => PathBuf::from(config_file_name(args.config_name.as_deref())?),
+    };
+    // has to be known before the first load, which is also where it warns
+    config::set_warn_unknown_fields(!args.allow_unknown_fields);
+    // errors are left for the command itself to report
+    let startup = load_merged_config(&config_path).ok();
+    runner::set_shell(startup.as_ref().and_then(|c| c.shell.as_deref()));
+    apply_color(startup.as_ref().and_then(|c| c.color));
+    output::set_emoji(!args.no_emoji && startup.as_ref().and_then(|c| c.emoji).unwrap_or(true));
+    output::set_timing_format(startup.as_ref().and_then(|c| c.timing_format).unwrap_or_default());
+    opts.max_capture_bytes =
+        match startup.as_ref().and_then(|c| c.max_capture_bytes).unwrap_or(DEFAULT_MAX_CAPTURE_BYTES) {
+            0 => None,
+            n => Some(n),
+        };
+    opts.skip_exit_code = Some(startup.as_ref().and_then(|c| c.skip_exit_code).unwrap_or(exit_codes::SKIPPED));
 
     let mut executed_alias = None;
 
-    match args.command {
+    // a bare `cs` runs the default alias if there is one that resolves
+    let command = match args.command {
+        None => default_command(startup.as_ref()),
+        command => command,
+    };
+    // only what runs commands needs the env file. a missing or broken one mustn't
+    // keep `cs list` or `cs config edit` from working; `cs doctor` reports it
+    if matches!(
+        &command,
+        Some(
+            Commands::Run { .. }
+                | Commands::Bench { .. }
+                | Commands::Watch { .. }
+                | Commands::Batch { .. }
+                | Commands::Rerun
+                | Commands::Tui { .. }
+        )
+    ) || matches!(&command, Some(Commands::External(a)) if !a.is_empty())
+    {
+        runner::set_env(load_env(startup.as_ref(), &config_path, args.env_file.as_deref(), &args.env)?);
+    }
+    match command {
         // ... (Add, Remove, List unchanged)
         Some(Commands::Add {
             parallel,
             desc,
             timeout,
             global,
+            no_append_args,
+            pipe_to,
+            file,
+            alias_of,
+            hidden,
+            confirm,
+            tags,
+            params,
             alias,
             commands,
         }) => {
-            let mut config = if global { load_global_config()? } else { load_config()? };
+            let _lock = lock_target(&config_path, global)?;
+            let mut config = if global { load_global_config()? } else { load_config(&config_path)? };
 
-            let entry = if parallel {
+            let entry = if let Some(target) = alias_of {
+                AliasEntry::Alias(target)
+            } else if let Some(path) = &file {
+                AliasEntry::Single(read_script(&expand_path(&path.to_string_lossy()))?)
+            } else if parallel {
                 AliasEntry::Parallel(commands.clone())
             } else {
                 if commands.len() > 1 {
@@ -60,86 +181,275 @@ fn main() -> Result<()> {
                 }
             };
 
+            if let AliasEntry::Parallel(cmds) = &entry {
+                if cmds.iter().all(|c| c.trim().is_empty()) {
+                    anyhow::bail!(UsageError(format!("Parallel alias '{}' needs at least one command", alias)));
+                }
+                // a blank one would start a shell that does nothing and count as a success
+                if let Some(i) = cmds.iter().position(|c| c.trim().is_empty()) {
+                    anyhow::bail!(UsageError(format!("Parallel alias '{}': command {} is blank", alias, i + 1)));
+                }
+                for cmd in cmds.iter().filter(|c| dangling_operator(c)) {
+                    eprintln!(
+                        "    {} '{}' is a separate parallel command, operators don't chain across them (quote the whole chain as one)",
+                        "⚠".yellow(),
+                        cmd
+                    );
+                }
+            }
+
             let display_val = match &entry {
+                AliasEntry::Single(s) if s.contains('\n') => format!("{} lines", s.lines().count()),
                 AliasEntry::Single(s) => s.clone(),
                 AliasEntry::Parallel(v) => format!("[{}]", v.join(", ")),
+                AliasEntry::Steps(steps) => format!("{} steps", steps.len()),
+                AliasEntry::Alias(target) => format!("another name for '{}'", target),
             };
 
-            config.aliases.insert(alias.clone(), AliasConfig { entry, description: desc, timeout_secs: timeout });
-            if global { save_global_config(&config)?; } else { save_config(&config)?; }
+            // a pointer has to land on something runnable, checked against everything
+            // reachable by name (the target may well be a global alias)
+            if let AliasEntry::Alias(_) = &entry {
+                let mut reachable = load_merged_config(&config_path)?;
+                reachable.aliases.insert(alias.clone(), AliasConfig::new(entry.clone()));
+                if let Err(e) = reachable.resolve(&alias) {
+                    anyhow::bail!(UsageError(e.to_string()));
+                }
+            }
 
-            println!(
-                "{} {} now stores {}",
-                "🐙".truecolor(80, 80, 80),
-                program_name.bold(),
-                display_val.cyan()
-            );
+            // re-adding a name counts as changing it, so it keeps its creation time
+            let created_at = config.aliases.get(&alias).and_then(|ac| ac.created_at.clone());
+            let now = rfc3339_now();
+            let mut new_ac = AliasConfig {
+                description: desc,
+                timeout_secs: timeout,
+                suppress_stdout: opts.suppress_stdout,
+                suppress_stderr: opts.suppress_stderr,
+                append_args: !no_append_args,
+                pipe_to,
+                login_shell: opts.login,
+                nice: opts.nice,
+                stdin_file: opts.stdin_file.clone(),
+                hidden,
+                confirm,
+                tags,
+                params,
+                created_at: Some(created_at.unwrap_or_else(|| now.clone())),
+                updated_at: Some(now),
+                ..AliasConfig::new(entry)
+            };
+            // adding exactly what's already there isn't an update
+            if let Some(old) = config.aliases.get(&alias)
+                && old.same_as(&new_ac)
+            {
+                new_ac.updated_at = old.updated_at.clone();
+            }
+            config.aliases.insert(alias.clone(), new_ac);
+            let written = if global { save_global_config(&config)? } else { save_config(&config, &config_path)? };
+            if written {
+                println!(
+                    "{} {} now stores {}",
+                    prefix(),
+                    program_name.bold(),
+                    display_val.cyan()
+                );
+            } else {
+                println!("{} {} no changes.", prefix(), alias.cyan());
+            }
         }
         Some(Commands::Remove { global, alias }) => {
-            let mut config = if global { load_global_config()? } else { load_config()? };
+            let _lock = lock_target(&config_path, global)?;
+            let mut config = if global { load_global_config()? } else { load_config(&config_path)? };
             if config.aliases.remove(&alias).is_some() { // remove returns the old value if it existed
-                if global { save_global_config(&config)?; } else { save_config(&config)?; }
+                if global { save_global_config(&config)?; } else { save_config(&config, &config_path)?; }
                 println!(
                     "{} {} {} removed.",
-                    "🐙".truecolor(80, 80, 80),
+                    prefix(),
                     program_name.bold(),
                     alias.red()
                 );
+                // other names for it are left in place but won't resolve any more
+                let mut orphans: Vec<&String> = config
+                    .aliases
+                    .iter()
+                    .filter(|(_, ac)| matches!(&ac.entry, AliasEntry::Alias(t) if *t == alias))
+                    .map(|(k, _)| k)
+                    .collect();
+                orphans.sort();
+                for name in orphans {
+                    eprintln!("    {} '{}' still points to '{}'", "⚠".yellow(), name, alias);
+                }
             } else {
                 eprintln!("Alias '{}' not found.", alias);
+                exit_code = exit_codes::UNKNOWN_ALIAS;
             }
         }
-        Some(Commands::List) => {
-            let local = load_config()?;
-            let global_cfg = load_global_config().unwrap_or_default();
+        Some(Commands::List { format: ListFormat::Man, all, .. }) => {
+            let reachable = load_merged_config(&config_path)?;
+            print!("{}", manpage::render(&reachable, &program_name, all));
+        }
+        Some(Commands::List { tree, sort, no_status, long, all, format: ListFormat::Text }) => {
+            let reachable = load_merged_config(&config_path)?;
+            let global_path = global_config_path();
             let state = load_state();
             let now = unix_now();
 
-            // build a sorted list: local aliases + global-only ones tagged with [global]
-            let mut entries: Vec<(String, &AliasConfig, bool)> = Vec::new();
-            for (k, v) in &local.aliases {
-                entries.push((k.clone(), v, false));
-            }
-            for (k, v) in &global_cfg.aliases {
-                if !local.aliases.contains_key(k) {
-                    entries.push((k.clone(), v, true));
-                }
-            }
+            // build a sorted list: local aliases, then ones from elsewhere tagged with
+            // [global] or the file that was included
+            let mut entries: Vec<(String, &AliasConfig, Option<String>)> = reachable
+                .aliases
+                .iter()
+                .filter(|(_, v)| all || !v.hidden)
+                .map(|(k, v)| {
+                    let origin = match reachable.sources.get(k) {
+                        Some(p) if *p == global_path => Some("global".to_string()),
+                        Some(p) if *p != config_path => Some(p.display().to_string()),
+                        _ => None,
+                    };
+                    (k.clone(), v, origin)
+                })
+                .collect();
 
             if entries.is_empty() {
                 println!("No aliases found.");
             } else {
-                entries.sort_by(|a, b| a.0.cmp(&b.0));
-                println!("{} Aliases", "🐙".truecolor(80, 80, 80));
                 // sort so the output is stable across runs
-                for (alias, ac, is_global) in entries {
-                    let tag = if is_global { " [global]".dimmed().to_string() } else { String::new() };
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                // the history log is only read when something needs it
+                let stats = if no_status && sort == SortOrder::Name {
+                    UsageStats::default()
+                } else {
+                    UsageStats::from_history(&load_history())
+                };
+                // other names are shown next to the alias they resolve to instead of on
+                // their own line; broken ones stay visible so they can be fixed
+                let mut other_names: HashMap<String, Vec<String>> = HashMap::new();
+                entries.retain(|(name, ac, _)| {
+                    if let AliasEntry::Alias(_) = &ac.entry
+                        && let Ok(Some((target, _))) = reachable.resolve(name)
+                    {
+                        other_names.entry(target.to_string()).or_default().push(name.clone());
+                        return false;
+                    }
+                    true
+                });
+                if sort != SortOrder::Name {
+                    stats.sort_names(&mut entries, sort, |e| &e.0);
+                }
+                println!("{} Aliases", prefix());
+                for (alias, ac, origin) in entries {
+                    let mut tag = origin.map(|o| format!(" [{}]", o).dimmed().to_string()).unwrap_or_default();
+                    let names = match other_names.get(&alias) {
+                        Some(others) => format!("{}, {}", alias, others.join(", ")),
+                        None => alias.clone(),
+                    };
+                    if ac.hidden {
+                        tag.push_str(&" [hidden]".dimmed().to_string());
+                    }
+                    for t in &ac.tags {
+                        tag.push_str(&format!(" #{}", t).dimmed().to_string());
+                    }
+                    let names = if ac.enabled {
+                        names.bold()
+                    } else {
+                        tag.push_str(&" [disabled]".dimmed().to_string());
+                        names.dimmed().strikethrough()
+                    };
+                    // green/red for how the last run went, a dash if it never ran
+                    let dot = if no_status {
+                        String::new()
+                    } else {
+                        match stats.last_success.get(&alias) {
+                            Some(true) => format!("{} ", "●".green()),
+                            Some(false) => format!("{} ", "●".red()),
+                            None => format!("{} ", "–".dimmed()),
+                        }
+                    };
                     match &ac.entry {
                         AliasEntry::Single(s) => {
-                            println!("{} {}{} → {}", program_name.dimmed(), alias.bold(), tag, s.cyan());
+                            println!("{}{} {}{} → {}", dot, program_name.dimmed(), names, tag, s.cyan());
+                        }
+                        AliasEntry::Parallel(cmds) if tree => {
+                            println!("{}{} {}{} → {} {}", dot, program_name.dimmed(), names, tag, "∥".yellow(), "parallel".yellow());
+                            for (i, cmd) in cmds.iter().enumerate() {
+                                let branch = if i + 1 == cmds.len() { "└─" } else { "├─" };
+                                println!("    {} {}", branch.dimmed(), cmd.cyan());
+                            }
                         }
                         AliasEntry::Parallel(cmds) => {
-                            println!("{} {}{} → {}", program_name.dimmed(), alias.bold(), tag, "[parallel]".yellow());
+                            println!("{}{} {}{} → {}", dot, program_name.dimmed(), names, tag, "[parallel]".yellow());
                             for cmd in cmds {
                                 println!("    {} {}", "└".dimmed(), cmd.cyan());
                             }
                         }
+                        AliasEntry::Steps(steps) => {
+                            println!("{}{} {}{} → {}", dot, program_name.dimmed(), names, tag, "[steps]".yellow());
+                            for step in steps {
+                                println!("    {} {} {}", "▶".dimmed(), step.name, step.command.cyan());
+                            }
+                        }
+                        AliasEntry::Alias(target) => {
+                            println!("{}{} {}{} → {} {}", dot, program_name.dimmed(), names, tag, "↪".red(), format!("{} (broken)", target).red());
+                        }
                     }
                     if let Some(desc) = &ac.description {
                         println!("    {} {}", "ℹ".dimmed(), desc.dimmed());
                     }
-                    if let Some(&last) = state.get(&alias) {
-                        let age = std::time::Duration::from_secs(now.saturating_sub(last));
-                        println!("    {} ran {} ago", "⏱".dimmed(), humantime::format_duration(age));
+                    // prefer the history log (covers failed runs too), the state file
+                    // only knows about successes
+                    let last = if no_status { state.get(&alias) } else { stats.last_run.get(&alias).or(state.get(&alias)) };
+                    if let Some(&last) = last {
+                        let clock = if output::emoji() { "⏱" } else { "·" };
+                        println!("    {} ran {}", clock.dimmed(), time_ago(now.saturating_sub(last)));
+                    }
+                    if long {
+                        let dates = [("created", &ac.created_at), ("updated", &ac.updated_at)]
+                            .iter()
+                            .filter_map(|(label, t)| t.as_ref().map(|t| format!("{} {}", label, t)))
+                            .collect::<Vec<_>>();
+                        if !dates.is_empty() {
+                            println!("    {}", dates.join(", ").dimmed());
+                        }
                     }
                 }
             }
         }
         Some(Commands::Init) => {
-            wizard::run_init()?;
+            wizard::run_init(&config_path)?;
+        }
+        Some(Commands::Doctor) => {
+            let env = load_env(startup.as_ref(), &config_path, args.env_file.as_deref(), &args.env);
+            exit_code = doctor::run_doctor(&config_path, env.map(|_| ()))?;
+        }
+        Some(Commands::Version) => {
+            let row = |label: &str, value: &str| println!("    {:<8} {}", label.dimmed(), value);
+            println!("{} {} {}", prefix(), get_program_name(), env!("CARGO_PKG_VERSION"));
+            row("commit", env!("CAWA_GIT_HASH"));
+            row("built", env!("CAWA_BUILD_DATE"));
+            row("rustc", env!("CAWA_RUSTC_VERSION"));
+            row("target", &format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS));
+        }
+        Some(Commands::Info) => {
+            print_info(&config_path)?;
+        }
+        Some(Commands::Config { action: ConfigAction::Path { global } }) => {
+            // only looks, never creates anything
+            let path = if global { global_config_path() } else { config_path.clone() };
+            let path = std::path::absolute(&path).unwrap_or(path);
+            println!("{}", path.display());
+            if !path.exists() {
+                eprintln!("{} {}", prefix(), "(doesn't exist yet, cs add will create it)".dimmed());
+            }
+        }
+        Some(Commands::Config { action: ConfigAction::Edit { global } }) => {
+            let path = if global { global_config_path() } else { config_path.clone() };
+            exit_code = edit_config_file(&path)?;
+        }
+        Some(Commands::Config { action: ConfigAction::Validate { global } }) => {
+            let path = if global { global_config_path() } else { config_path.clone() };
+            exit_code = validate_config_file(&path)?;
         }
         Some(Commands::Edit { global, alias }) => {
-            let mut config = if global { load_global_config()? } else { load_config()? };
+            let config = if global { load_global_config()? } else { load_config(&config_path)? };
 
             if let Some(ac) = config.aliases.get(&alias).cloned() {
                 let tmp = std::env::temp_dir().join(format!("cawa_edit_{}.txt", unix_now()));
@@ -147,6 +457,15 @@ fn main() -> Result<()> {
                     AliasEntry::Single(cmd) => cmd.clone(),
                     // parallel entries get one command per line so the user can add/remove/reorder
                     AliasEntry::Parallel(cmds) => cmds.join("\n"),
+                    AliasEntry::Alias(target) => {
+                        eprintln!("'{}' is another name for '{}'; edit that instead.", alias, target);
+                        return Ok(exit_codes::USAGE_ERROR);
+                    }
+                    // one command per line has nowhere to put the names
+                    AliasEntry::Steps(_) => {
+                        eprintln!("'{}' runs in steps; change them with `cs config edit`.", alias);
+                        return Ok(exit_codes::USAGE_ERROR);
+                    }
                 };
                 std::fs::write(&tmp, &contents)?;
 
@@ -157,6 +476,7 @@ fn main() -> Result<()> {
 
                 if !status.success() {
                     eprintln!("Editor exited with an error, alias unchanged.");
+                    exit_code = exit_codes::FAILURE;
                 } else {
                     let lines: Vec<String> = edited
                         .lines()
@@ -165,204 +485,1864 @@ fn main() -> Result<()> {
                         .collect();
 
                     let new_entry = match lines.len() {
-                        0 => { eprintln!("Editor result was empty, alias unchanged."); return Ok(()); }
+                        0 => { eprintln!("Editor result was empty, alias unchanged."); return Ok(exit_codes::USAGE_ERROR); }
                         1 => AliasEntry::Single(lines[0].clone()),
                         _ => AliasEntry::Parallel(lines),
                     };
 
-                    config.aliases.insert(alias.clone(), AliasConfig {
-                        entry: new_entry,
-                        description: ac.description,
-                        timeout_secs: ac.timeout_secs,
-                    });
-                    if global { save_global_config(&config)?; } else { save_config(&config)?; }
-                    println!("{} {} updated.", "🐙".truecolor(80, 80, 80), alias.cyan());
+                    // not locked while the editor is open, so re-read to keep whatever
+                    // else changed in the meantime
+                    let _lock = lock_target(&config_path, global)?;
+                    let mut config = if global { load_global_config()? } else { load_config(&config_path)? };
+                    let Some(ac) = config.aliases.remove(&alias) else {
+                        eprintln!("Alias '{}' was removed while it was being edited.", alias);
+                        return Ok(exit_codes::UNKNOWN_ALIAS);
+                    };
+                    config.aliases.insert(alias.clone(), AliasConfig { entry: new_entry, updated_at: Some(rfc3339_now()), ..ac });
+                    if global { save_global_config(&config)?; } else { save_config(&config, &config_path)?; }
+                    println!("{} {} updated.", prefix(), alias.cyan());
                 }
             } else {
                 eprintln!("Alias '{}' not found.", alias);
+                exit_code = exit_codes::UNKNOWN_ALIAS;
+            }
+        }
+        Some(Commands::Disable { global, alias }) => {
+            exit_code = set_enabled(&config_path, global, &alias, false)?;
+        }
+        Some(Commands::Enable { global, alias }) => {
+            exit_code = set_enabled(&config_path, global, &alias, true)?;
+        }
+        Some(Commands::SetVar { global, key, value }) => {
+            let _lock = lock_target(&config_path, global)?;
+            let mut config = if global { load_global_config()? } else { load_config(&config_path)? };
+            config.variables.insert(key.clone(), value.clone());
+            let written = if global { save_global_config(&config)? } else { save_config(&config, &config_path)? };
+            if written {
+                println!(
+                    "{} {} = {}",
+                    prefix(),
+                    format!("${{{}}}", key).bold(),
+                    value.cyan()
+                );
+            } else {
+                println!("{} {} no changes.", prefix(), format!("${{{}}}", key).bold());
+            }
+        }
+        Some(Commands::SetDefault { global, clear, alias }) => {
+            let _lock = lock_target(&config_path, global)?;
+            let mut config = if global { load_global_config()? } else { load_config(&config_path)? };
+            if let Some(alias) = &alias {
+                // checked against everything reachable, the default may well be global
+                let reachable = load_merged_config(&config_path)?;
+                if reachable.resolve(alias)?.is_none() {
+                    report_unknown_alias(&reachable, alias, &config_path);
+                    return Ok(exit_codes::UNKNOWN_ALIAS);
+                }
+            }
+            config.default_alias = alias.filter(|_| !clear);
+            let written = if global { save_global_config(&config)? } else { save_config(&config, &config_path)? };
+            match (&config.default_alias, written) {
+                (_, false) => println!("{} no changes.", prefix()),
+                (Some(alias), true) => println!("{} `{}` on its own now runs {}", prefix(), get_program_name(), alias.bold()),
+                (None, true) => println!("{} `{}` on its own prints help again", prefix(), get_program_name()),
+            }
+        }
+        Some(Commands::Template { action: TemplateAction::Add { global, name, command } }) => {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                anyhow::bail!(UsageError(format!(
+                    "Template name '{}' can only use letters, digits, '_' and '-'",
+                    name
+                )));
+            }
+            let _lock = lock_target(&config_path, global)?;
+            let mut config = if global { load_global_config()? } else { load_config(&config_path)? };
+            config.templates.insert(name.clone(), command.clone());
+            let written = if global { save_global_config(&config)? } else { save_config(&config, &config_path)? };
+            if written {
+                println!("{} {} = {}", prefix(), format!("{{{{template:{}}}}}", name).bold(), command.cyan());
+            } else {
+                println!("{} {} no changes.", prefix(), format!("{{{{template:{}}}}}", name).bold());
+            }
+            // saved anyway, the other half of a cycle may be about to change too
+            let mut reachable = load_merged_config(&config_path)?;
+            reachable.templates.insert(name, command.clone());
+            if let Err(e) = config::expand_templates(&command, &reachable.templates) {
+                eprintln!("{} {} {}", prefix(), "⚠".yellow(), e);
+            }
+        }
+        Some(Commands::Template { action: TemplateAction::Remove { global, name } }) => {
+            let _lock = lock_target(&config_path, global)?;
+            let mut config = if global { load_global_config()? } else { load_config(&config_path)? };
+            if config.templates.remove(&name).is_some() {
+                if global { save_global_config(&config)?; } else { save_config(&config, &config_path)?; }
+                println!("{} {} removed.", prefix(), format!("{{{{template:{}}}}}", name).red());
+                // aliases using it are left alone but won't run until it's back
+                let reference = format!("{{{{template:{}}}}}", name);
+                let reachable = load_merged_config(&config_path)?;
+                if !reachable.templates.contains_key(&name) {
+                    let mut users: Vec<&String> = reachable
+                        .aliases
+                        .iter()
+                        .filter(|(_, ac)| match &ac.entry {
+                            AliasEntry::Single(cmd) => cmd.contains(&reference),
+                            AliasEntry::Parallel(cmds) => cmds.iter().any(|c| c.contains(&reference)),
+                            AliasEntry::Steps(steps) => steps.iter().any(|s| s.command.contains(&reference)),
+                            AliasEntry::Alias(_) => false,
+                        })
+                        .map(|(k, _)| k)
+                        .collect();
+                    users.sort();
+                    for alias in users {
+                        eprintln!("    {} '{}' still uses {}", "⚠".yellow(), alias, reference);
+                    }
+                }
+            } else {
+                eprintln!("Template '{}' not found.", name);
+                exit_code = exit_codes::USAGE_ERROR;
+            }
+        }
+        Some(Commands::Template { action: TemplateAction::List }) => {
+            let reachable = load_merged_config(&config_path)?;
+            let mut names: Vec<&String> = reachable.templates.keys().collect();
+            names.sort();
+            if names.is_empty() {
+                println!("No templates defined. Add one with `{} template add <name> <command>`.", program_name);
+            }
+            for name in names {
+                println!("{} → {}", name.bold(), reachable.templates[name].cyan());
             }
         }
         Some(Commands::Rename { old_alias, new_alias }) => {
-            let mut config = load_config()?;
+            let _lock = lock_target(&config_path, false)?;
+            let mut config = load_config(&config_path)?;
             if let Some(entry) = config.aliases.remove(&old_alias) {
-                config.aliases.insert(new_alias.clone(), entry);
-                save_config(&config)?;
+                config.aliases.insert(new_alias.clone(), AliasConfig { updated_at: Some(rfc3339_now()), ..entry });
+                // keep other names pointing at it
+                for ac in config.aliases.values_mut() {
+                    if let AliasEntry::Alias(target) = &mut ac.entry
+                        && *target == old_alias
+                    {
+                        *target = new_alias.clone();
+                    }
+                }
+                save_config(&config, &config_path)?;
                 println!(
                     "{} {} → {}",
-                    "🐙".truecolor(80, 80, 80),
+                    prefix(),
                     old_alias.red(),
                     new_alias.cyan()
                 );
             } else {
                 eprintln!("Alias '{}' not found.", old_alias);
+                exit_code = exit_codes::UNKNOWN_ALIAS;
             }
         }
         Some(Commands::Run { parallel, timeout, commands }) => {
-            let config = load_config()?;
+            let config = load_config(&config_path)?;
             let entry = if parallel {
                 AliasEntry::Parallel(commands)
             } else {
                 AliasEntry::Single(commands.join(" "))
             };
             // one-off run: no alias name to look up, just execute directly
-            success = run_entry(&entry, &[], config.enable_timing.unwrap_or(false), dry_run, timeout)?;
+            let ac = AliasConfig { timeout_secs: timeout, ..AliasConfig::new(entry) };
+            exit_code = run_entry(None, &ac, &[], config.timing_threshold(), &opts, &ShellExecutor)?;
+        }
+        Some(Commands::Bench { runs, warmup, alias, args }) => {
+            let config = load_merged_config(&config_path)?;
+            exit_code = bench_alias(&config, &alias, &args, runs, warmup, &opts)?;
+        }
+        Some(Commands::Watch { paths, debounce, alias, args }) => {
+            if load_merged_config(&config_path)?.resolve(&alias)?.is_none() {
+                eprintln!("Unknown command or alias: {}", alias);
+                exit_code = exit_codes::UNKNOWN_ALIAS;
+            } else {
+                let paths = if paths.is_empty() { vec![PathBuf::from(".")] } else { paths };
+                // the config is read again for every run, so editing it takes effect
+                // (and, in a watched directory, triggers the rerun). a broken config
+                // or a run that can't start doesn't end the watch, the next change
+                // might fix it
+                // --notify goes off after every run, throttled by
+                // notify_min_interval_secs, rather than once when the watch ends
+                watch::Watch { paths, debounce }.run(|| {
+                    let code = load_merged_config(&config_path)
+                        .and_then(|config| run_configured_alias(&config, &alias, &args, &opts, &ShellExecutor))
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error: {:?}", e);
+                            exit_codes::for_error(&e)
+                        });
+                    if should_notify {
+                        notify(&config_path, Some(&alias), code, notify_mode);
+                    }
+                })?;
+                watching = true;
+            }
+        }
+        Some(Commands::Batch { parallel, aliases }) => {
+            let config = load_merged_config(&config_path)?;
+            exit_code = run_batch(&config, &config_path, &aliases, parallel, &opts)?;
+        }
+        Some(Commands::Rerun) => {
+            if let Some(last) = load_last_run() {
+                executed_alias = Some(last.alias.clone());
+                let config = load_merged_config(&config_path)?;
+                exit_code = run_configured_alias(&config, &last.alias, &last.args, &opts, &ShellExecutor)?;
+            } else {
+                eprintln!("No previous run to repeat. Run an alias first.");
+                exit_code = exit_codes::USAGE_ERROR;
+            }
+        }
+        Some(Commands::Last { alias, lines, pager }) => {
+            let alias = alias.or_else(|| load_history().last().map(|r| r.alias.clone()));
+            match alias {
+                None => {
+                    eprintln!("Nothing has run yet.");
+                    exit_code = exit_codes::FAILURE;
+                }
+                Some(alias) => {
+                    // parallel aliases have one log per command
+                    let logs = runner::existing_logs(&alias);
+                    if logs.is_empty() {
+                        // only detached runs write a log
+                        eprintln!("No log for '{}'. Logs are written for runs started with --detach.", alias);
+                        exit_code = exit_codes::FAILURE;
+                    } else if pager {
+                        let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+                        let status = std::process::Command::new(&pager).args(&logs).status()
+                            .with_context(|| format!("Failed to start pager '{}'", pager))?;
+                        exit_code = status.code().unwrap_or(exit_codes::FAILURE);
+                    } else {
+                        for log in &logs {
+                            let content = std::fs::read_to_string(log).context("Failed to read log file")?;
+                            let all: Vec<&str> = content.lines().collect();
+                            println!("{} {} {}", prefix(), alias.bold(), log.display().to_string().dimmed());
+                            for line in &all[all.len().saturating_sub(lines)..] {
+                                println!("{}", line);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Some(Commands::Ps) => {
+            // drop anything that has exited (or whose pid now belongs to someone else)
+            let (procs, dead): (Vec<_>, Vec<_>) = load_detached().into_iter().partition(is_alive);
+            if !dead.is_empty() {
+                save_detached(&procs)?;
+            }
+            if procs.is_empty() {
+                println!("No detached aliases running.");
+            } else {
+                let now = unix_now();
+                println!("{} Detached", prefix());
+                for p in &procs {
+                    println!(
+                        "{} {} {} → {}",
+                        p.alias.bold(),
+                        format!("pid {}", p.pid).yellow(),
+                        format!("started {}", time_ago(now.saturating_sub(p.started))).dimmed(),
+                        p.log.display().to_string().cyan()
+                    );
+                }
+            }
+            if !dead.is_empty() {
+                println!("    {} pruned {} exited process(es)", "ℹ".dimmed(), dead.len());
+            }
         }
-        Some(Commands::Tui) => {
+        Some(Commands::Stop { alias }) => {
+            let (matching, rest): (Vec<_>, Vec<_>) = load_detached().into_iter().partition(|p| p.alias == alias);
+            if matching.is_empty() {
+                eprintln!("No detached process for '{}'.", alias);
+                exit_code = exit_codes::UNKNOWN_ALIAS;
+            } else {
+                for p in &matching {
+                    if stop_detached(p) {
+                        println!("{} Stopped '{}' (pid {})", prefix(), alias, p.pid);
+                    } else {
+                        println!("{} '{}' (pid {}) had already exited", prefix(), alias, p.pid);
+                    }
+                }
+                // stale entries go too, there's nothing left to stop
+                save_detached(&rest)?;
+            }
+        }
+        Some(Commands::Export { format: ExportFormat::Make, out, all }) => {
+            let reachable = load_merged_config(&config_path)?;
+            let (makefile, skipped) = makefile::render(&reachable, all);
+            for (name, reason) in &skipped {
+                eprintln!("{} {} Left out '{}': {}", prefix(), "⚠".yellow(), name, reason);
+            }
+            match out {
+                Some(path) => {
+                    std::fs::write(&path, makefile).with_context(|| format!("Failed to write {}", path.display()))?;
+                    println!("{} wrote {}", prefix(), path.display());
+                }
+                None => print!("{}", makefile),
+            }
+        }
+        Some(Commands::Clean { keep, older_than }) => {
+            exit_code = clean(&config_path, keep, older_than, opts.dry_run)?;
+        }
+        Some(Commands::Tui { fresh, tag }) => {
             // use merged so global aliases appear in the TUI
-            let config = load_merged_config()?;
-            if let Some(selected_alias) = tui::run_tui(&config)? {
-                executed_alias = Some(selected_alias.clone());
-                success = run_configured_alias(&config, &selected_alias, &[], dry_run)?;
+            let config = load_merged_config(&config_path)?;
+            let detach = |alias: &str| {
+                detach_from_tui(&config_path, alias, &opts).unwrap_or_else(|e| format!("Couldn't start '{}': {:#}", alias, e))
+            };
+            if let Some(picked) = tui::run_tui(&config, &config_path, fresh, tag.as_deref(), &detach)? {
+                // the tui may have picked up edits since we loaded, so run the latest
+                let config = config::reload_merged_config(&config_path)?;
+                // several checked aliases go through cs batch
+                if let [alias] = picked.aliases.as_slice() {
+                    executed_alias = Some(alias.clone());
+                    exit_code = run_configured_alias(&config, alias, &[], &opts, &ShellExecutor)?;
+                } else {
+                    exit_code = run_batch(&config, &config_path, &picked.aliases, picked.parallel, &opts)?;
+                }
             }
         }
         Some(Commands::External(args)) => {
             if args.is_empty() {
                 Cli::command().print_help()?;
-                return Ok(());
+                return Ok(exit_codes::SUCCESS);
             }
             let alias = &args[0];
             let raw_extra_args = &args[1..];
 
-            // filter out our own flags before passing args through to the alias
-            let mut extra_args = Vec::new();
-            for arg in raw_extra_args {
-                if arg == "--notify" {
-                    should_notify = true;
-                } else if arg == "--dry-run" {
-                    // clap doesn't apply global flag parsing inside external subcommands
-                    dry_run = true;
-                } else {
-                    extra_args.push(arg.clone());
-                }
-            }
+            // cs's own flags go before the alias name, where clap has already taken
+            // them. everything after it belongs to the alias: its {name} parameters,
+            // then the args for the command, so `cs test -v` reaches the test runner
+            let extra_args = raw_extra_args.to_vec();
 
             executed_alias = Some(alias.clone());
-            // use merged so global aliases are reachable by name
-            let config = load_merged_config()?;
-
-            success = run_configured_alias(&config, alias, &extra_args, dry_run)?;
+            // use merged so global aliases are reachable by name.
+            // errors are reported here rather than bubbled with `?` so the failure
+            // notification below still fires before we exit non-zero
+            let outcome = load_merged_config(&config_path)
+                .with_context(|| format!("'{}' wasn't run because the config didn't load", alias))
+                .and_then(|config| {
+                    if config.resolve(alias)?.is_none() {
+                        report_unknown_alias(&config, alias, &config_path);
+                        return Ok(exit_codes::UNKNOWN_ALIAS);
+                    }
+                    run_configured_alias(&config, alias, &extra_args, &opts, &ShellExecutor)
+                });
+            exit_code = match outcome {
+                Ok(code) => code,
+                Err(e) => {
+                    eprintln!("{} {:#}", prefix(), e);
+                    exit_codes::for_error(&e)
+                }
+            };
         }
         None => {
             Cli::command().print_help()?;
         }
     }
 
-    if should_notify {
-        if let Err(e) = notifications::send(success, executed_alias.as_deref()) {
+    // watch has already notified after each of its runs
+    if should_notify && !watching {
+        notify(&config_path, executed_alias.as_deref(), exit_code, notify_mode);
+    }
+
+    Ok(exit_code)
+}
+
+// --notify for one run. the config is read fresh: the run may have changed it
+fn notify(config_path: &Path, alias: Option<&str>, exit_code: i32, mode: Option<NotifyMode>) {
+    let config = load_merged_config(config_path).ok();
+    let min_interval = config.as_ref().and_then(|c| c.notify_min_interval_secs).unwrap_or(0);
+    // the alias's own timeout, so a timeout notification can say how long it waited
+    let timeout = match (&config, alias) {
+        (Some(c), Some(a)) => c.resolve(a).ok().flatten().and_then(|(_, ac)| ac.timeout_secs),
+        _ => None,
+    };
+    let outcome = Outcome::from_code(exit_code, runner::take_timed_out(), timeout);
+    let mode = mode.or(config.as_ref().and_then(|c| c.notify_mode)).unwrap_or_default();
+    let res = if min_interval > 0 {
+        notifications::send_throttled(&outcome, alias, min_interval, mode)
+    } else {
+        notifications::send(&outcome, alias, mode)
+    };
+    if let Err(e) = res {
+        eprintln!(
+            "{} Failed to send notification: {}",
+            prefix(),
+            e
+        );
+    }
+}
+
+// the variables commands get on top of our own environment. the env file comes
+// from --env-file, then the config's env_file, then .env beside the config if
+// auto_env is on; like dotenv it doesn't override variables already set.
+// --env pairs override everything
+fn load_env(
+    config: Option<&crate::config::Config>,
+    config_path: &Path,
+    env_file: Option<&str>,
+    explicit: &[String],
+) -> Result<Vec<EnvVar>> {
+    let config_dir = config_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let path = match (env_file, config) {
+        (Some(flag), _) => Some(expand_path(flag)),
+        (None, Some(c)) => match &c.env_file {
+            Some(file) => Some(config_dir.join(expand_path(file))),
+            None if c.auto_env.unwrap_or(false) && config_dir.join(".env").exists() => Some(config_dir.join(".env")),
+            None => None,
+        },
+        (None, None) => None,
+    };
+
+    let mut vars = Vec::new();
+    if let Some(path) = path {
+        log::debug!("loading env file {}", path.display());
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| ConfigError(format!("Failed to read env file {}", path.display())))?;
+        let parsed = parse_env_file(&content).with_context(|| format!("Failed to parse env file {}", path.display()))?;
+        // the ones already set are kept too, so --verbose can say why the file's value lost
+        vars.extend(parsed.into_iter().map(|(name, value)| match std::env::var(&name) {
+            Ok(ours) => EnvVar { name, value: ours, source: EnvSource::Process(path.clone()) },
+            Err(_) => EnvVar { name, value, source: EnvSource::File(path.clone()) },
+        }));
+    }
+    for pair in explicit {
+        let Some((k, v)) = pair.split_once('=') else {
+            anyhow::bail!(UsageError(format!("--env expects KEY=VALUE, got '{}'", pair)));
+        };
+        vars.retain(|existing| existing.name != k);
+        vars.push(EnvVar { name: k.to_string(), value: v.to_string(), source: EnvSource::Flag });
+    }
+    Ok(vars)
+}
+
+// per-invocation switches that apply to whatever gets run
+#[derive(Clone, Default)]
+struct RunOptions {
+    dry_run: bool,
+    // skip the "Executing: ..." banners
+    quiet: bool,
+    // let parallel children read the terminal; off by default so they can't fight over it
+    inherit_stdin: bool,
+    // overrides the alias's stdin_file
+    stdin_file: Option<String>,
+    // on top of whatever the alias itself asks for
+    suppress_stdout: bool,
+    suppress_stderr: bool,
+    // silently drop args passed to an alias with append_args off
+    ignore_args: bool,
+    // run aliases that were turned off with `cs disable`
+    include_disabled: bool,
+    // parallel output is buffered per command and printed in config order afterwards
+    group: bool,
+    // ignore condition / require_files
+    force: bool,
+    // start in the background and return straight away
+    detach: bool,
+    // print how the alias was resolved before running it. this is diagnostics the
+    // user asked for, so it shows even when `quiet` hides the usual banners
+    verbose: bool,
+    // print the ResolvedRun as JSON and stop there
+    dump_resolved: bool,
+    // at most this many parallel commands at once (all of them when unset)
+    jobs: Option<usize>,
+    // delay between parallel starts, overrides the alias's stagger_ms
+    stagger_ms: Option<u64>,
+    // same as login_shell on the alias
+    login: bool,
+    // overrides the alias's nice
+    nice: Option<i32>,
+    // --output json: report progress as events::Event lines on stdout
+    json: bool,
+    // the config's max_capture_bytes, None when capture isn't capped
+    max_capture_bytes: Option<u64>,
+    // the config's skip_exit_code, or 75
+    skip_exit_code: Option<i32>,
+}
+
+impl RunOptions {
+    fn exec_options(&self, ac: &AliasConfig) -> ExecOptions {
+        ExecOptions {
+            timeout_secs: ac.timeout_secs,
+            // sudo may have to ask for a password
+            inherit_stdin: self.inherit_stdin || ac.run_as.is_some(),
+            suppress_stdout: self.suppress_stdout || ac.suppress_stdout,
+            suppress_stderr: self.suppress_stderr || ac.suppress_stderr,
+            login_shell: self.login || ac.login_shell,
+            nice: self.nice.or(ac.nice),
+            pty: ac.pty,
+            stdin_file: self.stdin_file.as_ref().or(ac.stdin_file.as_ref()).map(|p| expand_path(p)),
+            max_capture_bytes: self.max_capture_bytes,
+            run_as: ac.run_as.clone(),
+            memory_limit_mb: ac.memory_limit_mb,
+            cpu_seconds: ac.cpu_seconds,
+            skip_exit_code: self.skip_exit_code,
+        }
+    }
+}
+
+// load a script for `cs add --file`. the body is run with sh -c like any other alias,
+// so the shebang is dropped; warn if it asked for something sh won't understand
+fn read_script(path: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| UsageError(format!("Failed to read script file {}", path.display())))?;
+
+    let mut lines = content.lines().peekable();
+    if let Some(first) = lines.peek()
+        && let Some(interp) = first.strip_prefix("#!")
+    {
+        let interp = interp.trim();
+        let sh_like = ["sh", "bash", "zsh", "dash", "ksh"]
+            .iter()
+            .any(|s| interp.ends_with(&format!("/{}", s)) || interp.ends_with(&format!(" {}", s)));
+        if !sh_like {
             eprintln!(
-                "{} Failed to send notification: {}",
-                "🐙".truecolor(80, 80, 80),
-                e
+                "{} {} has shebang '{}', but aliases run with sh. Consider `cs add <alias> \"{} {}\"` instead.",
+                prefix(),
+                path.display(),
+                first,
+                interp,
+                path.display()
             );
         }
+        lines.next();
     }
 
-    if !success {
-        std::process::exit(1);
+    let body = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+    if body.is_empty() {
+        anyhow::bail!(UsageError(format!("Script file {} is empty", path.display())));
     }
+    Ok(body)
+}
 
-    Ok(())
+// --dump-resolved: a ResolvedRun as JSON, with what the runner adds to it
+#[derive(Serialize)]
+struct ResolvedDump<'a> {
+    alias: &'a str,
+    target: &'a str,
+    kind: &'static str,
+    commands: &'a [String],
+    shell: Vec<String>,
+    cwd: Option<PathBuf>,
+    // only what cs adds on top of its own environment, plus env file variables our
+    // environment overrides (with the value that wins). secrets are masked
+    env: BTreeMap<String, String>,
+    // where each of those came from: the env file's path, "--env", or
+    // "environment (overrides <file>)"
+    env_sources: BTreeMap<String, String>,
+    timeout_secs: Option<u64>,
+    detached: bool,
+    nice: Option<i32>,
+    stdin_file: Option<PathBuf>,
+    pty: bool,
+    condition: Option<&'a str>,
+    require_files: &'a [String],
+    requires: &'a [String],
+    memory_limit_mb: Option<u64>,
+    cpu_seconds: Option<u64>,
+    success_pattern: Option<&'a str>,
+    failure_pattern: Option<&'a str>,
+    success_overrides_exit: bool,
+    retries: u32,
+    // the waits before each retry, without jitter
+    retry_delays_ms: Vec<u64>,
+    retry_jitter: bool,
+    enabled: bool,
+}
+
+impl ResolvedRun {
+    fn dump(&self, config: &crate::config::Config, opts: &RunOptions) -> ResolvedDump<'_> {
+        let exec = opts.exec_options(&self.ac);
+        let vars = runner::env_vars();
+        ResolvedDump {
+            alias: &self.alias,
+            target: &self.target,
+            kind: match self.ac.entry {
+                AliasEntry::Parallel(_) => "parallel",
+                AliasEntry::Steps(_) => "steps",
+                _ => "single",
+            },
+            commands: &self.commands,
+            // only variable names, never values, so nothing here needs masking
+            shell: runner::launcher(&exec),
+            cwd: std::env::current_dir().ok(),
+            env: vars.iter().map(|v| (v.name.clone(), config.mask_env(&v.name, &v.value))).collect(),
+            env_sources: vars.iter().map(|v| (v.name.clone(), v.source.label())).collect(),
+            timeout_secs: exec.timeout_secs,
+            detached: opts.detach || self.ac.detached,
+            nice: exec.nice,
+            stdin_file: exec.stdin_file,
+            pty: exec.pty,
+            condition: self.ac.condition.as_deref(),
+            require_files: &self.ac.require_files,
+            requires: &self.ac.requires,
+            memory_limit_mb: exec.memory_limit_mb,
+            cpu_seconds: exec.cpu_seconds,
+            success_pattern: self.ac.success_pattern.as_deref(),
+            failure_pattern: self.ac.failure_pattern.as_deref(),
+            success_overrides_exit: self.ac.success_overrides_exit,
+            retries: self.ac.retries,
+            retry_delays_ms: retry_policy(&self.ac)
+                .map(|p| (1..=p.retries).map(|n| runner::retry_delay(&p, n, None).as_millis() as u64).collect())
+                .unwrap_or_default(),
+            retry_jitter: self.ac.retry_jitter,
+            enabled: self.ac.enabled,
+        }
+    }
+}
+
+// what an alias comes down to once its name, arguments and preconditions are sorted out
+enum Prepared {
+    Unknown,
+    // shouldn't run right now; that's a skip, not a failure
+    Skip(String),
+    Ready(Box<ResolvedRun>),
+}
+
+// everything before actually running an alias: resolve::resolve plus the checks.
+// problems that would only make it fail halfway are errors
+fn prepare_run(
+    config: &crate::config::Config,
+    alias: &str,
+    extra_args: &[String],
+    opts: &RunOptions,
+    exec: &dyn Executor,
+) -> Result<Prepared> {
+    // turning off the alias something points at turns off the pointer too
+    if let Some((resolved_name, _)) = config.resolve(alias)?
+        && let Some(name) =
+            [alias, resolved_name].into_iter().find(|n| config.aliases.get(*n).is_some_and(|ac| !ac.enabled))
+        && !opts.include_disabled
+    {
+        // asked for by name, so unlike a precondition it's an error rather than a skip
+        anyhow::bail!(UsageError(format!(
+            "Alias '{}' is disabled (cs enable {} to turn it back on, or --include-disabled to run it anyway)",
+            name, name
+        )));
+    }
+    let Some(run) = resolve::resolve(config, alias, extra_args, opts.ignore_args)? else {
+        return Ok(Prepared::Unknown);
+    };
+    let ac = &run.ac;
+    // unlike the preconditions this is an error: the alias would just fail halfway
+    let mut needed = ac.requires.clone();
+    if ac.run_as.is_some() {
+        needed.push("sudo".to_string());
+    }
+    if cfg!(not(target_os = "linux")) && (ac.memory_limit_mb.is_some() || ac.cpu_seconds.is_some()) {
+        anyhow::bail!(UsageError(format!(
+            "Alias '{}' sets memory_limit_mb / cpu_seconds, which are only supported on Linux",
+            alias
+        )));
+    }
+    let missing = missing_programs(&needed);
+    if !missing.is_empty() {
+        anyhow::bail!(UsageError(format!(
+            "Alias '{}' can't run, missing from PATH: {}",
+            alias,
+            missing.join(", ")
+        )));
+    }
+    if let Some(path) = opts.exec_options(ac).stdin_file
+        && !path.is_file()
+    {
+        anyhow::bail!(UsageError(format!(
+            "Alias '{}' reads its stdin from {}, which doesn't exist",
+            alias,
+            path.display()
+        )));
+    }
+    if !opts.force
+        && let Some(reason) = unmet_precondition(ac, &config.variables, opts, exec)?
+    {
+        return Ok(Prepared::Skip(format!("{} (use --force to run anyway)", reason)));
+    }
+    Ok(Prepared::Ready(Box::new(run)))
 }
 
 fn run_configured_alias(
     config: &crate::config::Config,
     alias: &str,
     extra_args: &[String],
-    dry_run: bool,
-) -> Result<bool> {
-    if let Some(ac) = config.aliases.get(alias) {
-        let result = run_entry(&ac.entry, extra_args, config.enable_timing.unwrap_or(false), dry_run, ac.timeout_secs)?;
-        // record the run timestamp so cs list can show when this was last used
-        if result && !dry_run {
-            let mut state = load_state();
-            state.insert(alias.to_string(), unix_now());
-            let _ = save_state(&state);
-        }
-        Ok(result)
+    opts: &RunOptions,
+    exec: &dyn Executor,
+) -> Result<i32> {
+    // nothing runs and nothing is checked, it only shows what would be run
+    if opts.dump_resolved {
+        let Some(run) = resolve::resolve(config, alias, extra_args, opts.ignore_args)? else {
+            eprintln!("Unknown command or alias: {}", alias);
+            return Ok(exit_codes::UNKNOWN_ALIAS);
+        };
+        println!("{}", serde_json::to_string_pretty(&run.dump(config, opts))?);
+        return Ok(exit_codes::SUCCESS);
+    }
+    match prepare_run(config, alias, extra_args, opts, exec)? {
+        Prepared::Unknown => {
+            eprintln!("Unknown command or alias: {}", alias);
+            Ok(exit_codes::UNKNOWN_ALIAS)
+        }
+        Prepared::Skip(reason) => {
+            // a skip isn't a failure, so it stays out of the history and exits 0
+            eprintln!("{} Skipping '{}': {}", prefix(), alias, reason);
+            Ok(exit_codes::SUCCESS)
+        }
+        Prepared::Ready(run) => execute(config, &run, opts, exec),
+    }
+}
+
+// run an alias prepare_run said is ready, in the foreground or detached, and
+// record how it went. the exit code is the run's outcome
+fn execute(config: &crate::config::Config, run: &ResolvedRun, opts: &RunOptions, exec: &dyn Executor) -> Result<i32> {
+    let alias = run.alias.as_str();
+    if !opts.dry_run {
+        let _ = save_last_run(&LastRun { alias: alias.to_string(), args: run.args.clone() });
+    }
+    if opts.verbose {
+        print_resolution(config, alias, &run.target, &run.ac, &run.extra_args, opts);
+    }
+    let start = Instant::now();
+    let result = if opts.detach || run.ac.detached {
+        start_detached(run, opts)?
     } else {
-        eprintln!("Unknown command or alias: {}", alias);
-        Ok(false)
+        run_entry(Some(alias), &run.ac, &run.extra_args, config.timing_threshold(), opts, exec)?
+    };
+    if !opts.dry_run {
+        record_run(config, alias, result, start);
+    }
+    Ok(result)
+}
+
+// a skip is neither a success nor a failure, and only failures stop a batch or
+// count against an alias
+fn failed(code: i32) -> bool {
+    code != exit_codes::SUCCESS && code != exit_codes::SKIPPED
+}
+
+// history, last-used time and webhook for a run that actually happened
+fn record_run(config: &Config, alias: &str, result: i32, start: Instant) {
+    let duration_ms = start.elapsed().as_millis() as u64;
+    let _ = append_history(&HistoryRecord {
+        alias: alias.to_string(),
+        timestamp: unix_now(),
+        success: !failed(result),
+        duration_ms,
+        timed_out: result == exit_codes::TIMED_OUT,
+        skipped: result == exit_codes::SKIPPED,
+    });
+    if let Some(url) = &config.webhook_url {
+        webhook::post(url, &webhook::RunSummary {
+            alias,
+            success: !failed(result),
+            skipped: result == exit_codes::SKIPPED,
+            duration_ms,
+            exit_code: result,
+        });
+    }
+    // record the run timestamp so cs list can show when this was last used
+    if !failed(result) {
+        let mut state = load_state();
+        state.insert(alias.to_string(), unix_now());
+        let _ = save_state(&state);
     }
 }
 
+// `o` in the tui: start the alias in the background without leaving the tui. the
+// tui owns the screen, so the outcome comes back as one line for its status bar
+fn detach_from_tui(config_path: &Path, alias: &str, opts: &RunOptions) -> Result<String> {
+    let config = config::reload_merged_config(config_path)?;
+    let run = match prepare_run(&config, alias, &[], opts, &ShellExecutor)? {
+        Prepared::Unknown => return Ok(format!("'{}' is no longer in the config", alias)),
+        Prepared::Skip(reason) => return Ok(format!("Skipped '{}': {}", alias, reason)),
+        Prepared::Ready(run) => run,
+    };
+    if opts.dry_run {
+        return Ok(format!("Would start '{}' in the background", alias));
+    }
+    let _ = save_last_run(&LastRun { alias: alias.to_string(), args: Vec::new() });
+    let start = Instant::now();
+    let started = spawn_all_detached(&run, opts)?;
+    record_run(&config, alias, exit_codes::SUCCESS, start);
+    let pids: Vec<String> = started.iter().map(|(_, p)| p.pid.to_string()).collect();
+    Ok(match started.as_slice() {
+        [(_, p)] => format!("Started '{}' in the background (pid {}) → {}", alias, p.pid, p.log.display()),
+        _ => format!("Started '{}' in the background (pids {}) → {}/", alias, pids.join(", "), runner::LOG_DIR),
+    })
+}
+
+// names in a batch can be globs ('test-*'): each one becomes the aliases it
+// matches, sorted. hidden ones only run by their exact name, and an alias named
+// twice only runs once
+fn expand_patterns(config: &Config, names: &[String], quiet: bool) -> Result<Vec<String>> {
+    let mut aliases: Vec<String> = Vec::new();
+    for name in names {
+        if !config::is_glob(name) || config.aliases.contains_key(name) {
+            if !aliases.contains(name) {
+                aliases.push(name.clone());
+            }
+            continue;
+        }
+        let mut matched: Vec<&String> = config
+            .aliases
+            .iter()
+            .filter(|(alias, ac)| !ac.hidden && config::glob_match(name, alias))
+            .map(|(alias, _)| alias)
+            .collect();
+        if matched.is_empty() {
+            anyhow::bail!(UsageError(format!("'{}' doesn't match any alias", name)));
+        }
+        matched.sort();
+        if !quiet {
+            let list = matched.iter().map(|a| a.as_str()).collect::<Vec<_>>().join(", ");
+            println!("{} {} matches {}", prefix(), name.bold(), list.cyan());
+        }
+        for alias in matched {
+            if !aliases.contains(alias) {
+                aliases.push(alias.clone());
+            }
+        }
+    }
+    Ok(aliases)
+}
+
+// cs batch: every name is checked up front so a typo doesn't leave the batch half
+// run. in sequence the first failure stops the rest; in parallel everything runs
+// and the first failure in the order given decides the exit code
+fn run_batch(
+    config: &crate::config::Config,
+    config_path: &Path,
+    aliases: &[String],
+    parallel: bool,
+    opts: &RunOptions,
+) -> Result<i32> {
+    let aliases = &expand_patterns(config, aliases, opts.quiet)?;
+    for alias in aliases {
+        if config.resolve(alias)?.is_none() {
+            report_unknown_alias(config, alias, config_path);
+            return Ok(exit_codes::UNKNOWN_ALIAS);
+        }
+    }
+
+    let run_one = |alias: &str| {
+        run_configured_alias(config, alias, &[], opts, &ShellExecutor).unwrap_or_else(|e| {
+            eprintln!("{} {:#}", prefix(), e);
+            exit_codes::for_error(&e)
+        })
+    };
+    let codes: Vec<Option<i32>> = if parallel {
+        thread::scope(|s| {
+            let handles: Vec<_> = aliases.iter().map(|a| s.spawn(|| run_one(a))).collect();
+            handles.into_iter().map(|h| Some(h.join().unwrap_or(exit_codes::FAILURE))).collect()
+        })
+    } else {
+        let mut codes = Vec::new();
+        for alias in aliases {
+            let code = run_one(alias);
+            codes.push(Some(code));
+            if failed(code) {
+                break;
+            }
+        }
+        codes.resize(aliases.len(), None);
+        codes
+    };
+
+    if !opts.quiet {
+        println!("{} Batch:", prefix());
+        for (alias, code) in aliases.iter().zip(&codes) {
+            match code {
+                Some(exit_codes::SUCCESS) => println!("    {} {}", "✓".green(), alias),
+                Some(exit_codes::SKIPPED) => println!("    {} {} {}", "↷".yellow(), alias, "(skipped)".yellow()),
+                Some(code) => println!("    {} {} {}", "✗".red(), alias, format!("(exit {})", code).red()),
+                None => println!("    {} {} {}", "–".dimmed(), alias, "(not run)".dimmed()),
+            }
+        }
+    }
+    Ok(codes.into_iter().flatten().find(|&c| failed(c)).unwrap_or(exit_codes::SUCCESS))
+}
+
+// a name that's neither a subcommand nor an alias. on a first run that's usually
+// because there's no config here at all, so say what was (and wasn't) found
+fn report_unknown_alias(config: &crate::config::Config, alias: &str, config_path: &Path) {
+    let row = |label: &str, value: String| eprintln!("    {:<12} {}", label.dimmed(), value);
+    let count = |path: &Path| match config.sources.values().filter(|p| *p == path).count() {
+        1 => "1 alias".to_string(),
+        n => format!("{} aliases", n),
+    };
+
+    eprintln!("{} '{}' isn't a cs command or an alias", prefix(), alias);
+    let local = std::path::absolute(config_path).unwrap_or_else(|_| config_path.to_path_buf());
+    if config_path.exists() {
+        row("config", format!("{} ({})", local.display(), count(config_path)));
+    } else {
+        row("config", format!("{} {}", local.display(), "(not found; cs init or cs add creates it)".yellow()));
+    }
+    let global = global_config_path();
+    match load_global_config() {
+        Ok(_) if global.exists() => row("global", format!("{} ({})", global.display(), count(&global))),
+        Ok(_) => row("global", format!("{} (not found)", global.display())),
+        Err(e) => row("global", format!("{} {}", global.display(), format!("(skipped, {:#})", e).red())),
+    }
+
+    let builtins: Vec<String> = Cli::command().get_subcommands().map(|c| c.get_name().to_string()).collect();
+    let suggestions = closest_names(alias, config.aliases.keys().chain(builtins.iter()));
+    if !suggestions.is_empty() {
+        row("did you mean", suggestions.join(", ").cyan().to_string());
+    }
+    eprintln!("{}", "    (cs list shows every alias, cs --help the built-in commands)".dimmed());
+}
+
+// what a bare `cs` does: the config's default_alias as if it was typed, or help
+// (None) when there's no default. a default that doesn't resolve is warned about
+fn default_command(config: Option<&Config>) -> Option<Commands> {
+    let config = config?;
+    let alias = config.default_alias.as_ref()?;
+    match config.resolve(alias) {
+        Ok(Some(_)) => Some(Commands::External(vec![alias.clone()])),
+        Ok(None) => {
+            eprintln!("{} {} default_alias '{}' doesn't exist, showing help instead", prefix(), "⚠".yellow(), alias);
+            None
+        }
+        Err(e) => {
+            eprintln!("{} {} default_alias '{}' can't run ({}), showing help instead", prefix(), "⚠".yellow(), alias, e);
+            None
+        }
+    }
+}
+
+// up to three names a typo away from `wanted`, nearest first
+fn closest_names<'a>(wanted: &str, names: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let limit = (wanted.chars().count() / 3).max(1);
+    let mut scored: Vec<(usize, &String)> = names
+        .map(|n| (edit_distance(wanted, n), n))
+        .filter(|(d, n)| *d <= limit || (wanted.len() > 1 && n.starts_with(wanted)))
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored.into_iter().take(3).map(|(_, n)| n.clone()).collect()
+}
+
+// cs info: counts over everything reachable by name, plus the files it came from
+fn print_info(config_path: &Path) -> Result<()> {
+    let config = load_merged_config(config_path)?;
+    let row = |label: &str, value: String| println!("    {:<13} {}", label.dimmed(), value);
+    let file = |path: &Path| match std::fs::metadata(path) {
+        Ok(meta) => format!("{} ({})", path.display(), human_size(meta.len())),
+        Err(_) => format!("{} {}", path.display(), "(not found)".dimmed()),
+    };
+
+    let aliases: Vec<&AliasConfig> = config.aliases.values().collect();
+    let count = |pred: fn(&AliasConfig) -> bool| aliases.iter().filter(|ac| pred(ac)).count();
+    let local = config.sources.values().filter(|p| *p == config_path).count();
+    // anything else came from an "include" of one of the two
+    let global = config.sources.values().filter(|p| **p == global_config_path()).count();
+    let included = aliases.len() - local - global;
+
+    println!("{} {}", prefix(), config.identifier.as_deref().unwrap_or("cs info").bold());
+    row("config", file(&std::path::absolute(config_path).unwrap_or(config_path.to_path_buf())));
+    row("global", file(&global_config_path()));
+    row(
+        "aliases",
+        if included > 0 {
+            format!("{} ({} local, {} global, {} included)", aliases.len(), local, global, included)
+        } else {
+            format!("{} ({} local, {} global)", aliases.len(), local, global)
+        },
+    );
+    row(
+        "kinds",
+        format!(
+            "{} single, {} parallel, {} steps, {} other names",
+            count(|ac| matches!(ac.entry, AliasEntry::Single(_))),
+            count(|ac| matches!(ac.entry, AliasEntry::Parallel(_))),
+            count(|ac| matches!(ac.entry, AliasEntry::Steps(_))),
+            count(|ac| matches!(ac.entry, AliasEntry::Alias(_))),
+        ),
+    );
+    row(
+        "described",
+        format!("{} of {}", count(|ac| ac.description.is_some()), aliases.len()),
+    );
+    let disabled = count(|ac| !ac.enabled);
+    let detached = count(|ac| ac.detached);
+    if disabled + detached > 0 {
+        row("flags", format!("{} disabled, {} detached", disabled, detached));
+    }
+    row("variables", config.variables.len().to_string());
+    row(
+        "timing",
+        match config.timing_threshold() {
+            Some(0) => "on".to_string(),
+            Some(ms) => format!("on, over {}ms", ms),
+            None => "off".to_string(),
+        },
+    );
+    if let Some(secs) = config.notify_min_interval_secs {
+        row("notify", format!("at most every {}s", secs));
+    }
+    if let Some(mode) = config.notify_mode.and_then(|m| m.to_possible_value()) {
+        row("notify to", mode.get_name().to_string());
+    }
+    row("shell", runner::invocation(false).join(" "));
+    Ok(())
+}
+
+fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+// history runs cs clean keeps and how old a log gets, when neither the flags nor
+// the config say
+const DEFAULT_HISTORY_KEEP: usize = 1000;
+const DEFAULT_LOG_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// cs clean: the history log, old logs and the detached list, each reported on
+// one line. with dry_run nothing is touched
+fn clean(config_path: &Path, keep: Option<usize>, older_than: Option<Duration>, dry_run: bool) -> Result<i32> {
+    let config = load_merged_config(config_path)?;
+    let keep = keep.or(config.history_keep).unwrap_or(DEFAULT_HISTORY_KEEP);
+    let max_age = match (older_than, &config.log_max_age) {
+        (Some(age), _) => age,
+        (None, Some(age)) => humantime::parse_duration(age)
+            .map_err(|e| ConfigError(format!("log_max_age '{}' isn't a duration: {}", age, e)))?,
+        (None, None) => DEFAULT_LOG_MAX_AGE,
+    };
+    let verb = if dry_run { "would remove" } else { "removed" };
+    let row = |label: &str, value: String| println!("    {:<10} {}", label.dimmed(), value);
+    println!("{} {}", prefix(), if dry_run { "Would clean up" } else { "Cleaning up" });
+
+    let (trimmed, total) = history::trim_history(keep, dry_run)?;
+    if trimmed > 0 {
+        row("history", format!("{} {} of {} runs, keeping the newest {}", verb, trimmed, total, keep));
+    } else {
+        row("history", format!("{} runs, nothing to trim (keeping {})", total, keep));
+    }
+
+    let logs = runner::stale_logs(max_age);
+    let age = humantime::format_duration(max_age);
+    if logs.is_empty() {
+        row("logs", format!("none older than {}", age));
+    } else {
+        let mut freed = 0;
+        for (path, size) in &logs {
+            if !dry_run {
+                std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+            freed += size;
+        }
+        // only goes when it's empty
+        if !dry_run {
+            let _ = std::fs::remove_dir(runner::LOG_DIR);
+        }
+        row("logs", format!("{} {} file(s) older than {} ({})", verb, logs.len(), age, human_size(freed)));
+        for (path, _) in &logs {
+            println!("               {}", path.display().to_string().dimmed());
+        }
+    }
+
+    let (alive, dead): (Vec<_>, Vec<_>) = load_detached().into_iter().partition(is_alive);
+    if dead.is_empty() {
+        row("detached", "no exited processes".to_string());
+    } else {
+        if !dry_run {
+            save_detached(&alive)?;
+        }
+        let pids: Vec<String> = dead.iter().map(|p| format!("{} (pid {})", p.alias, p.pid)).collect();
+        row("detached", format!("{} {} exited process(es): {}", verb, dead.len(), pids.join(", ")));
+    }
+    Ok(exit_codes::SUCCESS)
+}
+
+// cs enable / cs disable
+fn set_enabled(config_path: &Path, global: bool, alias: &str, enabled: bool) -> Result<i32> {
+    let _lock = lock_target(config_path, global)?;
+    let mut config = if global { load_global_config()? } else { load_config(config_path)? };
+    let Some(ac) = config.aliases.get_mut(alias) else {
+        eprintln!("Alias '{}' not found.", alias);
+        return Ok(exit_codes::UNKNOWN_ALIAS);
+    };
+    if ac.enabled == enabled {
+        println!("{} {} is already {}.", prefix(), alias.cyan(), if enabled { "enabled" } else { "disabled" });
+        return Ok(exit_codes::SUCCESS);
+    }
+    ac.enabled = enabled;
+    ac.updated_at = Some(rfc3339_now());
+    if global { save_global_config(&config)?; } else { save_config(&config, config_path)?; }
+    if enabled {
+        println!("{} {} enabled.", prefix(), alias.cyan());
+    } else {
+        println!("{} {} disabled.", prefix(), alias.dimmed().strikethrough());
+    }
+    Ok(exit_codes::SUCCESS)
+}
+
+// the lock for the config a command is about to change
+fn lock_target(config_path: &Path, global: bool) -> Result<ConfigLock> {
+    lock_config(&if global { global_config_path() } else { config_path.to_path_buf() })
+}
+
+// the checks `cs config edit` makes before saving, plus the programs each alias
+// requires, for a config file that's already on disk
+fn validate_config_file(path: &Path) -> Result<i32> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| ConfigError(format!("Failed to read config file {}", path.display())))?;
+    let config = match config::parse_config(path, &text) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{} {}: {:#}", "Error:".red(), path.display(), e);
+            return Ok(exit_codes::CONFIG_ERROR);
+        }
+    };
+    let mut problems: Vec<String> = config
+        .empty_parallel()
+        .iter()
+        .map(|name| format!("alias '{}' is a parallel alias with no commands or a blank one", name))
+        .collect();
+    let mut names: Vec<&String> = config.aliases.keys().collect();
+    names.sort();
+    for name in names {
+        let missing = missing_programs(&config.aliases[name].requires);
+        if !missing.is_empty() {
+            problems.push(format!("alias '{}' requires programs missing from PATH: {}", name, missing.join(", ")));
+        }
+    }
+    if problems.is_empty() {
+        println!("{} {} is valid.", prefix(), path.display());
+        config::warn_unknown_fields(path, &text);
+        return Ok(exit_codes::SUCCESS);
+    }
+    for problem in &problems {
+        eprintln!("{} {}: {}", "Error:".red(), path.display(), problem);
+    }
+    Ok(exit_codes::CONFIG_ERROR)
+}
+
+// the editor works on a temp copy. it only replaces the real file once it parses,
+// so a typo or a failed editor never leaves a broken config behind
+fn edit_config_file(path: &Path) -> Result<i32> {
+    if !path.exists() {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        save_config(&Config::default(), path)?;
+        println!("{} created {}", prefix(), path.display());
+    }
+    let original = std::fs::read_to_string(path)
+        .with_context(|| ConfigError(format!("Failed to read config file {}", path.display())))?;
+
+    // same extension as the config, so the editor highlights the right format
+    let ext = if config::is_toml(path) { "toml" } else { "json" };
+    let tmp = std::env::temp_dir().join(format!("cawa_config_{}.{}", unix_now(), ext));
+    std::fs::write(&tmp, &original)?;
+
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    let status = std::process::Command::new(&editor).arg(&tmp).status()?;
+    let edited = std::fs::read_to_string(&tmp)?;
+
+    if !status.success() {
+        let _ = std::fs::remove_file(&tmp);
+        eprintln!("Editor exited with an error, config unchanged.");
+        return Ok(exit_codes::FAILURE);
+    }
+    if edited == original {
+        let _ = std::fs::remove_file(&tmp);
+        println!("{} no changes.", prefix());
+        return Ok(exit_codes::SUCCESS);
+    }
+    let problem = match config::parse_config(path, &edited) {
+        Ok(config) => config
+            .empty_parallel()
+            .first()
+            .map(|name| format!("alias '{}' is a parallel alias with no commands or a blank one", name)),
+        Err(e) => Some(e.to_string()),
+    };
+    if let Some(problem) = problem {
+        // keep the temp file so the edits aren't lost
+        eprintln!("{} {}: {}", "Error:".red(), path.display(), problem);
+        eprintln!("Config unchanged, your edits are in {}", tmp.display());
+        return Ok(exit_codes::CONFIG_ERROR);
+    }
+    // someone else's change since the editor opened would be silently undone
+    let _lock = lock_config(path)?;
+    if std::fs::read_to_string(path).ok().as_ref() != Some(&original) {
+        eprintln!("{} {} changed while you were editing it.", "Error:".red(), path.display());
+        eprintln!("Config unchanged, your edits are in {}", tmp.display());
+        return Ok(exit_codes::FAILURE);
+    }
+    // written as typed rather than re-serialized, so formatting and key order survive
+    std::fs::write(path, &edited).context("Failed to write config file")?;
+    config::forget_merged_config();
+    let _ = std::fs::remove_file(&tmp);
+    println!("{} {} updated.", prefix(), path.display());
+    config::warn_unknown_fields(path, &edited);
+    Ok(exit_codes::SUCCESS)
+}
+
+// a parallel command that is only a shell operator, or starts / ends with one,
+// was almost certainly meant to join its neighbours (`-p make && test` unquoted).
+// a trailing ; or & is left alone since that's valid on its own
+fn dangling_operator(cmd: &str) -> bool {
+    let cmd = cmd.trim();
+    ["&&", "||", "|", ";", "&"].iter().any(|op| cmd.starts_with(op))
+        || ["&&", "||", "|"].iter().any(|op| cmd.ends_with(op))
+}
+
+// the --verbose block: where the alias came from and what it turned into
+fn print_resolution(
+    config: &crate::config::Config,
+    alias: &str,
+    resolved_name: &str,
+    ac: &AliasConfig,
+    extra_args: &[String],
+    opts: &RunOptions,
+) {
+    let row = |label: &str, value: String| eprintln!("    {:<10} {}", label.dimmed(), value);
+
+    eprintln!("{} Resolving '{}'", prefix(), alias);
+    if resolved_name != alias {
+        row("alias", format!("{} → {}", alias, resolved_name));
+    }
+    let source = match config.sources.get(resolved_name) {
+        Some(path) if *path == global_config_path() => format!("{} (global)", path.display()),
+        Some(path) => format!("{} (local)", path.display()),
+        None => "unknown".to_string(),
+    };
+    row("source", source);
+    let cmds = match &ac.entry {
+        AliasEntry::Single(cmd) => vec![cmd.clone()],
+        AliasEntry::Parallel(cmds) => cmds.clone(),
+        AliasEntry::Steps(steps) => steps.iter().map(|s| s.command.clone()).collect(),
+        AliasEntry::Alias(target) => vec![format!("↪ {}", target)],
+    };
+    for cmd in cmds {
+        row("command", build_command(&cmd, extra_args, ac.pipe_to.as_deref()).cyan().to_string());
+    }
+    let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_else(|_| "?".to_string());
+    row("cwd", cwd);
+    if let Some(t) = ac.timeout_secs {
+        row("timeout", format!("{}s", t));
+    }
+    if let Some(t) = &ac.created_at {
+        row("created", t.clone());
+    }
+    if let Some(t) = &ac.updated_at {
+        row("updated", t.clone());
+    }
+    if let Some(ms) = opts.stagger_ms.or(ac.stagger_ms) {
+        row("stagger", format!("{}ms", ms));
+    }
+    let exec = opts.exec_options(ac);
+    if let Some(n) = exec.nice {
+        row("nice", n.to_string());
+    }
+    if let Some(path) = &exec.stdin_file {
+        row("stdin", path.display().to_string());
+    }
+    if let Some(user) = &exec.run_as {
+        row("run as", user.clone());
+    }
+    let mut limits = Vec::new();
+    if let Some(mb) = exec.memory_limit_mb {
+        limits.push(format!("{} MB memory", mb));
+    }
+    if let Some(secs) = exec.cpu_seconds {
+        limits.push(format!("{}s CPU", secs));
+    }
+    if !limits.is_empty() {
+        row("limits", limits.join(", "));
+    }
+    if let Some(p) = &ac.success_pattern {
+        let rescue = if ac.success_overrides_exit { " (overrides the exit code)" } else { "" };
+        row("succeeds", format!("/{}/{}", p, rescue));
+    }
+    if let Some(p) = &ac.failure_pattern {
+        row("fails", format!("/{}/", p));
+    }
+    if let Some(policy) = retry_policy(ac) {
+        let delays: Vec<String> = (1..=policy.retries)
+            .map(|n| format_duration(runner::retry_delay(&policy, n, None), output::timing_format()))
+            .collect();
+        let backoff = match policy.backoff {
+            RetryBackoff::Fixed => "",
+            RetryBackoff::Linear => ", linear",
+            RetryBackoff::Exponential => ", exponential",
+        };
+        let jitter = if policy.jitter { ", with jitter" } else { "" };
+        row("retries", format!("{} (waits {}{}{})", policy.retries, delays.join(", "), backoff, jitter));
+    }
+    for var in runner::env_vars() {
+        let value = config.mask_env(&var.name, &var.value);
+        row("env", format!("{}={} {}", var.name, value, format!("({})", var.source.label()).dimmed()));
+    }
+    let mut flags = Vec::new();
+    if exec.suppress_stdout {
+        flags.push("quiet stdout");
+    }
+    if exec.suppress_stderr {
+        flags.push("quiet stderr");
+    }
+    if exec.login_shell {
+        flags.push("login shell");
+    }
+    if exec.pty {
+        flags.push("pty");
+    }
+    if opts.detach || ac.detached {
+        flags.push("detached");
+    }
+    if opts.dry_run {
+        flags.push("dry run");
+    }
+    if !flags.is_empty() {
+        row("options", flags.join(", "));
+    }
+}
+
+// hand every command of the alias to the background and report the pids
+fn start_detached(run: &ResolvedRun, opts: &RunOptions) -> Result<i32> {
+    if opts.dry_run {
+        for cmd in &run.commands {
+            println!("{} Would run (detached): {}", prefix(), cmd.cyan());
+        }
+        return Ok(exit_codes::SUCCESS);
+    }
+    for (cmd, p) in spawn_all_detached(run, opts)? {
+        if !opts.quiet {
+            println!(
+                "{} Detached: {} {} → {}",
+                prefix(),
+                cmd.cyan(),
+                format!("(pid {})", p.pid).yellow(),
+                p.log.display()
+            );
+        }
+    }
+    Ok(exit_codes::SUCCESS)
+}
+
+// each command with the process it was started as
+fn spawn_all_detached<'a>(run: &'a ResolvedRun, opts: &RunOptions) -> Result<Vec<(&'a str, DetachedProcess)>> {
+    let several = run.commands.len() > 1;
+    let mut started = Vec::new();
+    for (i, cmd) in run.commands.iter().enumerate() {
+        let p = spawn_detached(&run.alias, several.then_some(i + 1), cmd, &opts.exec_options(&run.ac))?;
+        started.push((cmd.as_str(), p));
+    }
+    Ok(started)
+}
+
+// why the alias shouldn't run right now, if anything. files are checked first since
+// that's free; the condition is a real command, so dry runs only print it
+fn unmet_precondition(
+    ac: &AliasConfig,
+    variables: &HashMap<String, String>,
+    opts: &RunOptions,
+    exec: &dyn Executor,
+) -> Result<Option<String>> {
+    for file in &ac.require_files {
+        let path = expand_path(&expand_variables(file, variables)?);
+        if !path.exists() {
+            return Ok(Some(format!("{} does not exist", path.display())));
+        }
+    }
+
+    let Some(condition) = &ac.condition else {
+        return Ok(None);
+    };
+    let condition = expand_variables(condition, variables)?;
+    if opts.dry_run {
+        println!("{} Would check: {}", prefix(), condition.cyan());
+        return Ok(None);
+    }
+    let quiet = ExecOptions {
+        timeout_secs: ac.timeout_secs,
+        suppress_stdout: true,
+        suppress_stderr: true,
+        login_shell: opts.login || ac.login_shell,
+        ..Default::default()
+    };
+    if exec.run(&condition, &quiet) != exit_codes::SUCCESS {
+        return Ok(Some(format!("condition `{}` failed", condition)));
+    }
+    Ok(None)
+}
+
+// one command under --output json: start / end events around it and its output
+// as output_line events
+fn run_with_events(exec: &dyn Executor, alias: Option<&str>, index: usize, cmd: &str, exec_opts: &ExecOptions) -> i32 {
+    let start = Instant::now();
+    events::emit(&Event::CommandStart { alias, index, command: cmd });
+    let owned = alias.map(str::to_string);
+    let sink: LineSink = Arc::new(move |stream, line| {
+        events::emit(&Event::OutputLine { alias: owned.as_deref(), index, stream, line });
+    });
+    let code = exec.run_lines(cmd, exec_opts, sink);
+    events::emit(&Event::CommandEnd { alias, index, exit_code: code, duration_ms: start.elapsed().as_millis() as u64 });
+    code
+}
+
+// None when the alias doesn't retry
+fn retry_policy(ac: &AliasConfig) -> Option<RetryPolicy> {
+    (ac.retries > 0).then(|| RetryPolicy {
+        retries: ac.retries,
+        delay_ms: ac.retry_delay_ms.unwrap_or(DEFAULT_RETRY_DELAY_MS),
+        backoff: ac.retry_backoff,
+        jitter: ac.retry_jitter,
+        max_delay_ms: ac.retry_max_delay_ms.unwrap_or(DEFAULT_RETRY_MAX_DELAY_MS),
+    })
+}
+
+// expects the alias with variables already expanded. alias is None for `cs run`
 fn run_entry(
-    entry: &AliasEntry,
+    alias: Option<&str>,
+    ac: &AliasConfig,
     extra_args: &[String],
-    enable_timing: bool,
-    dry_run: bool,
-    timeout_secs: Option<u64>,
-) -> Result<bool> {
+    timing_threshold: Option<u64>,
+    opts: &RunOptions,
+    exec: &dyn Executor,
+) -> Result<i32> {
     let start = Instant::now();
+    let exec_opts = &opts.exec_options(ac);
+    let pipe_to = ac.pipe_to.as_deref();
+    let matchers = OutputMatchers::new(ac.success_pattern.as_deref(), ac.failure_pattern.as_deref(), ac.success_overrides_exit)?;
+    let matching;
+    let exec: &dyn Executor = match &matchers {
+        Some(matchers) => {
+            matching = MatchingExecutor { inner: exec, matchers };
+            &matching
+        }
+        None => exec,
+    };
+    // outside the matchers, so a run their patterns failed is retried too
+    let policy = retry_policy(ac);
+    let retrying;
+    let exec: &dyn Executor = match &policy {
+        Some(policy) => {
+            retrying = RetryingExecutor { inner: exec, policy };
+            &retrying
+        }
+        None => exec,
+    };
+    let run_end = |code: i32| {
+        if opts.json {
+            let duration_ms = start.elapsed().as_millis() as u64;
+            events::emit(&Event::RunEnd {
+                alias,
+                exit_code: code,
+                duration_ms,
+                timed_out: code == exit_codes::TIMED_OUT,
+                skipped: code == exit_codes::SKIPPED,
+            });
+        }
+    };
+    if opts.json {
+        let commands: Vec<String> = match &ac.entry {
+            AliasEntry::Single(cmd) => vec![build_command(cmd, extra_args, pipe_to)],
+            AliasEntry::Parallel(cmds) => cmds.iter().map(|c| build_command(c, extra_args, pipe_to)).collect(),
+            AliasEntry::Steps(steps) => steps.iter().map(|s| build_command(&s.command, extra_args, pipe_to)).collect(),
+            AliasEntry::Alias(_) => Vec::new(),
+        };
+        events::emit(&Event::RunStart { alias, commands: &commands });
+    }
 
-    let success = match entry {
+    let code = match &ac.entry {
         AliasEntry::Single(cmd) => {
-            let final_cmd = if !extra_args.is_empty() {
-                format!("{} {}", cmd, extra_args.join(" "))
-            } else {
-                cmd.clone()
-            };
-            if dry_run {
-                println!("{} Would run: {}", "🐙".truecolor(80, 80, 80), final_cmd.cyan());
-                true
+            let final_cmd = build_command(cmd, extra_args, pipe_to);
+            // a single command owns the terminal, so it always gets stdin
+            let single_opts = ExecOptions { inherit_stdin: true, ..exec_opts.clone() };
+            if opts.dry_run {
+                if !opts.json {
+                    println!("{} Would run: {}", prefix(), final_cmd.cyan());
+                }
+                exit_codes::SUCCESS
+            } else if opts.json {
+                run_with_events(exec, alias, 0, &final_cmd, &single_opts)
             } else {
-                println!("{} Executing: {}", "🐙".truecolor(80, 80, 80), final_cmd.cyan());
-                execute_command(&final_cmd, timeout_secs)
+                if !opts.quiet {
+                    println!("{} Executing: {}", prefix(), final_cmd.cyan());
+                }
+                exec.run(&final_cmd, &single_opts)
             }
         }
         AliasEntry::Parallel(cmds) => {
-            if dry_run {
-                println!("{} Would run (parallel):", "🐙".truecolor(80, 80, 80));
-                for cmd in cmds {
-                    let full = if !extra_args.is_empty() {
-                        format!("{} {}", cmd, extra_args.join(" "))
-                    } else {
-                        cmd.clone()
-                    };
-                    println!("    {} {}", "└".dimmed(), full.cyan());
+            if opts.dry_run {
+                if !opts.json {
+                    println!("{} Would run (parallel):", prefix());
+                    for cmd in cmds {
+                        println!("    {} {}", "└".dimmed(), build_command(cmd, extra_args, pipe_to).cyan());
+                    }
                 }
-                return Ok(true);
+                run_end(exit_codes::SUCCESS);
+                return Ok(exit_codes::SUCCESS);
             }
 
-            println!("{} Executing (parallel): {:?}", "🐙".truecolor(80, 80, 80), cmds);
+            if !opts.quiet {
+                println!("{} Executing (parallel):", prefix());
+            }
+            // the numbered list is the noisy part; --quiet-out drops it with the
+            // commands' own stdout
+            if !opts.quiet && !opts.suppress_stdout {
+                for (i, cmd) in cmds.iter().enumerate() {
+                    println!("    {} {}", format!("{}.", i + 1).dimmed(), build_command(cmd, extra_args, pipe_to).cyan());
+                }
+            }
 
-            let failure_occurred = Arc::new(AtomicBool::new(false));
-            let mut handles = vec![];
+            // --jobs caps how many run at once; workers pull the next command off a
+            // shared queue until they run out. results are kept by position
+            let jobs = opts.jobs.unwrap_or(cmds.len()).clamp(1, cmds.len().max(1));
+            // with a stagger, each command also gets a start time at least that long after
+            // the previous one's. both are handed out under the same lock so launches
+            // still happen in config order
+            let stagger = Duration::from_millis(opts.stagger_ms.or(ac.stagger_ms).unwrap_or(0));
+            let queue: Mutex<(usize, Option<Instant>)> = Mutex::new((0, None));
+            let results: Vec<Mutex<Option<i32>>> = cmds.iter().map(|_| Mutex::new(None)).collect();
+            // --group output waits in temp files until everything's done
+            let group_files = if opts.group && !opts.json { GroupFiles::create(cmds.len())? } else { GroupFiles(Vec::new()) };
 
-            for cmd in cmds {
-                // append extra args to each sub-command, same as single aliases do
-                let cmd_str = if !extra_args.is_empty() {
-                    format!("{} {}", cmd, extra_args.join(" "))
-                } else {
-                    cmd.clone()
-                };
-                let fail_flag = failure_occurred.clone();
-                handles.push(thread::spawn(move || {
-                    if !execute_command(&cmd_str, timeout_secs) {
-                        fail_flag.store(true, Ordering::Relaxed);
+            // scoped so the threads can borrow the executor instead of needing it 'static
+            thread::scope(|s| {
+                let handles: Vec<_> = (0..jobs)
+                    .map(|_| {
+                        s.spawn(|| {
+                            loop {
+                                let Ok((i, start_at)) = queue.lock().map(|mut q| {
+                                    let i = q.0;
+                                    let at = q.1.map_or_else(Instant::now, |prev| (prev + stagger).max(Instant::now()));
+                                    *q = (i + 1, Some(at));
+                                    (i, at)
+                                }) else {
+                                    break;
+                                };
+                                let Some(cmd) = cmds.get(i) else { break };
+                                thread::sleep(start_at.saturating_duration_since(Instant::now()));
+                                // append extra args to each sub-command, same as single aliases do
+                                let cmd_str = build_command(cmd, extra_args, pipe_to);
+                                let code = if opts.json {
+                                    run_with_events(exec, alias, i, &cmd_str, exec_opts)
+                                } else if let Some((out, err)) = group_files.open(i) {
+                                    exec.run_to_files(&cmd_str, exec_opts, out, err)
+                                } else if opts.group {
+                                    eprintln!("{} Couldn't open the temp files for --group", prefix());
+                                    exit_codes::FAILURE
+                                } else {
+                                    exec.run(&cmd_str, exec_opts)
+                                };
+                                if let Ok(mut slot) = results[i].lock() {
+                                    *slot = Some(code);
+                                }
+                            }
+                        })
+                    })
+                    .collect();
+                for h in handles {
+                    let _ = h.join();
+                }
+            });
+
+            // walked in config order, so grouped blocks come out in that order too.
+            // a missing result means its worker panicked
+            let codes: Vec<i32> = results
+                .into_iter()
+                .zip(cmds)
+                .enumerate()
+                .map(|(i, (slot, cmd))| {
+                    let code = slot.into_inner().ok().flatten().unwrap_or(exit_codes::FAILURE);
+                    if let Some(paths) = group_files.0.get(i) {
+                        print_group(cmd, code, paths, opts.quiet);
+                    }
+                    code
+                })
+                .collect();
+
+            // report the first failure in config order, not whichever finished first.
+            // the run only counts as skipped when every command skipped
+            match codes.iter().find(|&&c| failed(c)) {
+                Some(&code) => code,
+                None if codes.iter().all(|&c| c == exit_codes::SKIPPED) => exit_codes::SKIPPED,
+                None => exit_codes::SUCCESS,
+            }
+        }
+        AliasEntry::Steps(steps) => {
+            let cmds: Vec<String> = steps.iter().map(|s| build_command(&s.command, extra_args, pipe_to)).collect();
+            if opts.dry_run {
+                if !opts.json {
+                    println!("{} Would run (steps):", prefix());
+                    for (step, cmd) in steps.iter().zip(&cmds) {
+                        println!("    {} {} {}", "▶".dimmed(), step.name, cmd.cyan());
                     }
-                }));
+                }
+                run_end(exit_codes::SUCCESS);
+                return Ok(exit_codes::SUCCESS);
+            }
+            run_steps(alias, steps, &cmds, opts, exec, exec_opts)
+        }
+        AliasEntry::Alias(target) => anyhow::bail!("alias '{}' wasn't resolved before running", target),
+    };
+
+    // only report here; exiting is left to main so notifications always go out first.
+    // json consumers get the duration from run_end instead
+    run_end(code);
+    if code == exit_codes::SKIPPED && !opts.quiet {
+        println!("{} {}", prefix(), "Skipped: nothing to do".yellow());
+    }
+    if let Some(threshold) = timing_threshold.filter(|_| !opts.json) {
+        let duration = start.elapsed();
+        if exceeds_threshold(duration, threshold) {
+            let took = format_duration(duration, output::timing_format());
+            if !failed(code) {
+                println!("{}{}{}", prefix(), stopwatch(), took);
+            } else {
+                eprintln!("{}{}{} (Failed)", prefix(), stopwatch(), took);
             }
+        }
+    }
+
+    Ok(code)
+}
 
-            for h in handles {
-                let _ = h.join();
+// a steps alias: a ▶ header before each step and its time after, stopping at the
+// first failure. a skipped step doesn't stop the rest, like in a batch. the
+// summary at the end shows every step's time, which one broke and what didn't run
+fn run_steps(
+    alias: Option<&str>,
+    steps: &[Step],
+    cmds: &[String],
+    opts: &RunOptions,
+    exec: &dyn Executor,
+    exec_opts: &ExecOptions,
+) -> i32 {
+    // one at a time, so each step can have the terminal like a single command
+    let step_opts = ExecOptions { inherit_stdin: true, ..exec_opts.clone() };
+    let format = output::timing_format();
+    let mut results: Vec<(i32, Duration)> = Vec::new();
+    for (i, (step, cmd)) in steps.iter().zip(cmds).enumerate() {
+        if !opts.quiet {
+            println!("{} {} {}", prefix(), "▶".cyan(), step.name.bold());
+        }
+        let start = Instant::now();
+        let code = if opts.json { run_with_events(exec, alias, i, cmd, &step_opts) } else { exec.run(cmd, &step_opts) };
+        let took = start.elapsed();
+        results.push((code, took));
+        if !opts.quiet {
+            let took = format_duration(took, format);
+            match code {
+                exit_codes::SUCCESS => println!("{} {} {} {}", prefix(), "✓".green(), step.name, took.dimmed()),
+                exit_codes::SKIPPED => println!("{} {} {} {}", prefix(), "↷".yellow(), step.name, "(skipped)".yellow()),
+                _ => eprintln!("{} {} {} {}", prefix(), "✗".red(), step.name, format!("failed after {} (exit {})", took, code).red()),
             }
+        }
+        if failed(code) {
+            break;
+        }
+    }
 
-            !failure_occurred.load(Ordering::Relaxed)
+    if !opts.quiet {
+        let width = steps.iter().map(|s| s.name.chars().count()).max().unwrap_or(0);
+        println!("{} Steps:", prefix());
+        for (i, step) in steps.iter().enumerate() {
+            let name = format!("{:<width$}", step.name);
+            match results.get(i) {
+                Some(&(exit_codes::SKIPPED, _)) => println!("    {} {} {}", "↷".yellow(), name, "(skipped)".yellow()),
+                Some(&(code, took)) if failed(code) => println!(
+                    "    {} {} {} {}",
+                    "✗".red(),
+                    name,
+                    format_duration(took, format),
+                    format!("(exit {})", code).red()
+                ),
+                Some(&(_, took)) => println!("    {} {} {}", "✓".green(), name, format_duration(took, format)),
+                None => println!("    {} {} {}", "–".dimmed(), name, "(not run)".dimmed()),
+            }
         }
+    }
+
+    match results.iter().find(|(c, _)| failed(*c)) {
+        Some(&(code, _)) => code,
+        None if results.iter().all(|(c, _)| *c == exit_codes::SKIPPED) => exit_codes::SKIPPED,
+        None => exit_codes::SUCCESS,
+    }
+}
+
+// one contiguous block per parallel command, like make --output-sync
+fn print_group(cmd: &str, code: i32, (out, err): &(PathBuf, PathBuf), quiet: bool) {
+    if !quiet {
+        let status = match code {
+            exit_codes::SUCCESS => "✓".green(),
+            exit_codes::SKIPPED => "↷".yellow(),
+            _ => "✗".red(),
+        };
+        println!("{} {} {}", "──".dimmed(), status, cmd.cyan());
+    }
+    // copied straight from disk so a huge log never has to fit in memory
+    if let Ok(mut f) = std::fs::File::open(out) {
+        let _ = io::copy(&mut f, &mut io::stdout().lock());
+    }
+    let _ = io::stdout().flush();
+    if let Ok(mut f) = std::fs::File::open(err) {
+        let _ = io::copy(&mut f, &mut io::stderr().lock());
+    }
+}
+
+// a stdout / stderr temp file pair per parallel command for --group. they're
+// deleted on drop, so an error or a panic partway through doesn't leave them behind
+struct GroupFiles(Vec<(PathBuf, PathBuf)>);
+
+impl GroupFiles {
+    fn create(count: usize) -> Result<GroupFiles> {
+        let mut files = GroupFiles(Vec::new());
+        for _ in 0..count {
+            let out = Self::create_new("out")?;
+            let err = Self::create_new("err").inspect_err(|_| {
+                // not tracked yet, so Drop wouldn't clean it up
+                let _ = std::fs::remove_file(&out);
+            })?;
+            files.0.push((out, err));
+        }
+        Ok(files)
+    }
+
+    // create_new refuses to follow a file or symlink someone planted in the shared temp dir,
+    // and the random suffix keeps the name from being guessed up front
+    fn create_new(ext: &str) -> Result<PathBuf> {
+        use std::hash::{BuildHasher, Hasher};
+        let dir = std::env::temp_dir();
+        for _ in 0..16 {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_u32(std::process::id());
+            hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0));
+            let path = dir.join(format!("cawa_group_{:016x}.{}", hasher.finish(), ext));
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(path),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e).context("Failed to create a temp file for --group"),
+            }
+        }
+        anyhow::bail!("Failed to create a temp file for --group: too many name collisions")
+    }
+
+    // writable handles for command i, None without --group
+    fn open(&self, i: usize) -> Option<(std::fs::File, std::fs::File)> {
+        let (out, err) = self.0.get(i)?;
+        let open = |p: &PathBuf| std::fs::OpenOptions::new().write(true).truncate(true).open(p).ok();
+        Some((open(out)?, open(err)?))
+    }
+}
+
+impl Drop for GroupFiles {
+    fn drop(&mut self) {
+        for (out, err) in &self.0 {
+            let _ = std::fs::remove_file(out);
+            let _ = std::fs::remove_file(err);
+        }
+    }
+}
+
+// run an alias warmup + runs times with its output silenced and print duration stats
+fn bench_alias(
+    config: &crate::config::Config,
+    alias: &str,
+    extra_args: &[String],
+    runs: u32,
+    warmup: u32,
+    cli: &RunOptions,
+) -> Result<i32> {
+    if runs == 0 {
+        anyhow::bail!(UsageError("--runs must be at least 1".to_string()));
+    }
+    // the same checks as a plain run: a disabled alias, a missing program or an
+    // unmet precondition stops it before the first timed run
+    let opts = RunOptions {
+        quiet: true,
+        suppress_stdout: true,
+        suppress_stderr: true,
+        include_disabled: cli.include_disabled,
+        force: cli.force,
+        ignore_args: cli.ignore_args,
+        ..Default::default()
+    };
+    let run = match prepare_run(config, alias, extra_args, &opts, &QuietExecutor)? {
+        Prepared::Unknown => {
+            eprintln!("Unknown command or alias: {}", alias);
+            return Ok(exit_codes::UNKNOWN_ALIAS);
+        }
+        Prepared::Skip(reason) => {
+            eprintln!("{} Skipping '{}': {}", prefix(), alias, reason);
+            return Ok(exit_codes::SUCCESS);
+        }
+        Prepared::Ready(run) => run,
     };
 
-    if enable_timing {
-        // round to ms so humantime doesn't print nanoseconds
-        let duration = Duration::from_millis(start.elapsed().as_millis() as u64);
-        if success {
-            println!("{}⏱️  {}", "🐙".truecolor(80, 80, 80), humantime::format_duration(duration));
-        } else {
-            eprintln!("{}⏱️  {} (Failed)", "🐙".truecolor(80, 80, 80), humantime::format_duration(duration));
-            return Ok(false);
+    println!(
+        "{} Benchmarking {} ({} runs, {} warmup)",
+        prefix(),
+        alias.bold(),
+        runs,
+        warmup
+    );
+
+    let mut samples = Vec::with_capacity(runs as usize);
+    for i in 0..(warmup + runs) {
+        let start = Instant::now();
+        let code = run_entry(Some(alias), &run.ac, &run.extra_args, None, &opts, &QuietExecutor)?;
+        if code != exit_codes::SUCCESS {
+            eprintln!(
+                "{} Run {} failed; run `{} {}` directly to see its output.",
+                prefix(),
+                i + 1,
+                get_program_name(),
+                alias
+            );
+            return Ok(code);
+        }
+        if i >= warmup {
+            samples.push(start.elapsed());
         }
-    } else if !success {
-        return Ok(false);
     }
 
-    Ok(true)
+    samples.sort();
+    let secs: Vec<f64> = samples.iter().map(|d| d.as_secs_f64()).collect();
+    let mean = secs.iter().sum::<f64>() / secs.len() as f64;
+    let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / secs.len() as f64;
+    let median = if secs.len().is_multiple_of(2) {
+        (secs[secs.len() / 2 - 1] + secs[secs.len() / 2]) / 2.0
+    } else {
+        secs[secs.len() / 2]
+    };
+
+    // round to ms so humantime doesn't print nanoseconds
+    let fmt = |s: f64| humantime::format_duration(Duration::from_millis((s * 1000.0).round() as u64)).to_string();
+    println!("    {:<7} {}", "min", fmt(secs[0]).cyan());
+    // stddev is usually well under a second, so keep sub-ms precision for it
+    let stddev = format!("{:.1}ms", variance.sqrt() * 1000.0);
+    println!("    {:<7} {} {} {}", "mean", fmt(mean).cyan(), "±".dimmed(), stddev.dimmed());
+    println!("    {:<7} {}", "median", fmt(median).cyan());
+    println!("    {:<7} {}", "max", fmt(secs[secs.len() - 1]).cyan());
+
+    Ok(exit_codes::SUCCESS)
+}
+
+// an explicit always/never in the config wins (the NO_COLOR convention lets config
+// override it); otherwise NO_COLOR turns color off, and so does piping stdout
+fn apply_color(mode: Option<ColorMode>) {
+    let enabled = match mode {
+        Some(ColorMode::Always) => true,
+        Some(ColorMode::Never) => false,
+        Some(ColorMode::Auto) | None => {
+            std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+        }
+    };
+    colored::control::set_override(enabled);
+}
+
+// a threshold of 0 reports everything; otherwise the run has to be strictly slower
+fn exceeds_threshold(duration: Duration, threshold_ms: u64) -> bool {
+    threshold_ms == 0 || duration.as_millis() > threshold_ms as u128
 }
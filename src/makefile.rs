@@ -0,0 +1,178 @@
+use crate::config::{AliasEntry, Config, expand_entry, expand_items, param_names, scan_braces, shell_quote};
+use crate::resolve::build_command;
+
+// `cs export --format make`: the aliases as a Makefile, for teams that would
+// rather type `make test`. only the commands make it over: timeouts, conditions,
+// env files and the like stay cs features. like the man page, the output only
+// changes when the config does
+
+// what `make help` prints: every target line with a ## comment
+const HELP_RECIPE: &str =
+    "@grep -E '^[A-Za-z0-9_.+-]+:.*## ' $(MAKEFILE_LIST) | awk 'BEGIN {FS = \":.*## \"}; {printf \"  %-20s %s\\n\", $$1, $$2}'";
+
+// make is happy with these in a target name. anything else becomes _, and a name
+// with nothing usable left (or one make would read as an option or a special
+// target) has no target at all
+fn target_name(alias: &str) -> Option<String> {
+    let name: String =
+        alias.chars().map(|c| if c.is_ascii_alphanumeric() || "_.+-".contains(c) { c } else { '_' }).collect();
+    if name.starts_with(['-', '.']) || !name.chars().any(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(name)
+}
+
+// one command as make wants it in a recipe. $ is make's own, so the shell's are
+// doubled first. a {param} becomes the make variable of that name, quoted the way
+// cs quotes values, and the built-in tokens are worked out when the target runs.
+// other braces are left as they are
+fn recipe_command(cmd: &str, target: &str, params: &[String], append_args: bool, pipe_to: Option<&str>) -> String {
+    let cmd = scan_braces(&cmd.replace('$', "$$"), |name| match name {
+        "cwd" => Some("$(CURDIR)".to_string()),
+        "date" => Some("$$(date -u +%F)".to_string()),
+        "time" => Some("$$(date -u +%T)".to_string()),
+        "alias" => Some(target.to_string()),
+        "host" => Some("$$(hostname)".to_string()),
+        param if params.iter().any(|p| p == param) => Some(format!("'$({})'", param)),
+        _ => None,
+    });
+    let args = if append_args { vec!["$(ARGS)".to_string()] } else { Vec::new() };
+    build_command(&cmd, &args, pipe_to.map(|p| p.replace('$', "$$")).as_deref())
+}
+
+// a make recipe line is one line of shell, a script spread over several can't be one
+fn multi_line(entry: &AliasEntry) -> bool {
+    match entry {
+        AliasEntry::Single(cmd) => cmd.contains('\n'),
+        AliasEntry::Parallel(cmds) => cmds.iter().any(|c| c.contains('\n')),
+        AliasEntry::Steps(steps) => steps.iter().any(|s| s.command.contains('\n')),
+        AliasEntry::Alias(_) => false,
+    }
+}
+
+// returns the Makefile, and the aliases that were left out with the reason why
+pub fn render(config: &Config, include_hidden: bool) -> (String, Vec<(String, String)>) {
+    let mut names: Vec<&String> =
+        config.aliases.iter().filter(|(_, ac)| include_hidden || !ac.hidden).map(|(n, _)| n).collect();
+    // names that are fine as they are get their target before any that had to be
+    // changed can take it
+    names.sort_by_key(|n| (target_name(n).as_deref() != Some(n.as_str()), n.as_str()));
+    // an alias called help beats the generated one
+    let own_help = !names.iter().any(|n| *n == "help");
+
+    let mut rules: Vec<(String, String)> = Vec::new();
+    let mut skipped: Vec<(String, String)> = Vec::new();
+    for name in names {
+        let mut skip = |reason: String| skipped.push((name.clone(), reason));
+        let Some(target) = target_name(name) else {
+            skip("the name can't be a make target".to_string());
+            continue;
+        };
+        if rules.iter().any(|(t, _)| *t == target) || (own_help && target == "help") {
+            skip(format!("its target '{}' is already taken", target));
+            continue;
+        }
+        // another name gets its own copy of the recipe, so it works even when
+        // what it points at is hidden
+        let (resolved_name, ac) = match config.resolve(name) {
+            Ok(Some(found)) => found,
+            Ok(None) => continue,
+            Err(e) => {
+                skip(e.to_string());
+                continue;
+            }
+        };
+        if !config.aliases[name].enabled || !ac.enabled {
+            skip("it's disabled".to_string());
+            continue;
+        }
+        let entry = match expand_entry(&ac.entry, config) {
+            Ok(entry) => expand_items(&entry, &ac.items),
+            Err(e) => {
+                skip(e.to_string());
+                continue;
+            }
+        };
+        if multi_line(&entry) {
+            skip("a multi-line script doesn't fit in a recipe".to_string());
+            continue;
+        }
+
+        let mut help = config.aliases[name]
+            .description
+            .as_ref()
+            .or(ac.description.as_ref())
+            .and_then(|d| d.lines().next().map(str::to_string))
+            .unwrap_or_else(|| {
+                if resolved_name != name { format!("Same as {}", resolved_name) } else { "No description.".to_string() }
+            });
+        if target != *name {
+            help = format!("{} (cs {})", help, name);
+        }
+        // a default is set for the target only, so a value given to make still wins
+        let params = param_names(ac, &entry);
+        let mut rule = String::new();
+        for param in params.iter().filter(|p| ac.defaults.contains_key(*p)) {
+            let default = ac.defaults[param].replace('$', "$$").replace('#', "\\#");
+            rule.push_str(&format!("{}: {} ?= {}\n", target, param, default));
+        }
+        rule.push_str(&format!("{}: ## {}\n", target, help));
+        let mut recipe = |line: String| {
+            rule.push('\t');
+            rule.push_str(&line);
+            rule.push('\n');
+        };
+        // cs refuses to run without the others, so does the target
+        for param in params.iter().filter(|p| !ac.defaults.contains_key(*p)) {
+            recipe(format!(
+                "@test -n \"$({})\" || {{ echo \"make $@ needs {}=...\" >&2; exit 2; }}",
+                param, param
+            ));
+        }
+        let command = |cmd: &str| recipe_command(cmd, &target, &params, ac.append_args, ac.pipe_to.as_deref());
+        match &entry {
+            AliasEntry::Single(cmd) => recipe(command(cmd)),
+            // each in the background, then every one waited for so that any
+            // failure fails the target, like it does in cs
+            AliasEntry::Parallel(cmds) => {
+                recipe("status=0; \\".to_string());
+                for (i, cmd) in cmds.iter().enumerate() {
+                    recipe(format!("( {} ) & pid{}=$$!; \\", command(cmd), i));
+                }
+                for i in 0..cmds.len() {
+                    recipe(format!("wait $$pid{} || status=1; \\", i));
+                }
+                recipe("exit $$status".to_string());
+            }
+            AliasEntry::Steps(steps) => {
+                for step in steps {
+                    recipe(format!("@echo {}", shell_quote(&format!("▶ {}", step.name)).replace('$', "$$")));
+                    recipe(command(&step.command));
+                }
+            }
+            AliasEntry::Alias(_) => {}
+        }
+        rules.push((target, rule));
+    }
+    rules.sort();
+    skipped.sort();
+
+    let mut out = String::new();
+    out.push_str("# Generated by `cs export --format make`. Change the cs config and export\n");
+    out.push_str("# again rather than editing this file.\n");
+    out.push_str("# Runtime arguments go in ARGS (make test ARGS=--verbose), parameters by\n");
+    out.push_str("# name (make deploy env=prod).\n\n");
+    if own_help {
+        out.push_str(".DEFAULT_GOAL := help\n");
+    }
+    let phony: Vec<&str> = own_help.then_some("help").into_iter().chain(rules.iter().map(|(t, _)| t.as_str())).collect();
+    out.push_str(&format!(".PHONY: {}\n", phony.join(" ")));
+    if own_help {
+        out.push_str(&format!("\nhelp: ## Show the targets\n\t{}\n", HELP_RECIPE));
+    }
+    for (_, rule) in rules {
+        out.push('\n');
+        out.push_str(&rule);
+    }
+    (out, skipped)
+}
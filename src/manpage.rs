@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::config::{AliasEntry, Config, expand_entry, param_names};
+
+// `cs list --format man`: the aliases as a man page, for `cs list --format man |
+// man -l -` or to ship next to a project's docs. nothing in it depends on the
+// machine or the date, so the output only changes when the config does
+
+// troff reads backslashes as escapes and a leading . or ' as a request, and wants
+// \- for a dash that should stay a plain ascii one
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') { format!("\\&{}", escaped) } else { escaped }
+}
+
+// the tag line of an alias: the name, its --name <value> parameters (in brackets
+// when they have a default), and [args...] when runtime args get appended
+fn synopsis(program: &str, name: &str, params: &[String], defaults: &HashMap<String, String>, append_args: bool) -> String {
+    let mut line = format!("\\fB{} {}\\fR", escape(program), escape(name));
+    for param in params {
+        let flag = format!("\\fB\\-\\-{}\\fR \\fI{}\\fR", escape(param), escape(param));
+        if defaults.contains_key(param) {
+            line.push_str(&format!(" [{}]", flag));
+        } else {
+            line.push_str(&format!(" {}", flag));
+        }
+    }
+    if append_args {
+        line.push_str(" [\\fIargs\\fR...]");
+    }
+    line
+}
+
+pub fn render(config: &Config, program: &str, include_hidden: bool) -> String {
+    let mut out = String::new();
+    let mut line = |s: &str| {
+        out.push_str(s);
+        out.push('\n');
+    };
+    line(&format!(".TH {} 1 \"\" \"{}\" \"Project Aliases\"", escape(&program.to_uppercase()), escape(program)));
+    line(".SH NAME");
+    line(&format!("{} \\- aliases for this project", escape(program)));
+    line(".SH SYNOPSIS");
+    line(&format!(".B {}", escape(program)));
+    line(".I alias");
+    line("[\\fIargs\\fR...]");
+    line(".SH ALIASES");
+
+    let mut names: Vec<&String> = config.aliases.iter().filter(|(_, ac)| include_hidden || !ac.hidden).map(|(n, _)| n).collect();
+    names.sort();
+    if names.is_empty() {
+        line("No aliases are defined.");
+    }
+    for name in names {
+        let ac = &config.aliases[name];
+        // another name is documented like the alias it points at; broken ones are left out
+        let Ok(Some((target, resolved))) = config.resolve(name) else {
+            continue;
+        };
+        line(".TP");
+        // parameters a template brings along count too
+        let entry = expand_entry(&resolved.entry, config).unwrap_or_else(|_| resolved.entry.clone());
+        let params = param_names(resolved, &entry);
+        line(&synopsis(program, name, &params, &resolved.defaults, resolved.append_args));
+        if let Some(desc) = &resolved.description {
+            for desc_line in desc.lines() {
+                line(&escape(desc_line));
+            }
+        } else {
+            line("No description.");
+        }
+        for param in params.iter().filter(|p| resolved.defaults.contains_key(*p)) {
+            line(".br");
+            line(&format!("\\fB\\-\\-{}\\fR defaults to \\fB{}\\fR.", escape(param), escape(&resolved.defaults[param])));
+        }
+        if target != name {
+            line(".br");
+            line(&format!("Another name for \\fB{}\\fR.", escape(target)));
+        }
+        match &resolved.entry {
+            AliasEntry::Single(cmd) => {
+                line(".br");
+                line(&format!("Runs: \\fB{}\\fR", escape(cmd)));
+            }
+            AliasEntry::Parallel(cmds) => {
+                line(".br");
+                line("Runs in parallel:");
+                for cmd in cmds {
+                    line(".br");
+                    line(&format!("\\(bu \\fB{}\\fR", escape(cmd)));
+                }
+            }
+            AliasEntry::Steps(steps) => {
+                line(".br");
+                line("Runs in steps:");
+                for (i, step) in steps.iter().enumerate() {
+                    line(".br");
+                    line(&format!("{}. {}: \\fB{}\\fR", i + 1, escape(&step.name), escape(&step.command)));
+                }
+            }
+            AliasEntry::Alias(_) => {}
+        }
+        let disabled = if !ac.enabled { Some(name.as_str()) } else { Some(target).filter(|_| !resolved.enabled) };
+        if let Some(disabled) = disabled {
+            line(".br");
+            line(&format!("Disabled; \\fB{} enable {}\\fR turns it back on.", escape(program), escape(disabled)));
+        }
+    }
+    out
+}
@@ -1,81 +1,166 @@
 use anyhow::Result;
-use colored::*;
+use colored::Colorize;
 use notify_rust::Notification;
-use std::path::Path;
-
-// figure out what we're called so we can sign the note
-fn get_prog_name() -> String {
-    std::env::args()
-        .next()
-        .and_then(|s| {
-            Path::new(&s)
-                .file_name()
-                .map(|s| s.to_string_lossy().into_owned())
-        })
-        .unwrap_or_else(|| "cs".to_string())
+
+use crate::cli::NotifyMode;
+use crate::config::{load_notify_state, save_notify_state, unix_now};
+use crate::exit_codes;
+use crate::output::prefix;
+
+// how a run ended, as far as the notification is concerned
+pub enum Outcome {
+    Success,
+    // the command exited with skip_exit_code
+    Skipped,
+    Failure,
+    // with the alias's timeout, when we know it
+    TimedOut(Option<u64>),
 }
 
-// send the actual popup thingy
-pub fn send(success: bool, alias: Option<&str>) -> Result<()> {
-    let program_name = get_prog_name();
-    let summary = format!("{} {}", "🐙", program_name);
-
-    // figure out what to say based on how things went
-    let body = if success {
-        if let Some(a) = alias {
-            format!("Alias '{}' finished successfully.", a)
-        } else {
-            "Command finished successfully.".to_string()
+impl Outcome {
+    // `timed_out` is whether we killed it for its timeout; a command can exit 124 on its own
+    pub fn from_code(code: i32, timed_out: bool, timeout_secs: Option<u64>) -> Outcome {
+        match code {
+            exit_codes::SUCCESS => Outcome::Success,
+            exit_codes::SKIPPED => Outcome::Skipped,
+            exit_codes::TIMED_OUT if timed_out => Outcome::TimedOut(timeout_secs),
+            _ => Outcome::Failure,
         }
-    } else {
-        if let Some(a) = alias {
-            format!("Alias '{}' failed.", a)
-        } else {
-            "Command failed.".to_string()
+    }
+}
+
+// send the actual popup thingy (or the terminal line)
+pub fn send(outcome: &Outcome, alias: Option<&str>, mode: NotifyMode) -> Result<()> {
+    deliver(&body_for(outcome, alias), outcome, mode)
+}
+
+// same as send, but drops notifications that land within min_interval_secs of the
+// previous one. failures swallowed that way get rolled into the next one we do show.
+pub fn send_throttled(outcome: &Outcome, alias: Option<&str>, min_interval_secs: u64, mode: NotifyMode) -> Result<()> {
+    let mut state = load_notify_state();
+    let now = unix_now();
+    let success = matches!(outcome, Outcome::Success | Outcome::Skipped);
+
+    if now.saturating_sub(state.last_sent) < min_interval_secs {
+        if !success {
+            state.suppressed_failures += 1;
+        }
+        log::debug!(
+            "notification suppressed, last one was {}s ago ({} failures pending)",
+            now.saturating_sub(state.last_sent),
+            state.suppressed_failures
+        );
+        return save_notify_state(&state);
+    }
+
+    let failed = state.suppressed_failures + if success { 0 } else { 1 };
+    // even one held-back failure is worth more than this run's own news
+    let body = if state.suppressed_failures > 0 {
+        let runs = if failed == 1 { "1 run".to_string() } else { format!("{} runs", failed) };
+        match alias {
+            Some(a) => format!("{} of alias '{}' failed.", runs, a),
+            None => format!("{} failed.", runs),
         }
+    } else {
+        body_for(outcome, alias)
+    };
+
+    state.last_sent = now;
+    state.suppressed_failures = 0;
+    save_notify_state(&state)?;
+    deliver(&body, outcome, mode)
+}
+
+fn deliver(body: &str, outcome: &Outcome, mode: NotifyMode) -> Result<()> {
+    if mode != NotifyMode::Desktop {
+        print_line(body, outcome);
+    }
+    if mode != NotifyMode::Terminal {
+        show(body)?;
+    }
+    Ok(())
+}
+
+// the terminal version: one line on stderr, colored by how the run went, so it
+// stands out from the command's own output
+fn print_line(body: &str, outcome: &Outcome) {
+    let bell = if crate::output::emoji() { "🔔" } else { "notify:" };
+    let body = match outcome {
+        Outcome::Success => body.green().bold(),
+        Outcome::Skipped => body.yellow().bold(),
+        Outcome::Failure | Outcome::TimedOut(_) => body.red().bold(),
+    };
+    eprintln!("{} {} {}", prefix(), bell, body);
+}
+
+// figure out what to say based on how things went
+fn body_for(outcome: &Outcome, alias: Option<&str>) -> String {
+    let what = match alias {
+        Some(a) => format!("Alias '{}'", a),
+        None => "Command".to_string(),
     };
+    match outcome {
+        Outcome::Success => format!("{} finished successfully.", what),
+        Outcome::Skipped => format!("{} was skipped, nothing to do.", what),
+        Outcome::Failure => format!("{} failed.", what),
+        Outcome::TimedOut(Some(secs)) => format!("{} timed out after {}s.", what, secs),
+        Outcome::TimedOut(None) => format!("{} timed out.", what),
+    }
+}
+
+fn show(body: &str) -> Result<()> {
+    // sign the note with whatever we were called as
+    let program_name = crate::output::get_program_name();
+    let summary = if crate::output::emoji() { format!("🐙 {}", program_name) } else { program_name };
 
     // build the notification object
     let mut notification = Notification::new();
-    notification.summary(&summary).body(&body);
+    notification.summary(&summary).body(body);
 
     #[cfg(target_os = "macos")]
     {
-        // macos is picky about banners, so we force a dialog box via apple script.
-        // using "display dialog" so it stays until clicked.
+        // macos is picky about banners from unbundled binaries, so it goes through
+        // apple script's "display notification" instead.
         // no icon param cause users asked for it clean.
         let res = std::process::Command::new("osascript")
             .arg("-e")
-            .arg(format!(
-                "display notification \"{}\" with title \"{}\"",
-                body, summary
-            ))
+            .arg(osascript_source(body, &summary))
             .output();
 
         // if apple script complains, we should prob know why
-        if let Ok(output) = res {
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                eprintln!(
-                    "{} osascript nope'd out: {}",
-                    "🐙".truecolor(80, 80, 80),
-                    stderr
-                );
+        match res {
+            Ok(output) if !output.status.success() => {
+                log::warn!("osascript nope'd out: {}", String::from_utf8_lossy(&output.stderr).trim());
             }
+            Ok(_) => log::debug!("notification shown via osascript"),
+            Err(e) => log::warn!("couldn't run osascript: {}", e),
         }
     }
 
     #[cfg(not(target_os = "macos"))]
     {
         // linux/windows usually play nice with the standard crate
-        if let Err(e) = notification.show() {
-            eprintln!(
-                "{} notification failed to show: {}",
-                "🐙".truecolor(80, 80, 80),
-                e
-            );
+        match notification.show() {
+            Ok(_) => log::debug!("notification shown"),
+            Err(e) => log::warn!("notification failed to show: {}", e),
         }
     }
 
     Ok(())
 }
+
+// alias names end up in the body, so quotes/backslashes have to be escaped or they'd
+// break out of the applescript string literal
+#[cfg(target_os = "macos")]
+fn osascript_source(body: &str, summary: &str) -> String {
+    format!(
+        "display notification \"{}\" with title \"{}\"",
+        applescript_escape(body),
+        applescript_escape(summary)
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn applescript_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,85 @@
+use colored::*;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::config::TimingFormat;
+
+// whether cs's own lines start with the octopus. turned off with `"emoji": false`
+// or --no-emoji, for CI logs and terminals that render it badly
+static EMOJI: AtomicBool = AtomicBool::new(true);
+
+pub fn set_emoji(on: bool) {
+    EMOJI.store(on, Ordering::Relaxed);
+}
+
+pub fn emoji() -> bool {
+    EMOJI.load(Ordering::Relaxed)
+}
+
+// the config's timing_format, set once at startup
+static TIMING_FORMAT: Mutex<TimingFormat> = Mutex::new(TimingFormat::Human);
+
+pub fn set_timing_format(format: TimingFormat) {
+    if let Ok(mut f) = TIMING_FORMAT.lock() {
+        *f = format;
+    }
+}
+
+pub fn timing_format() -> TimingFormat {
+    TIMING_FORMAT.lock().map(|f| *f).unwrap_or_default()
+}
+
+// what we were invoked as, for banners and notifications. that's argv[0], so a
+// symlink named `c` says "c"; only when that's missing do we ask the OS for the
+// binary's real path. a trailing .exe is dropped
+pub fn get_program_name() -> String {
+    std::env::args()
+        .next()
+        .and_then(|arg0| program_name_from(&arg0))
+        .or_else(|| std::env::current_exe().ok().and_then(|p| program_name_from(&p.to_string_lossy())))
+        .unwrap_or_else(|| "cs".to_string())
+}
+
+// "/usr/local/bin/cs" -> "cs", "C:\tools\cs.exe" -> "cs"
+fn program_name_from(path: &str) -> Option<String> {
+    // split on \ too so windows paths work wherever this runs
+    let base = Path::new(path).file_name()?.to_string_lossy();
+    let base = base.rsplit('\\').next().unwrap_or_default();
+    let lower = base.to_ascii_lowercase();
+    let name = if lower.ends_with(".exe") { &base[..base.len() - 4] } else { base };
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// the marker in front of every line cs prints itself
+pub fn prefix() -> ColoredString {
+    if emoji() { "🐙".truecolor(80, 80, 80) } else { "cs:".truecolor(80, 80, 80) }
+}
+
+// human keeps a bit of precision for short runs and drops it for long ones:
+// 250ms, 3.25s, 1m 3s, 2h 5m
+pub fn format_duration(d: Duration, format: TimingFormat) -> String {
+    match format {
+        TimingFormat::S => format!("{:.3}s", d.as_secs_f64()),
+        TimingFormat::Ms => format!("{}ms", d.as_millis()),
+        TimingFormat::Human if d < Duration::from_secs(1) => format!("{}ms", d.as_millis()),
+        TimingFormat::Human if d < Duration::from_secs(60) => format!("{:.2}s", d.as_secs_f64()),
+        TimingFormat::Human => humantime::format_duration(Duration::from_secs(d.as_secs())).to_string(),
+    }
+}
+
+// coarse relative time for list output and the tui, e.g. "2h ago"
+pub fn time_ago(secs: u64) -> String {
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+// goes between the prefix and a run's duration
+pub fn stopwatch() -> &'static str {
+    if emoji() { "⏱️  " } else { " took " }
+}
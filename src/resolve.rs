@@ -0,0 +1,161 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::config::{AliasConfig, AliasEntry, Config, expand_entry, expand_items, fill_params, param_names};
+use crate::exit_codes::{ConfigError, UsageError};
+
+// working out what an alias comes down to, kept apart from running it: nothing
+// here starts a process or prints, so it gives the same answer for a real run,
+// --dry-run and --dump-resolved
+
+// an alias with everything that can be worked out without running anything:
+// the alias it resolves to, its parameters filled in and its final commands
+pub struct ResolvedRun {
+    pub alias: String,
+    // the alias that actually runs, differs from `alias` for another name
+    pub target: String,
+    // with variables, items and parameters expanded
+    pub ac: AliasConfig,
+    // the runtime args as given, parameters and all (what `cs rerun` repeats)
+    pub args: Vec<String>,
+    // what's left of them once the parameters are taken out
+    pub extra_args: Vec<String>,
+    // exactly what's handed to the shell
+    pub commands: Vec<String>,
+}
+
+// resolution, parameters and substitution. None if the alias isn't defined.
+// `ignore_args` drops args an alias with append_args off would otherwise refuse
+pub fn resolve(config: &Config, alias: &str, args: &[String], ignore_args: bool) -> Result<Option<ResolvedRun>> {
+    // other names run the alias they point at; history etc. stay under the name used
+    let Some((resolved_name, ac)) = config.resolve(alias)? else {
+        return Ok(None);
+    };
+    // templates can bring {name} parameters of their own, so they're expanded first
+    let entry = expand_items(&expand_entry(&ac.entry, config)?, &ac.items);
+    let (params, extra_args) = parse_params(alias, &param_names(ac, &entry), &ac.defaults, args)?;
+    let extra_args = if ac.append_args || extra_args.is_empty() {
+        extra_args
+    } else if ignore_args {
+        Vec::new()
+    } else {
+        anyhow::bail!(UsageError(format!(
+            "Alias '{}' doesn't take arguments (got: {}). Pass --ignore-args to drop them.",
+            alias,
+            extra_args.join(" ")
+        )));
+    };
+    // would otherwise start nothing and report success
+    if let AliasEntry::Parallel(cmds) = &entry
+        && cmds.is_empty()
+    {
+        anyhow::bail!(ConfigError(format!("Alias '{}' is a parallel alias with no commands", alias)));
+    }
+    if let AliasEntry::Parallel(cmds) = &entry
+        && let Some(i) = cmds.iter().position(|c| c.trim().is_empty())
+    {
+        anyhow::bail!(ConfigError(format!("Alias '{}': parallel command {} is blank", alias, i + 1)));
+    }
+    let ac = AliasConfig { entry: fill_params(&entry, &params, alias), ..ac.clone() };
+    let commands = match &ac.entry {
+        AliasEntry::Single(cmd) => vec![build_command(cmd, &extra_args, ac.pipe_to.as_deref())],
+        AliasEntry::Parallel(cmds) => cmds.iter().map(|c| build_command(c, &extra_args, ac.pipe_to.as_deref())).collect(),
+        AliasEntry::Steps(steps) => {
+            steps.iter().map(|s| build_command(&s.command, &extra_args, ac.pipe_to.as_deref())).collect()
+        }
+        AliasEntry::Alias(target) => anyhow::bail!("alias '{}' wasn't resolved before running", target),
+    };
+    Ok(Some(ResolvedRun {
+        alias: alias.to_string(),
+        target: resolved_name.to_string(),
+        ac,
+        args: args.to_vec(),
+        extra_args,
+        commands,
+    }))
+}
+
+// how the parameters are passed, for error messages: `--env <env>`, and
+// `[--region <region>, default us-1]` for one that can be left out
+pub fn usage(names: &[String], defaults: &HashMap<String, String>) -> String {
+    let param = |n: &String| match defaults.get(n) {
+        Some(default) => format!("[--{} <{}>, default {}]", n, n, default),
+        None => format!("--{} <{}>", n, n),
+    };
+    names.iter().map(param).collect::<Vec<_>>().join(" ")
+}
+
+// pull the alias's --name value parameters out of the runtime args. whatever's left
+// (plus everything after a `--`) is appended as usual. a --flag that isn't one of
+// the parameters is refused, since it's most likely a typo. one that isn't given
+// takes its default, and is only missing without one. the first `--` itself is
+// dropped whether or not the alias has parameters, like `cargo run --` does
+pub fn parse_params(
+    alias: &str,
+    names: &[String],
+    defaults: &HashMap<String, String>,
+    args: &[String],
+) -> Result<(HashMap<String, String>, Vec<String>)> {
+    if names.is_empty() {
+        let mut rest = args.to_vec();
+        if let Some(i) = rest.iter().position(|a| a == "--") {
+            rest.remove(i);
+        }
+        return Ok((HashMap::new(), rest));
+    }
+    let usage = usage(names, defaults);
+    let mut values = HashMap::new();
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            rest.extend(args.by_ref().cloned());
+            break;
+        }
+        let Some(flag) = arg.strip_prefix("--") else {
+            rest.push(arg.clone());
+            continue;
+        };
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+        if !names.iter().any(|n| n == name) {
+            anyhow::bail!(UsageError(format!(
+                "'{}' has no parameter --{} (usage: cs {} {}). Put flags meant for the command after --",
+                alias, name, alias, usage
+            )));
+        }
+        let value = match value {
+            Some(v) => v,
+            None => args.next().cloned().ok_or_else(|| UsageError(format!("--{} needs a value", name)))?,
+        };
+        values.insert(name.to_string(), value);
+    }
+
+    for name in names {
+        if !values.contains_key(name)
+            && let Some(default) = defaults.get(name)
+        {
+            values.insert(name.clone(), default.clone());
+        }
+    }
+    let missing: Vec<String> = names.iter().filter(|n| !values.contains_key(*n)).map(|n| format!("--{}", n)).collect();
+    if !missing.is_empty() {
+        anyhow::bail!(UsageError(format!("'{}' is missing {} (usage: cs {} {})", alias, missing.join(", "), alias, usage)));
+    }
+    Ok((values, rest))
+}
+
+// the final string handed to the shell: runtime args go on the command itself,
+// then the optional pipe, so `cmd {args} | pipe_to`
+pub fn build_command(cmd: &str, extra_args: &[String], pipe_to: Option<&str>) -> String {
+    let mut full = cmd.to_string();
+    if !extra_args.is_empty() {
+        full = format!("{} {}", full, extra_args.join(" "));
+    }
+    if let Some(pipe) = pipe_to {
+        full = format!("{} | {}", full, pipe);
+    }
+    full
+}
@@ -1,41 +1,1128 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{IsTerminal, Read, Write};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, Once, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-pub fn execute_command(cmd_str: &str, timeout_secs: Option<u64>) -> bool {
-    let child = Command::new("sh")
-        .arg("-c")
-        .arg(cmd_str)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .spawn();
+use colored::Colorize;
+use regex::Regex;
+
+use crate::config::{DetachedProcess, RetryBackoff, load_detached, save_detached, unix_now};
+use crate::exit_codes;
+use crate::output::{format_duration, prefix, timing_format};
+
+// where detached aliases write their output, one file per alias
+pub const LOG_DIR: &str = ".cawa_logs";
+
+// pids of the shells we're currently waiting on, so ctrl-c can reach all of them
+static RUNNING: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+// under `cs watch` ctrl-c is how you stop watching, so it isn't reported as a failure
+static WATCHING: AtomicBool = AtomicBool::new(false);
+// set when we killed a command for its timeout, so a child's own exit 124 isn't
+// taken for one
+static TIMED_OUT: AtomicBool = AtomicBool::new(false);
+// the `shell` from the config, split into program + leading args. unset means sh -c
+static SHELL: OnceLock<Vec<String>> = OnceLock::new();
+static FALLBACK_NOTE: Once = Once::new();
+// extra variables for every command: the env file, with --env on top
+static EXTRA_ENV: Mutex<Vec<EnvVar>> = Mutex::new(Vec::new());
+
+// where a variable handed to set_env came from
+#[derive(Clone)]
+pub enum EnvSource {
+    File(PathBuf),
+    Flag,
+    // the env file has it but our own environment already did, which wins; cs
+    // doesn't set these, the command just inherits them
+    Process(PathBuf),
+}
+
+impl EnvSource {
+    pub fn label(&self) -> String {
+        match self {
+            EnvSource::File(path) => path.display().to_string(),
+            EnvSource::Flag => "--env".to_string(),
+            EnvSource::Process(path) => format!("environment (overrides {})", path.display()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+    pub source: EnvSource,
+}
+
+// on ctrl-c, pass SIGINT on to every running child, give them a moment to clean up,
+// then exit 130 like a shell would. the handler owns the exit so the normal
+// success/failure reporting doesn't race it with a half-finished summary.
+pub fn install_interrupt_handler() {
+    let _ = ctrlc::set_handler(|| {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        let pids = RUNNING.lock().map(|r| r.clone()).unwrap_or_default();
+        log::debug!("interrupted, forwarding SIGINT to {:?}", pids);
+        for pid in &pids {
+            interrupt(*pid);
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline && RUNNING.lock().map(|r| !r.is_empty()).unwrap_or(false) {
+            thread::sleep(Duration::from_millis(50));
+        }
+        // anything still alive ignored the polite request
+        for pid in RUNNING.lock().map(|r| r.clone()).unwrap_or_default() {
+            log::warn!("pid {} ignored SIGINT, killing it", pid);
+            kill_tree(pid);
+        }
+
+        if WATCHING.load(Ordering::SeqCst) {
+            // the SIGINT may have come from outside while the keyboard was raw
+            let _ = crossterm::terminal::disable_raw_mode();
+            eprintln!("\n{} Stopped watching", prefix());
+            std::process::exit(exit_codes::SUCCESS);
+        }
+        eprintln!("\n{} interrupted", prefix());
+        std::process::exit(exit_codes::INTERRUPTED);
+    });
+}
+
+// pass ctrl-c on to a child and whatever it started. windows already delivers
+// ctrl-c to everything attached to the console, so there's nothing to forward
+#[cfg(unix)]
+fn interrupt(pid: u32) {
+    signal_tree(pid, libc::SIGINT);
+}
+
+#[cfg(windows)]
+fn interrupt(_pid: u32) {}
+
+// kill a child for good, along with whatever it started
+#[cfg(unix)]
+fn kill_tree(pid: u32) {
+    signal_tree(pid, libc::SIGKILL);
+}
+
+#[cfg(windows)]
+fn kill_tree(pid: u32) {
+    let _ = Command::new("taskkill").args(["/T", "/F", "/PID", &pid.to_string()]).status();
+}
+
+// the whole process group when the child leads one (see own_group), just the
+// child otherwise. straight syscalls: this runs in the signal handler thread,
+// where starting a `kill` process is the last thing we want
+#[cfg(unix)]
+fn signal_tree(pid: u32, sig: libc::c_int) {
+    let pid = pid as libc::pid_t;
+    // SAFETY: getpgid and kill only look at / signal the given pid
+    unsafe {
+        let target = if libc::getpgid(pid) == pid { -pid } else { pid };
+        libc::kill(target, sig);
+    }
+}
+
+// a child normally gets its own process group, so a timeout or ctrl-c can take
+// down everything it started. not when it reads the terminal though: outside the
+// terminal's foreground group it'd be stopped the moment it did (sudo asking for
+// a password included), and it gets the terminal's ctrl-c directly anyway
+#[cfg(unix)]
+fn own_group(opts: &ExecOptions) -> bool {
+    !((opts.inherit_stdin || opts.run_as.is_some()) && std::io::stdin().is_terminal())
+}
+
+pub fn interrupt_ends_watch() {
+    WATCHING.store(true, Ordering::SeqCst);
+}
+
+// whether a timeout killed anything since the last call
+pub fn take_timed_out() -> bool {
+    TIMED_OUT.swap(false, Ordering::SeqCst)
+}
+
+// everything that actually runs a command goes through this: the shell itself,
+// and the wrappers that judge output or retry on top of whatever they're given
+pub trait Executor: Sync {
+    // returns the command's exit code
+    fn run(&self, cmd: &str, opts: &ExecOptions) -> i32;
+
+    // run with stdout/stderr buffered in memory instead of streamed. the other
+    // capturing modes fall back to this, and executors that don't produce output
+    // can just lean on run()
+    fn run_captured(&self, cmd: &str, opts: &ExecOptions) -> Captured {
+        Captured { code: self.run(cmd, opts), ..Default::default() }
+    }
+
+    // run with stdout/stderr written to these files, for grouped parallel output.
+    // on disk rather than in memory so a command with huge output can't blow us up
+    fn run_to_files(&self, cmd: &str, opts: &ExecOptions, mut stdout: File, mut stderr: File) -> i32 {
+        let out = self.run_captured(cmd, opts);
+        let _ = stdout.write_all(&out.stdout);
+        let _ = stderr.write_all(&out.stderr);
+        out.code
+    }
+
+    // run with each line of output handed to `sink` ("stdout" or "stderr", line),
+    // for --output json. by default the lines arrive once the command is done
+    fn run_lines(&self, cmd: &str, opts: &ExecOptions, sink: LineSink) -> i32 {
+        let out = self.run_captured(cmd, opts);
+        for (stream, bytes) in [("stdout", &out.stdout), ("stderr", &out.stderr)] {
+            for line in String::from_utf8_lossy(bytes).lines() {
+                sink(stream, line);
+            }
+        }
+        out.code
+    }
+}
+
+pub type LineSink = Arc<dyn Fn(&'static str, &str) + Send + Sync>;
+
+// success_pattern / failure_pattern, compiled. matched against every line of a
+// command's output (both streams), they can overrule its exit code:
+// - a failure_pattern match fails the run, even after exit 0
+// - with a success_pattern, exit 0 only counts if some line matched it
+// - a success_pattern match turns a failed exit into 0 only with success_overrides_exit
+// timeouts and skips are left as they are
+#[derive(Clone)]
+pub struct OutputMatchers {
+    success: Option<Regex>,
+    failure: Option<Regex>,
+    rescue: bool,
+}
+
+// what the output showed so far: a success_pattern match, and the first line
+// matching failure_pattern, for the note
+#[derive(Default)]
+struct Seen {
+    success: bool,
+    failure: Option<String>,
+}
+
+impl OutputMatchers {
+    // None when the alias has neither pattern
+    pub fn new(success: Option<&str>, failure: Option<&str>, rescue: bool) -> Result<Option<OutputMatchers>> {
+        if success.is_none() && failure.is_none() {
+            return Ok(None);
+        }
+        let compile = |p: Option<&str>| p.map(Regex::new).transpose().context("Invalid output pattern");
+        Ok(Some(OutputMatchers { success: compile(success)?, failure: compile(failure)?, rescue }))
+    }
+
+    fn scan(&self, line: &str, seen: &mut Seen) {
+        if !seen.success && self.success.as_ref().is_some_and(|re| re.is_match(line)) {
+            seen.success = true;
+        }
+        if seen.failure.is_none() && self.failure.as_ref().is_some_and(|re| re.is_match(line)) {
+            seen.failure = Some(line.trim().to_string());
+        }
+    }
+
+    fn judge(&self, code: i32, seen: &Seen) -> i32 {
+        if matches!(code, exit_codes::TIMED_OUT | exit_codes::SKIPPED | exit_codes::INTERRUPTED) {
+            return code;
+        }
+        let note = |msg: String| eprintln!("{} {} {}", prefix(), "⚠".yellow(), msg);
+        if let Some(line) = &seen.failure {
+            note(format!("Output matched failure_pattern: {}", line));
+            return if code == exit_codes::SUCCESS { exit_codes::FAILURE } else { code };
+        }
+        match (&self.success, seen.success) {
+            (Some(re), false) if code == exit_codes::SUCCESS => {
+                note(format!("Exited 0 but no output matched success_pattern '{}'", re.as_str()));
+                exit_codes::FAILURE
+            }
+            (Some(_), true) if code != exit_codes::SUCCESS && self.rescue => {
+                note(format!("Exited {} but the output matched success_pattern, counting it as a success", code));
+                exit_codes::SUCCESS
+            }
+            _ => code,
+        }
+    }
+}
+
+// runs commands through another executor with their output checked against an
+// alias's OutputMatchers. plain runs can't see output that goes straight to the
+// terminal, so they go through run_lines and print each line as it arrives.
+// suppressed output is still read for matching, it just isn't passed on
+pub struct MatchingExecutor<'a> {
+    pub inner: &'a dyn Executor,
+    pub matchers: &'a OutputMatchers,
+}
+
+fn unsuppressed(opts: &ExecOptions) -> ExecOptions {
+    ExecOptions { suppress_stdout: false, suppress_stderr: false, ..opts.clone() }
+}
+
+fn suppressed(stream: &str, opts: &ExecOptions) -> bool {
+    if stream == "stderr" { opts.suppress_stderr } else { opts.suppress_stdout }
+}
+
+impl Executor for MatchingExecutor<'_> {
+    fn run(&self, cmd: &str, opts: &ExecOptions) -> i32 {
+        let sink: LineSink = Arc::new(|stream, line| {
+            if stream == "stderr" {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+        });
+        self.run_lines(cmd, opts, sink)
+    }
+
+    fn run_captured(&self, cmd: &str, opts: &ExecOptions) -> Captured {
+        let mut out = self.inner.run_captured(cmd, &unsuppressed(opts));
+        let mut seen = Seen::default();
+        for bytes in [&out.stdout, &out.stderr] {
+            for line in String::from_utf8_lossy(bytes).lines() {
+                self.matchers.scan(line, &mut seen);
+            }
+        }
+        out.code = self.matchers.judge(out.code, &seen);
+        if opts.suppress_stdout {
+            out.stdout.clear();
+        }
+        if opts.suppress_stderr {
+            out.stderr.clear();
+        }
+        out
+    }
+
+    fn run_lines(&self, cmd: &str, opts: &ExecOptions, sink: LineSink) -> i32 {
+        let seen = Arc::new(Mutex::new(Seen::default()));
+        let (matchers, collected, passed) = (self.matchers.clone(), seen.clone(), opts.clone());
+        let checking: LineSink = Arc::new(move |stream, line| {
+            if let Ok(mut seen) = collected.lock() {
+                matchers.scan(line, &mut seen);
+            }
+            if !suppressed(stream, &passed) {
+                sink(stream, line);
+            }
+        });
+        let code = self.inner.run_lines(cmd, &unsuppressed(opts), checking);
+        match seen.lock() {
+            Ok(seen) => self.matchers.judge(code, &seen),
+            Err(_) => code,
+        }
+    }
+}
+
+// an alias's retries, delay_ms and max_delay_ms filled in with the defaults
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub delay_ms: u64,
+    pub backoff: RetryBackoff,
+    pub jitter: bool,
+    pub max_delay_ms: u64,
+}
+
+// the wait before retry number `attempt` (1 for the first). fixed waits delay_ms
+// every time, linear delay_ms × attempt, exponential delay_ms × 2^(attempt - 1),
+// never more than max_delay_ms. `jitter` is a number in [0, 1) drawn by the
+// caller, and moves the wait to somewhere between half of it and all of it, so
+// aliases that failed together don't all come back at the same moment
+pub fn retry_delay(policy: &RetryPolicy, attempt: u32, jitter: Option<f64>) -> Duration {
+    let steps = attempt.max(1);
+    let ms = match policy.backoff {
+        RetryBackoff::Fixed => policy.delay_ms,
+        RetryBackoff::Linear => policy.delay_ms.saturating_mul(steps as u64),
+        RetryBackoff::Exponential => policy.delay_ms.saturating_mul(1u64.checked_shl(steps - 1).unwrap_or(u64::MAX)),
+    }
+    .min(policy.max_delay_ms);
+    let ms = match jitter {
+        Some(f) => ms / 2 + (ms.div_ceil(2) as f64 * f.clamp(0.0, 1.0)) as u64,
+        None => ms,
+    };
+    Duration::from_millis(ms)
+}
+
+// xorshift64, plenty for spreading retries out. seeded rather than pulled from
+// the os so the same seed always gives the same waits
+pub struct Jitter(u64);
+
+impl Jitter {
+    pub fn seeded(seed: u64) -> Jitter {
+        // an all-zero state would stay zero forever
+        Jitter(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed })
+    }
+
+    // different for every run, which is the point, unless CAWA_RETRY_SEED pins
+    // it down so the waits can be checked
+    fn for_run() -> Jitter {
+        if let Some(seed) = std::env::var("CAWA_RETRY_SEED").ok().and_then(|s| s.trim().parse().ok()) {
+            return Jitter::seeded(seed);
+        }
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+        Jitter::seeded(nanos ^ ((std::process::id() as u64) << 32))
+    }
+
+    // the next number in [0, 1)
+    pub fn next_fraction(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// runs each command through another executor, again after a failure as the
+// alias's retries allow. a skip or ctrl-c is never retried, a timeout is. each
+// command of a parallel alias or step of a steps one is retried on its own
+pub struct RetryingExecutor<'a> {
+    pub inner: &'a dyn Executor,
+    pub policy: &'a RetryPolicy,
+}
+
+impl RetryingExecutor<'_> {
+    fn retry<T>(&self, mut run: impl FnMut() -> T, code: impl Fn(&T) -> i32) -> T {
+        let mut jitter = self.policy.jitter.then(Jitter::for_run);
+        let mut result = run();
+        for attempt in 1..=self.policy.retries {
+            let failed = code(&result);
+            if matches!(failed, exit_codes::SUCCESS | exit_codes::SKIPPED | exit_codes::INTERRUPTED) {
+                break;
+            }
+            let delay = retry_delay(self.policy, attempt, jitter.as_mut().map(Jitter::next_fraction));
+            let why = if failed == exit_codes::TIMED_OUT { "Timed out".to_string() } else { format!("Exited {}", failed) };
+            eprintln!(
+                "{} {} {}, retrying in {} ({} of {})",
+                prefix(),
+                "↻".yellow(),
+                why,
+                format_duration(delay, timing_format()),
+                attempt,
+                self.policy.retries
+            );
+            thread::sleep(delay);
+            result = run();
+        }
+        result
+    }
+}
+
+impl Executor for RetryingExecutor<'_> {
+    fn run(&self, cmd: &str, opts: &ExecOptions) -> i32 {
+        self.retry(|| self.inner.run(cmd, opts), |code| *code)
+    }
+
+    fn run_captured(&self, cmd: &str, opts: &ExecOptions) -> Captured {
+        self.retry(|| self.inner.run_captured(cmd, opts), |out| out.code)
+    }
+
+    // every attempt writes to the same files, so the output of the failed ones stays
+    fn run_to_files(&self, cmd: &str, opts: &ExecOptions, stdout: File, stderr: File) -> i32 {
+        self.retry(
+            || match (stdout.try_clone(), stderr.try_clone()) {
+                (Ok(out), Ok(err)) => self.inner.run_to_files(cmd, opts, out, err),
+                _ => exit_codes::FAILURE,
+            },
+            |code| *code,
+        )
+    }
+
+    fn run_lines(&self, cmd: &str, opts: &ExecOptions, sink: LineSink) -> i32 {
+        self.retry(|| self.inner.run_lines(cmd, opts, sink.clone()), |code| *code)
+    }
+}
+
+// what a buffered run produced
+#[derive(Default)]
+pub struct Captured {
+    pub code: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+// how a single command should be spawned
+#[derive(Clone, Default)]
+pub struct ExecOptions {
+    pub timeout_secs: Option<u64>,
+    // false for parallel children so several of them can't block fighting over the
+    // same terminal input
+    pub inherit_stdin: bool,
+    pub suppress_stdout: bool,
+    pub suppress_stderr: bool,
+    // run as a login shell (`sh -l -c`) so profile files set up PATH etc. first
+    pub login_shell: bool,
+    // niceness increment for the child, see lower_priority
+    pub nice: Option<i32>,
+    // captured output goes through a pty instead of pipes, see open_pty
+    pub pty: bool,
+    // fed to the command as its stdin, instead of the terminal or nothing
+    pub stdin_file: Option<PathBuf>,
+    // most bytes kept from each captured stream, None keeps everything
+    pub max_capture_bytes: Option<u64>,
+    // the alias's run_as user, see launcher
+    pub run_as: Option<String>,
+    // see apply_limits
+    pub memory_limit_mb: Option<u64>,
+    pub cpu_seconds: Option<u64>,
+    // the child's exit status that means "skipped", see skip_code
+    pub skip_exit_code: Option<i32>,
+}
+
+// the real thing: hand the command to the shell
+pub struct ShellExecutor;
+
+impl Executor for ShellExecutor {
+    fn run(&self, cmd: &str, opts: &ExecOptions) -> i32 {
+        execute_command(cmd, opts)
+    }
+
+    fn run_captured(&self, cmd: &str, opts: &ExecOptions) -> Captured {
+        spawn_and_wait(cmd, opts, Output::Buffer)
+    }
+
+    // the child writes to the files itself
+    fn run_to_files(&self, cmd: &str, opts: &ExecOptions, stdout: File, stderr: File) -> i32 {
+        spawn_and_wait(cmd, opts, Output::Files(stdout, stderr)).code
+    }
+
+    // lines go out as they're read
+    fn run_lines(&self, cmd: &str, opts: &ExecOptions, sink: LineSink) -> i32 {
+        spawn_and_wait(cmd, opts, Output::Lines(sink)).code
+    }
+}
+
+// same as ShellExecutor but throws the command's output away (used by cs bench)
+pub struct QuietExecutor;
+
+impl Executor for QuietExecutor {
+    fn run(&self, cmd: &str, opts: &ExecOptions) -> i32 {
+        execute_command(cmd, &ExecOptions { suppress_stdout: true, suppress_stderr: true, ..opts.clone() })
+    }
+
+    // kept in memory rather than thrown away, for aliases judged by their output
+    fn run_captured(&self, cmd: &str, opts: &ExecOptions) -> Captured {
+        spawn_and_wait(cmd, opts, Output::Buffer)
+    }
+}
+
+// look a binary up the same way the shell would (with the .exe added on windows)
+pub fn find_on_path(bin: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(bin))
+        .find(|candidate| candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file()))
+}
+
+// the entries of an alias's `requires` that can't be found
+pub fn missing_programs(bins: &[String]) -> Vec<String> {
+    bins.iter().filter(|b| find_on_path(b).is_none()).cloned().collect()
+}
+
+// called once at startup with the config's `shell`, e.g. "bash -c" or "pwsh -Command"
+pub fn set_shell(shell: Option<&str>) {
+    let parts: Vec<String> = shell.unwrap_or_default().split_whitespace().map(String::from).collect();
+    if !parts.is_empty() {
+        let _ = SHELL.set(parts);
+    }
+}
+
+pub fn set_env(vars: Vec<EnvVar>) {
+    if let Ok(mut env) = EXTRA_ENV.lock() {
+        *env = vars;
+    }
+}
+
+// everything set_env handed over, the ones our environment overrides included
+pub fn env_vars() -> Vec<EnvVar> {
+    EXTRA_ENV.lock().map(|env| env.clone()).unwrap_or_default()
+}
+
+// the variables cs actually sets, in the order they're applied
+pub fn extra_env() -> Vec<(String, String)> {
+    env_vars()
+        .into_iter()
+        .filter(|v| !matches!(v.source, EnvSource::Process(_)))
+        .map(|v| (v.name, v.value))
+        .collect()
+}
+
+// whether a command will see this variable, from our environment or the extras
+pub fn env_is_set(name: &str) -> bool {
+    std::env::var_os(name).is_some() || EXTRA_ENV.lock().is_ok_and(|env| env.iter().any(|v| v.name == name))
+}
+
+// the program and leading args every command string is handed to. windows
+// without a unix shell falls back to cmd /C, with a note so it isn't a surprise
+// when sh syntax stops working
+pub fn shell_argv() -> Vec<String> {
+    if let Some(custom) = SHELL.get() {
+        return custom.clone();
+    }
+    if cfg!(windows) && find_on_path("sh").is_none() {
+        FALLBACK_NOTE.call_once(|| {
+            eprintln!(
+                "{} `sh` not found, running commands with `cmd /C`. Set \"shell\" in the config to choose another.",
+                prefix()
+            );
+        });
+        return vec!["cmd".to_string(), "/C".to_string()];
+    }
+    vec!["sh".to_string(), "-c".to_string()]
+}
+
+// shell_argv with -l slipped in before the command flag, e.g. `bash -l -c`.
+// cmd has no such thing, so it's left alone
+pub fn invocation(login_shell: bool) -> Vec<String> {
+    let mut argv = shell_argv();
+    if login_shell && argv.len() > 1 && !argv[0].eq_ignore_ascii_case("cmd") {
+        argv.insert(argv.len() - 1, "-l".to_string());
+    }
+    argv
+}
+
+// what the command string is appended to: the shell, behind sudo for run_as.
+// sudo starts from a clean environment, so it's asked to keep the extra
+// variables. only their names go on the command line: the values are in sudo's
+// own environment, out of `ps` and the debug log
+pub fn launcher(opts: &ExecOptions) -> Vec<String> {
+    let shell = invocation(opts.login_shell);
+    let Some(user) = &opts.run_as else {
+        return shell;
+    };
+    if !cfg!(unix) {
+        log::warn!("run_as needs sudo, which isn't there on this platform; running as yourself");
+        return shell;
+    }
+    let mut argv = vec!["sudo".to_string()];
+    if user != "root" {
+        argv.extend(["-u".to_string(), user.clone()]);
+    }
+    let names: Vec<String> = extra_env().into_iter().map(|(name, _)| name).collect();
+    if !names.is_empty() {
+        argv.push(format!("--preserve-env={}", names.join(",")));
+    }
+    argv.extend(shell);
+    argv
+}
+
+fn shell_command(cmd_str: &str, opts: &ExecOptions) -> Command {
+    let argv = launcher(opts);
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..]).arg(cmd_str);
+    command.envs(extra_env());
+    if let Some(n) = opts.nice.filter(|n| *n > 0) {
+        lower_priority(&mut command, n);
+    }
+    if opts.memory_limit_mb.is_some() || opts.cpu_seconds.is_some() {
+        apply_limits(&mut command, opts.memory_limit_mb, opts.cpu_seconds);
+    }
+    command
+}
+
+// rlimits set in the child right before exec. they're per process: whatever the
+// command starts inherits the same limits but gets its own budget, so this is a
+// guard against one runaway process rather than a cap on the whole alias.
+// memory is address space (RLIMIT_AS), so allocations past it fail and it's up
+// to the program how it dies. a second after the cpu time runs out SIGXCPU turns
+// into SIGKILL
+#[cfg(target_os = "linux")]
+fn apply_limits(command: &mut Command, memory_limit_mb: Option<u64>, cpu_seconds: Option<u64>) {
+    // SAFETY: setrlimit() is async-signal-safe and only affects the child
+    unsafe {
+        command.pre_exec(move || {
+            let set = |resource, soft: u64, hard: u64| {
+                if libc::setrlimit(resource, &libc::rlimit { rlim_cur: soft, rlim_max: hard }) == 0 {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::last_os_error())
+                }
+            };
+            if let Some(mb) = memory_limit_mb {
+                let bytes = mb.saturating_mul(1024 * 1024);
+                set(libc::RLIMIT_AS, bytes, bytes)?;
+            }
+            if let Some(secs) = cpu_seconds {
+                set(libc::RLIMIT_CPU, secs, secs.saturating_add(1))?;
+            }
+            Ok(())
+        });
+    }
+}
+
+// prepare_run refuses aliases with limits on other platforms
+#[cfg(not(target_os = "linux"))]
+fn apply_limits(_command: &mut Command, _memory_limit_mb: Option<u64>, _cpu_seconds: Option<u64>) {}
+
+// how a failed command may have run into one of its limits, as far as we can
+// tell. the signal comes straight from the status, or as 128 + signal when the
+// shell outlived the command. only a signal counts as evidence: a program that
+// reports a failed allocation and exits 1 looks like any other failure, so that
+// gets no guess
+#[cfg(target_os = "linux")]
+fn limit_exceeded(status: &std::process::ExitStatus, opts: &ExecOptions) -> Option<String> {
+    use std::os::unix::process::ExitStatusExt;
+    if status.success() {
+        return None;
+    }
+    let signal = status.signal().or_else(|| status.code().filter(|c| *c > 128 && *c < 160).map(|c| c - 128));
+    match (signal, opts.cpu_seconds, opts.memory_limit_mb) {
+        (Some(libc::SIGXCPU), Some(secs), _) | (Some(libc::SIGKILL), Some(secs), None) => {
+            Some(format!("Killed after using its {}s of CPU time (cpu_seconds)", secs))
+        }
+        (Some(signal), _, Some(mb)) if [libc::SIGKILL, libc::SIGABRT, libc::SIGSEGV].contains(&signal) => {
+            Some(format!("Died from signal {}, most likely out of memory under its {} MB limit (memory_limit_mb)", signal, mb))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn limit_exceeded(_status: &std::process::ExitStatus, _opts: &ExecOptions) -> Option<String> {
+    None
+}
+
+// on unix the child adds n to its niceness right before exec, like `nice -n`, so
+// anything it starts inherits it. windows has no niceness, only priority classes:
+// 1-9 is "below normal" and 10-19 is "idle"
+#[cfg(unix)]
+fn lower_priority(command: &mut Command, n: i32) {
+    // SAFETY: nice() is async-signal-safe and touches nothing but the child itself.
+    // a positive increment can't fail, so the result is ignored
+    unsafe {
+        command.pre_exec(move || {
+            libc::nice(n);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+fn lower_priority(command: &mut Command, n: i32) {
+    use std::os::windows::process::CommandExt;
+    const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x4000;
+    const IDLE_PRIORITY_CLASS: u32 = 0x40;
+    command.creation_flags(if n >= 10 { IDLE_PRIORITY_CLASS } else { BELOW_NORMAL_PRIORITY_CLASS });
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lower_priority(_command: &mut Command, _n: i32) {}
+
+// a pseudo-terminal as (our end, the child's end). programs that check isatty keep
+// their colors on it. output post-processing is turned off so lines end in plain \n
+// like they would through a pipe. the size is copied from our terminal, 80x24 without one
+#[cfg(unix)]
+fn open_pty() -> Option<(File, File)> {
+    use std::os::fd::FromRawFd;
+    let mut size = libc::winsize { ws_row: 24, ws_col: 80, ws_xpixel: 0, ws_ypixel: 0 };
+    let (mut reader, mut writer) = (-1, -1);
+    // SAFETY: plain libc calls on fds we own; each fd is wrapped in a File right
+    // away so it's closed exactly once
+    unsafe {
+        let mut ours = size;
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ours) == 0 && ours.ws_col > 0 {
+            size = ours;
+        }
+        if libc::openpty(&mut reader, &mut writer, std::ptr::null_mut(), std::ptr::null(), &size) != 0 {
+            log::warn!("couldn't open a pty, using pipes: {}", std::io::Error::last_os_error());
+            return None;
+        }
+        // otherwise every command started meanwhile (parallel siblings, the child
+        // itself) inherits a copy, and our end never sees the child's end close
+        for fd in [reader, writer] {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+        let mut term: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(writer, &mut term) == 0 {
+            term.c_oflag &= !libc::OPOST;
+            libc::tcsetattr(writer, libc::TCSANOW, &term);
+        }
+        Some((File::from_raw_fd(reader), File::from_raw_fd(writer)))
+    }
+}
+
+#[cfg(not(unix))]
+fn open_pty() -> Option<(File, File)> {
+    log::warn!("pty isn't supported on this platform, using pipes");
+    None
+}
+
+// suppressed streams go to /dev/null; that only hides output, the exit status
+// still comes from the child as usual
+pub fn execute_command(cmd_str: &str, opts: &ExecOptions) -> i32 {
+    spawn_and_wait(cmd_str, opts, Output::Inherit).code
+}
+
+// each command opens stdin_file itself, so parallel ones all read it from the start
+fn stdin_for(opts: &ExecOptions) -> Result<Stdio> {
+    Ok(match &opts.stdin_file {
+        Some(path) => Stdio::from(
+            File::open(path).with_context(|| format!("Couldn't open {} for the command's stdin", path.display()))?,
+        ),
+        None if opts.inherit_stdin => Stdio::inherit(),
+        None => Stdio::null(),
+    })
+}
+
+// where the output of a command that isn't suppressed goes
+enum Output {
+    // straight to our terminal
+    Inherit,
+    // piped back to us and kept in memory
+    Buffer,
+    // piped back and handed over line by line
+    Lines(LineSink),
+    // written by the child directly into these (stdout, stderr)
+    Files(File, File),
+}
+
+// where a drain thread puts what it reads from one of the child's pipes
+enum Dest {
+    Buffer,
+    Lines(LineSink),
+    File(File),
+}
+
+// hands at most `limit` bytes of the pipe to `take`, then keeps reading so the
+// child never blocks on a full pipe. returns how many bytes were thrown away
+fn pump(mut pipe: impl Read, limit: u64, mut take: impl FnMut(&[u8])) -> u64 {
+    let mut chunk = [0u8; 8192];
+    let (mut kept, mut skipped) = (0u64, 0u64);
+    loop {
+        let n = match pipe.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        let room = limit.saturating_sub(kept).min(n as u64) as usize;
+        if room > 0 {
+            take(&chunk[..room]);
+        }
+        kept += room as u64;
+        skipped += (n - room) as u64;
+    }
+    skipped
+}
+
+fn truncated_marker(skipped: u64) -> String {
+    format!("...[truncated {} bytes]", skipped)
+}
+
+fn spawn_and_wait(cmd_str: &str, opts: &ExecOptions, output: Output) -> Captured {
+    // inherited output is already on a terminal, so a pty only matters when capturing
+    let pty = if opts.pty && !opts.suppress_stdout && !matches!(output, Output::Inherit) { open_pty() } else { None };
+    // files are normally handed to the child as is, but with a pty or a capture
+    // limit we pipe the output and copy it into them ourselves
+    let copy_files = pty.is_some() || opts.max_capture_bytes.is_some();
+    let (mut out_stdio, mut err_stdio, out_dest, err_dest) = match output {
+        Output::Inherit => (Stdio::inherit(), Stdio::inherit(), None, None),
+        Output::Buffer => (Stdio::piped(), Stdio::piped(), Some(Dest::Buffer), Some(Dest::Buffer)),
+        Output::Lines(sink) => {
+            (Stdio::piped(), Stdio::piped(), Some(Dest::Lines(sink.clone())), Some(Dest::Lines(sink)))
+        }
+        Output::Files(out, err) if copy_files => {
+            (Stdio::piped(), Stdio::piped(), Some(Dest::File(out)), Some(Dest::File(err)))
+        }
+        Output::Files(out, err) => (Stdio::from(out), Stdio::from(err), None, None),
+    };
+    // with a pty the child writes both streams to it and we copy from our end
+    // into wherever stdout would have gone
+    let mut pty_reader = None;
+    if let Some((reader, writer)) = pty {
+        // no second copy for a suppressed stderr, an unused one would keep the pty open
+        let second = if opts.suppress_stderr { Ok(None) } else { writer.try_clone().map(Some) };
+        match second {
+            Ok(second) => {
+                out_stdio = Stdio::from(writer);
+                if let Some(second) = second {
+                    err_stdio = Stdio::from(second);
+                }
+                pty_reader = Some(reader);
+            }
+            Err(e) => log::warn!("couldn't set up the pty, using pipes: {}", e),
+        }
+    }
+    let stdin = match stdin_for(opts) {
+        Ok(stdin) => stdin,
+        Err(e) => {
+            eprintln!("{} {:#}", prefix(), e);
+            return Captured { code: exit_codes::FAILURE, ..Default::default() };
+        }
+    };
+    let mut command = shell_command(cmd_str, opts);
+    command
+        .stdin(stdin)
+        .stdout(if opts.suppress_stdout { Stdio::null() } else { out_stdio })
+        .stderr(if opts.suppress_stderr { Stdio::null() } else { err_stdio });
+    #[cfg(unix)]
+    if own_group(opts) {
+        command.process_group(0);
+    }
+    let child = command.spawn();
+    // the builder still holds our copy of the pty's writing end, which would keep
+    // the reader below from ever seeing the end
+    drop(command);
 
     let mut child = match child {
         Ok(c) => c,
-        Err(_) => return false,
+        Err(e) => {
+            eprintln!(
+                "{} Couldn't start `{}`: {}. Set \"shell\" in the config to a shell that exists (e.g. \"bash -c\").",
+                prefix(),
+                invocation(opts.login_shell).join(" "),
+                e
+            );
+            return Captured { code: exit_codes::FAILURE, ..Default::default() };
+        }
     };
 
-    let Some(timeout) = timeout_secs else {
+    // drain the pipes on their own threads so a chatty command can't fill one up
+    // and block forever while we wait on it
+    // (a pty reports EIO instead of EOF once the child is gone, which ends these too)
+    let limit = opts.max_capture_bytes.unwrap_or(u64::MAX);
+    let drain = |pipe: Option<Box<dyn Read + Send>>, stream: &'static str, dest: Option<Dest>| {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            let (Some(pipe), Some(dest)) = (pipe, dest) else {
+                return buf;
+            };
+            match dest {
+                Dest::Lines(sink) => {
+                    let mut line = Vec::new();
+                    let skipped = pump(pipe, limit, |chunk| {
+                        // every piece after the first one starts a new line
+                        let mut pieces = chunk.split(|&b| b == b'\n');
+                        line.extend_from_slice(pieces.next().unwrap_or_default());
+                        for piece in pieces {
+                            sink(stream, String::from_utf8_lossy(&line).trim_end_matches('\r'));
+                            line.clear();
+                            line.extend_from_slice(piece);
+                        }
+                    });
+                    if !line.is_empty() {
+                        sink(stream, String::from_utf8_lossy(&line).trim_end_matches('\r'));
+                    }
+                    if skipped > 0 {
+                        sink(stream, &truncated_marker(skipped));
+                    }
+                }
+                Dest::File(mut file) => {
+                    let skipped = pump(pipe, limit, |chunk| {
+                        let _ = file.write_all(chunk);
+                    });
+                    if skipped > 0 {
+                        let _ = writeln!(file, "{}", truncated_marker(skipped));
+                    }
+                }
+                Dest::Buffer => {
+                    let skipped = pump(pipe, limit, |chunk| buf.extend_from_slice(chunk));
+                    if skipped > 0 {
+                        buf.extend_from_slice(format!("{}\n", truncated_marker(skipped)).as_bytes());
+                    }
+                }
+            }
+            buf
+        })
+    };
+    let stdout = match pty_reader {
+        Some(reader) => drain(Some(Box::new(reader)), "stdout", out_dest),
+        None => drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>), "stdout", out_dest),
+    };
+    let stderr = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>), "stderr", err_dest);
+
+    let pid = child.id();
+    log::debug!("started pid {}: {} {:?}", pid, launcher(opts).join(" "), cmd_str);
+    if let Ok(mut running) = RUNNING.lock() {
+        running.push(pid);
+    }
+
+    let code = skip_code(wait_for(child, opts), opts.skip_exit_code);
+    log::debug!("pid {} exited with {}", pid, code);
+
+    if let Ok(mut running) = RUNNING.lock() {
+        running.retain(|&p| p != pid);
+    }
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        // the interrupt handler is wrapping up and will exit for us
+        loop {
+            thread::park();
+        }
+    }
+
+    Captured {
+        code,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }
+}
+
+// <alias>.log, or <alias>.<n>.log for the nth command of a parallel alias so
+// commands running side by side don't interleave their lines in one file
+pub fn log_path(alias: &str, index: Option<usize>) -> PathBuf {
+    match index {
+        Some(n) => Path::new(LOG_DIR).join(format!("{}.{}.log", alias, n)),
+        None => Path::new(LOG_DIR).join(format!("{}.log", alias)),
+    }
+}
+
+// every log the alias has: its single-command log and/or the numbered ones
+pub fn existing_logs(alias: &str) -> Vec<PathBuf> {
+    let single = Some(log_path(alias, None)).filter(|p| p.exists());
+    single.into_iter().chain((1..).map(|n| log_path(alias, Some(n))).take_while(|p| p.exists())).collect()
+}
+
+// logs not written to for longer than max_age, with their sizes, for cs clean.
+// the ones detached processes that are still running write to stay, however old
+pub fn stale_logs(max_age: Duration) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = fs::read_dir(LOG_DIR) else {
+        return Vec::new();
+    };
+    let live: Vec<PathBuf> = load_detached().into_iter().filter(is_alive).map(|p| p.log).collect();
+    let mut stale: Vec<(PathBuf, u64)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| Some((Path::new(LOG_DIR).join(e.file_name()), e.metadata().ok()?)))
+        .filter(|(path, meta)| meta.is_file() && !live.contains(path))
+        .filter(|(_, meta)| meta.modified().ok().and_then(|m| m.elapsed().ok()).is_some_and(|age| age > max_age))
+        .map(|(path, meta)| (path, meta.len()))
+        .collect();
+    stale.sort();
+    stale
+}
+
+// for {host}. "unknown" if the system won't say
+pub fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buf = [0u8; 256];
+        // SAFETY: the buffer outlives the call and its length is passed along
+        if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } == 0 {
+            let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+            return String::from_utf8_lossy(&buf[..end]).into_owned();
+        }
+    }
+    std::env::var("COMPUTERNAME").or_else(|_| std::env::var("HOSTNAME")).unwrap_or_else(|_| "unknown".to_string())
+}
+
+// start a command in the background with its output appended to a log file, and
+// remember it so cs ps / cs stop can find it. returns without waiting. index is
+// the command's 1-based position when it's one of several, see log_path
+pub fn spawn_detached(alias: &str, index: Option<usize>, cmd_str: &str, opts: &ExecOptions) -> Result<DetachedProcess> {
+    fs::create_dir_all(LOG_DIR).context("Failed to create log directory")?;
+    let log = log_path(alias, index);
+    let out = OpenOptions::new().create(true).append(true).open(&log).context("Failed to open log file")?;
+    let err = out.try_clone().context("Failed to open log file")?;
+
+    let mut command = shell_command(cmd_str, opts);
+    command.stdin(stdin_for(&ExecOptions { inherit_stdin: false, ..opts.clone() })?).stdout(out).stderr(err);
+    // own process group, so ctrl-c in this terminal doesn't reach it later
+    #[cfg(unix)]
+    command.process_group(0);
+    let child = command.spawn().context("Failed to start detached process")?;
+
+    let entry = DetachedProcess {
+        alias: alias.to_string(),
+        pid: child.id(),
+        started: unix_now(),
+        command: cmd_str.to_string(),
+        log,
+    };
+    log::info!("detached pid {} for '{}', logging to {}", entry.pid, alias, entry.log.display());
+    let mut procs = load_detached();
+    procs.push(entry.clone());
+    save_detached(&procs)?;
+    Ok(entry)
+}
+
+// is the tracked process still the one we started? kill -0 says the pid exists; the
+// elapsed time from ps guards against the pid having been reused by something else
+pub fn is_alive(p: &DetachedProcess) -> bool {
+    let exists = Command::new("kill")
+        .args(["-0", &p.pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if !exists {
+        return false;
+    }
+    match process_info(p.pid) {
+        // a zombie has exited, it's just waiting for its parent to notice
+        Some((stat, _)) if stat.starts_with('Z') => false,
+        // a few seconds of slack for the gap between spawn and recording
+        Some((_, age)) => unix_now().saturating_sub(age).abs_diff(p.started) <= 5,
+        // no usable ps, kill -0 is the best we've got
+        None => true,
+    }
+}
+
+// stop the whole process group, so whatever the shell started goes too. stale
+// entries are left alone rather than killing an unrelated process that got the pid
+pub fn stop_detached(p: &DetachedProcess) -> bool {
+    if !is_alive(p) {
+        return false;
+    }
+    let _ = Command::new("kill").args(["-TERM", "--", &format!("-{}", p.pid)]).status();
+    true
+}
+
+// process state and seconds since it started, from ps. etime is [[dd-]hh:]mm:ss
+fn process_info(pid: u32) -> Option<(String, u64)> {
+    let out = Command::new("ps").args(["-o", "stat=,etime=", "-p", &pid.to_string()]).output().ok()?;
+    let text = String::from_utf8_lossy(&out.stdout).to_string();
+    let mut cols = text.split_whitespace();
+    let (stat, etime) = (cols.next()?.to_string(), cols.next()?);
+    let (days, clock) = match etime.split_once('-') {
+        Some((d, rest)) => (d.parse::<u64>().ok()?, rest),
+        None => (0, etime),
+    };
+    let mut secs = 0;
+    for part in clock.split(':') {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some((stat, days * 86400 + secs))
+}
+
+// the configured skip status becomes exit_codes::SKIPPED. every other code,
+// a 75 of the child's own included, is passed through as it is
+fn skip_code(code: i32, skip_exit_code: Option<i32>) -> i32 {
+    match skip_exit_code {
+        Some(skip) if code == skip => exit_codes::SKIPPED,
+        _ => code,
+    }
+}
+
+// killed-by-signal has no code of its own, so that counts as a plain failure
+fn exit_code(status: std::io::Result<std::process::ExitStatus>, opts: &ExecOptions) -> i32 {
+    let Ok(status) = status else {
+        return exit_codes::FAILURE;
+    };
+    if let Some(reason) = limit_exceeded(&status, opts) {
+        eprintln!("{} {} {}", prefix(), "⚠".yellow(), reason);
+    }
+    status.code().unwrap_or(exit_codes::FAILURE)
+}
+
+fn wait_for(mut child: std::process::Child, opts: &ExecOptions) -> i32 {
+    let Some(timeout) = opts.timeout_secs else {
         // no timeout configured, just wait normally
-        return child.wait().map(|s| s.success()).unwrap_or(false);
+        return exit_code(child.wait(), opts);
     };
 
     let pid = child.id();
     let (tx, rx) = mpsc::channel();
 
     thread::spawn(move || {
-        let result = child.wait().map(|s| s.success()).unwrap_or(false);
-        let _ = tx.send(result);
+        let _ = tx.send(child.wait());
     });
 
     match rx.recv_timeout(Duration::from_secs(timeout)) {
-        Ok(result) => result,
+        Ok(status) => exit_code(status, opts),
         Err(_) => {
-            // kill the shell process and report failure
-            let _ = Command::new("kill").args(["-9", &pid.to_string()]).status();
-            false
+            // kill the shell and what it started, and report the timeout as its own outcome
+            log::info!("pid {} hit its {}s timeout, killing it", pid, timeout);
+            kill_tree(pid);
+            TIMED_OUT.store(true, Ordering::SeqCst);
+            eprintln!("{} Timed out after {}s", prefix(), timeout);
+            exit_codes::TIMED_OUT
         }
     }
 }
@@ -0,0 +1,230 @@
+use anyhow::{Result, anyhow, bail};
+use serde_json::{Map, Number, Value};
+use toml_edit::{Document, DocumentMut, Item, Table};
+
+// .cawa_cfg.toml support. the config is only ever (de)serialized as json values:
+// a toml file is turned into the same value a json file would parse to, and a
+// value is written back out as toml. that way Config and AliasConfig keep their
+// one serde implementation, and an alias is told apart the same way in both
+// formats: a string, an array (parallel), or a table whose run / parallel /
+// alias_of key says which kind it is
+
+pub fn to_json(content: &str) -> Result<Value> {
+    let doc = Document::parse(content).map_err(|e| anyhow!("{}", e.to_string().trim_end()))?;
+    table_to_json(doc.as_table().iter())
+}
+
+fn table_to_json<'a>(entries: impl Iterator<Item = (&'a str, &'a Item)>) -> Result<Value> {
+    let mut map = Map::new();
+    for (key, item) in entries {
+        map.insert(key.to_string(), item_to_json(item)?);
+    }
+    Ok(Value::Object(map))
+}
+
+fn item_to_json(item: &Item) -> Result<Value> {
+    match item {
+        Item::None => Ok(Value::Null),
+        Item::Value(v) => value_to_json(v),
+        Item::Table(t) => table_to_json(t.iter()),
+        Item::ArrayOfTables(tables) => Ok(Value::Array(
+            tables.iter().map(|t| table_to_json(t.iter())).collect::<Result<_>>()?,
+        )),
+    }
+}
+
+fn value_to_json(value: &toml_edit::Value) -> Result<Value> {
+    use toml_edit::Value as T;
+    Ok(match value {
+        T::String(s) => Value::String(s.value().clone()),
+        T::Integer(i) => Value::Number((*i.value()).into()),
+        T::Float(f) => Number::from_f64(*f.value())
+            .map(Value::Number)
+            .ok_or_else(|| anyhow!("{} can't be used as a number", f.value()))?,
+        T::Boolean(b) => Value::Bool(*b.value()),
+        // nothing in the config is a date, keep it as the text that was written
+        T::Datetime(d) => Value::String(d.value().to_string()),
+        T::Array(items) => Value::Array(items.iter().map(value_to_json).collect::<Result<_>>()?),
+        T::InlineTable(t) => {
+            let mut map = Map::new();
+            for (key, v) in t.iter() {
+                map.insert(key.to_string(), value_to_json(v)?);
+            }
+            Value::Object(map)
+        }
+    })
+}
+
+// `rank` orders the keys of the table at a path (the keys leading to it), lower
+// first; ties keep the value's own order. toml wants plain keys before any
+// [sub.table], so those always come first
+pub fn from_json(value: &Value, rank: &dyn Fn(&[&str], &str) -> usize) -> Result<String> {
+    let Value::Object(map) = value else {
+        bail!("only a table can be written as a toml document");
+    };
+    let mut out = String::new();
+    write_table(&mut out, &mut Vec::new(), map, rank)?;
+    Ok(out)
+}
+
+fn write_table<'a>(
+    out: &mut String,
+    path: &mut Vec<&'a str>,
+    map: &'a Map<String, Value>,
+    rank: &dyn Fn(&[&str], &str) -> usize,
+) -> Result<()> {
+    let mut keys: Vec<&String> = map.keys().filter(|k| !map[*k].is_null()).collect();
+    keys.sort_by_key(|k| rank(path, k));
+    let (tables, plain): (Vec<&String>, Vec<&String>) = keys.into_iter().partition(|k| map[*k].is_object());
+
+    // the top level has no header, and a table holding nothing but sub-tables
+    // doesn't need one either
+    if !path.is_empty() && (!plain.is_empty() || tables.is_empty()) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let header: Vec<String> = path.iter().map(|k| key(k)).collect();
+        out.push_str(&format!("[{}]\n", header.join(".")));
+    }
+    for k in plain {
+        out.push_str(&format!("{} = {}\n", key(k), inline(&map[k.as_str()])?));
+    }
+    for k in tables {
+        let Value::Object(sub) = &map[k.as_str()] else { unreachable!() };
+        path.push(k);
+        write_table(out, path, sub, rank)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+// `fresh` (from from_json) written over the toml in `existing`, changing only
+// what differs: comments, blank lines and the layout of everything else the
+// user wrote stay as they were
+pub fn update(existing: &str, fresh: &str) -> Result<String> {
+    let mut doc: DocumentMut = existing.parse().map_err(|e| anyhow!("{}", e))?;
+    let fresh: DocumentMut = fresh.parse().map_err(|e| anyhow!("{}", e))?;
+    merge(doc.as_table_mut(), fresh.as_table())?;
+    Ok(doc.to_string())
+}
+
+fn merge(old: &mut Table, new: &Table) -> Result<()> {
+    let gone: Vec<String> = old.iter().map(|(k, _)| k.to_string()).filter(|k| !new.contains_key(k)).collect();
+    for k in gone {
+        old.remove(&k);
+    }
+    for (k, new_item) in new.iter() {
+        // replaced in place rather than re-inserted, which would reset the key
+        // along with the comment above it
+        let Some(old_item) = old.get_mut(k) else {
+            old.insert(k, unplaced(new_item));
+            continue;
+        };
+        match (old_item, new_item) {
+            (Item::Table(old_table), Item::Table(new_table)) => merge(old_table, new_table)?,
+            (old_item, _) if item_to_json(old_item)? == item_to_json(new_item)? => {}
+            (Item::Value(old_value), Item::Value(new_value)) => {
+                // keeps a comment at the end of the line
+                let decor = old_value.decor().clone();
+                *old_value = new_value.clone();
+                *old_value.decor_mut() = decor;
+            }
+            (old_item, _) => *old_item = unplaced(new_item),
+        }
+    }
+    Ok(())
+}
+
+// a copy of a table from the fresh document without its position there, so it's
+// written after whatever comes before it here instead
+fn unplaced(item: &Item) -> Item {
+    let table_of = |t: &Table| {
+        let mut copy = Table::new();
+        copy.set_implicit(t.is_implicit());
+        *copy.decor_mut() = t.decor().clone();
+        for (k, v) in t.iter() {
+            copy.insert(k, unplaced(v));
+        }
+        copy
+    };
+    match item {
+        Item::Table(t) => Item::Table(table_of(t)),
+        Item::ArrayOfTables(tables) => {
+            let mut copy = toml_edit::ArrayOfTables::new();
+            for t in tables.iter() {
+                copy.push(table_of(t));
+            }
+            Item::ArrayOfTables(copy)
+        }
+        other => other.clone(),
+    }
+}
+
+fn key(k: &str) -> String {
+    if !k.is_empty() && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        k.to_string()
+    } else {
+        string(k)
+    }
+}
+
+fn inline(value: &Value) -> Result<String> {
+    Ok(match value {
+        // only reachable inside arrays; toml has no null, so there's no way to keep it
+        Value::Null => bail!("toml has no null, can't write one inside an array"),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) if n.is_f64() => {
+            let f = n.to_string();
+            if f.contains(['.', 'e', 'E']) { f } else { format!("{}.0", f) }
+        }
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.to_string(),
+            None => bail!("{} is too big for toml", n),
+        },
+        Value::String(s) if s.contains('\n') => multiline(s),
+        Value::String(s) => string(s),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(inline).collect::<Result<_>>()?;
+            format!("[{}]", items.join(", "))
+        }
+        Value::Object(map) => {
+            let fields: Vec<String> = map
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| Ok(format!("{} = {}", key(k), inline(v)?)))
+                .collect::<Result<_>>()?;
+            if fields.is_empty() { "{}".to_string() } else { format!("{{ {} }}", fields.join(", ")) }
+        }
+    })
+}
+
+// in a multi-line string a quote only needs escaping where it could close the
+// string: next to another quote, or at the very end
+fn escape(s: &str, multiline: bool) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' if multiline && !matches!(chars.peek(), Some('"') | None) => out.push('"'),
+            '"' => out.push_str("\\\""),
+            '\n' if multiline => out.push('\n'),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn string(s: &str) -> String {
+    format!("\"{}\"", escape(s, false))
+}
+
+// scripts added with --file stay readable: one line per line, like in the file.
+// the newline right after the opening quotes isn't part of the string
+fn multiline(s: &str) -> String {
+    format!("\"\"\"\n{}\"\"\"", escape(s, true))
+}
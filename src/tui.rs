@@ -1,22 +1,49 @@
 use anyhow::Result;
+use arboard::Clipboard;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{
     Terminal,
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, TableState, Tabs, Wrap},
 };
-use std::{io, time::Duration};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::cli::SortOrder;
+use crate::config::{
+    AliasConfig, AliasEntry, Config, TuiState, expand_entry, global_config_path, load_tui_state, reload_merged_config,
+    save_tui_state,
+};
+use crate::config::unix_now;
+use crate::history::{HistoryRecord, UsageStats, load_history};
+use crate::output::{format_duration, time_ago, timing_format};
 
-use crate::config::{AliasConfig, AliasEntry, Config};
+// what Enter picked: the highlighted alias, or every checked one in the order they
+// were checked. parallel only ever comes with checked ones (Alt+Enter)
+pub struct Picked {
+    pub aliases: Vec<String>,
+    pub parallel: bool,
+}
 
-pub fn run_tui(config: &Config) -> Result<Option<String>> {
+// `detach` starts an alias in the background and describes the outcome in one line.
+// with a `tag`, only the aliases carrying it are listed
+pub fn run_tui(
+    config: &Config,
+    config_path: &Path,
+    fresh: bool,
+    tag: Option<&str>,
+    detach: &dyn Fn(&str) -> String,
+) -> Result<Option<Picked>> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -25,8 +52,13 @@ pub fn run_tui(config: &Config) -> Result<Option<String>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let app = App::new(config);
-    let res = run_app(&mut terminal, app);
+    let mut app = App::new(config, config_path, tag);
+    if !fresh {
+        app.restore(&load_tui_state());
+    }
+    let res = run_app(&mut terminal, &mut app, detach);
+    // best effort, a read-only directory shouldn't stop the alias from running
+    let _ = save_tui_state(&app.snapshot());
 
     // restore terminal
     disable_raw_mode()?;
@@ -45,30 +77,209 @@ pub fn run_tui(config: &Config) -> Result<Option<String>> {
     Ok(res?)
 }
 
+struct AliasRow {
+    name: String,
+    display: String,
+    is_parallel: bool,
+    description: Option<String>,
+    // what would actually run, with variables expanded (copied by `y`)
+    command: String,
+    // turned off with `cs disable`, drawn dimmed and struck through
+    enabled: bool,
+    // marked `confirm`: Enter asks first
+    confirm: bool,
+    // its own and, for another name, those of what it points at
+    tags: Vec<String>,
+}
+
+// which part of a row the search query was found in, best first
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchField {
+    NamePrefix,
+    Name,
+    Tag,
+    Description,
+    Command,
+}
+
+impl MatchField {
+    // shown next to rows that didn't match on their name
+    fn label(self) -> Option<&'static str> {
+        match self {
+            MatchField::NamePrefix | MatchField::Name => None,
+            MatchField::Tag => Some("tag"),
+            MatchField::Description => Some("desc"),
+            MatchField::Command => Some("cmd"),
+        }
+    }
+}
+
+// the best field the (lowercased) query appears in, None if it's nowhere
+fn score(row: &AliasRow, q: &str) -> Option<MatchField> {
+    let name = row.name.to_lowercase();
+    if name.starts_with(q) {
+        Some(MatchField::NamePrefix)
+    } else if name.contains(q) {
+        Some(MatchField::Name)
+    } else if row.tags.iter().any(|t| t.to_lowercase().contains(q)) {
+        Some(MatchField::Tag)
+    } else if row.description.as_deref().is_some_and(|d| d.to_lowercase().contains(q)) {
+        Some(MatchField::Description)
+    } else if row.display.to_lowercase().contains(q) || row.command.to_lowercase().contains(q) {
+        Some(MatchField::Command)
+    } else {
+        None
+    }
+}
+
+// the views along the top, switched with Tab or their number
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum View {
+    Aliases,
+    History,
+    Stats,
+}
+
+impl View {
+    const ALL: [View; 3] = [View::Aliases, View::History, View::Stats];
+
+    fn title(self) -> &'static str {
+        match self {
+            View::Aliases => "1 Aliases",
+            View::History => "2 History",
+            View::Stats => "3 Stats",
+        }
+    }
+
+    fn index(self) -> usize {
+        View::ALL.iter().position(|&v| v == self).unwrap_or(0)
+    }
+}
+
+// one line of the stats view, from UsageStats
+struct StatsRow {
+    name: String,
+    runs: u32,
+    failures: u32,
+    avg_ms: u64,
+    last_run: u64,
+}
+
+fn build_stats(stats: &UsageStats) -> Vec<StatsRow> {
+    let mut rows: Vec<StatsRow> = stats
+        .runs
+        .iter()
+        .map(|(name, &runs)| StatsRow {
+            name: name.clone(),
+            runs,
+            failures: stats.failures.get(name).copied().unwrap_or(0),
+            avg_ms: stats.total_ms.get(name).copied().unwrap_or(0) / runs.max(1) as u64,
+            last_run: stats.last_run.get(name).copied().unwrap_or(0),
+        })
+        .collect();
+    // most used first, ties by name
+    rows.sort_by(|a, b| b.runs.cmp(&a.runs).then_with(|| a.name.cmp(&b.name)));
+    rows
+}
+
 struct App {
-    aliases: Vec<(String, String, bool, Option<String>)>, // (name, display_value, is_parallel, description)
+    view: View,
+    aliases: Vec<AliasRow>,
     filtered: Vec<usize>, // indices into aliases matching the current filter
+    // where each filtered row matched, same order as `filtered`
+    matched: Vec<MatchField>,
     filter: String,
     search_active: bool,
+    // toggled with space, run together by Enter
+    checked: Vec<String>,
+    sort: SortOrder,
+    stats: UsageStats,
     state: ListState,
+    // the history and stats views, newest run first. re-read whenever one is opened
+    history: Vec<HistoryRecord>,
+    history_state: TableState,
+    stats_rows: Vec<StatsRow>,
+    stats_state: TableState,
+    // one-off message shown in place of the key hints until the next key press
+    status: Option<String>,
+    // kept alive for the whole session; on X11 the copied text goes away with it
+    clipboard: Option<Clipboard>,
+    // watched so edits made in another window show up without restarting
+    config_path: PathBuf,
+    config_mtimes: (Option<SystemTime>, Option<SystemTime>),
+    // set while the config on disk doesn't parse; sticks until a good reload
+    warning: Option<String>,
+    // tui_confirm: ask before every run, not only the aliases marked confirm
+    confirm_all: bool,
+    // what Enter picked while the y/n popup is up; None the rest of the time
+    confirming: Option<Picked>,
+    // the popup came from o: a y starts it in the background instead of returning it
+    detaching: bool,
+    // --tag: the list only ever holds aliases with this tag, reloads included
+    tag: Option<String>,
 }
 
-impl App {
-    fn new(config: &Config) -> App {
-        let mut aliases: Vec<(String, String, bool, Option<String>)> = config
-            .aliases
-            .iter()
-            .map(|(k, ac): (&String, &AliasConfig)| {
-                let (display, is_parallel) = match &ac.entry {
-                    AliasEntry::Single(s) => (s.clone(), false),
-                    AliasEntry::Parallel(cmds) => (cmds.join(", "), true),
-                };
-                (k.clone(), display, is_parallel, ac.description.clone())
-            })
-            .collect();
+// the merged config comes from two files, so a change to either counts
+fn config_mtimes(config_path: &Path) -> (Option<SystemTime>, Option<SystemTime>) {
+    let mtime = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    (mtime(config_path), mtime(&global_config_path()))
+}
 
-        // sort for consistent display
-        aliases.sort_by(|a, b| a.0.cmp(&b.0));
+// rows in name order. another name has the tags of what it points at as well
+fn build_rows(config: &Config, tag: Option<&str>) -> Vec<AliasRow> {
+    let mut aliases: Vec<AliasRow> = config
+        .aliases
+        .iter()
+        .filter(|(_, ac)| !ac.hidden)
+        .map(|(k, ac): (&String, &AliasConfig)| {
+            // another name shows as a pointer but copies what it points at
+            let target = match config.resolve(k) {
+                Ok(Some((_, t))) => t,
+                _ => ac,
+            };
+            (k, ac, target)
+        })
+        .filter(|(_, ac, target)| tag.is_none_or(|tag| ac.tags.iter().chain(&target.tags).any(|t| t == tag)))
+        .map(|(k, ac, target)| {
+            let (display, is_parallel) = match &ac.entry {
+                AliasEntry::Single(s) => (s.clone(), false),
+                AliasEntry::Parallel(cmds) => (cmds.join(", "), true),
+                AliasEntry::Steps(steps) => (steps.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(" → "), false),
+                AliasEntry::Alias(t) => (format!("↪ {}", t), false),
+            };
+            // fall back to the raw text if a variable doesn't resolve
+            let resolved = expand_entry(&target.entry, config).unwrap_or_else(|_| target.entry.clone());
+            let command = match resolved {
+                AliasEntry::Single(s) => s,
+                AliasEntry::Parallel(cmds) => cmds.join("\n"),
+                AliasEntry::Steps(steps) => steps.iter().map(|s| s.command.as_str()).collect::<Vec<_>>().join("\n"),
+                AliasEntry::Alias(t) => t,
+            };
+            AliasRow {
+                name: k.clone(),
+                display,
+                is_parallel,
+                description: target.description.clone(),
+                command,
+                enabled: ac.enabled && target.enabled,
+                confirm: ac.confirm || target.confirm,
+                tags: {
+                    let mut tags = ac.tags.clone();
+                    tags.extend(target.tags.iter().filter(|t| !ac.tags.contains(t)).cloned());
+                    tags
+                },
+            }
+        })
+        .collect();
+
+    // sort for consistent display
+    aliases.sort_by(|a, b| a.name.cmp(&b.name));
+    aliases
+}
+
+impl App {
+    fn new(config: &Config, config_path: &Path, tag: Option<&str>) -> App {
+        let aliases = build_rows(config, tag);
 
         let filtered: Vec<usize> = (0..aliases.len()).collect();
         let mut state = ListState::default();
@@ -76,18 +287,177 @@ impl App {
             state.select(Some(0));
         }
 
-        App { aliases, filtered, filter: String::new(), search_active: false, state }
+        let history = load_history();
+        App {
+            view: View::Aliases,
+            aliases,
+            matched: vec![MatchField::Name; filtered.len()],
+            filtered,
+            filter: String::new(),
+            search_active: false,
+            checked: Vec::new(),
+            sort: SortOrder::Name,
+            stats: UsageStats::from_history(&history),
+            state,
+            history: Vec::new(),
+            history_state: TableState::default(),
+            stats_rows: Vec::new(),
+            stats_state: TableState::default(),
+            status: None,
+            clipboard: None,
+            config_path: config_path.to_path_buf(),
+            config_mtimes: config_mtimes(config_path),
+            warning: None,
+            confirm_all: config.tui_confirm.unwrap_or(false),
+            confirming: None,
+            detaching: false,
+            tag: tag.map(str::to_string),
+        }
+    }
+
+    // called every loop iteration; cheap unless a file actually changed
+    fn reload_if_changed(&mut self) {
+        let mtimes = config_mtimes(&self.config_path);
+        if mtimes == self.config_mtimes {
+            return;
+        }
+        self.config_mtimes = mtimes;
+
+        // editors often write in several steps, so a parse error here is usually
+        // transient: keep showing the old list until the next save lands
+        let config = match reload_merged_config(&self.config_path) {
+            Ok(c) => c,
+            Err(e) => {
+                self.warning = Some(format!("⚠ Config not reloaded: {}", e));
+                return;
+            }
+        };
+        self.warning = None;
+        self.confirm_all = config.tui_confirm.unwrap_or(false);
+
+        let selected_name = self.selected_row().map(|r| r.name.clone());
+        let old_index = self.state.selected();
+
+        self.aliases = build_rows(&config, self.tag.as_deref());
+        self.checked.retain(|name| self.aliases.iter().any(|r| r.name == *name));
+        self.stats.sort_names(&mut self.aliases, self.sort, |a| &a.name);
+        self.apply_filter();
+
+        // stay on the same alias if it's still there, otherwise the same spot
+        let same = selected_name.and_then(|name| self.filtered.iter().position(|&i| self.aliases[i].name == name));
+        let clamped = old_index.map(|i| i.min(self.filtered.len().saturating_sub(1)));
+        if !self.filtered.is_empty() {
+            self.state.select(same.or(clamped));
+        }
+    }
+
+    // put the sort and selection back the way the last session left them. the
+    // selected alias may have been removed since, then the old position is used
+    fn restore(&mut self, saved: &TuiState) {
+        if saved.sort != self.sort {
+            self.sort = saved.sort;
+            self.stats.sort_names(&mut self.aliases, self.sort, |a| &a.name);
+            self.apply_filter();
+        }
+        if self.filtered.is_empty() {
+            return;
+        }
+        let by_name = saved
+            .selected
+            .as_ref()
+            .and_then(|name| self.filtered.iter().position(|&i| self.aliases[i].name == *name));
+        self.state.select(Some(by_name.unwrap_or(saved.index.min(self.filtered.len() - 1))));
+    }
+
+    fn snapshot(&self) -> TuiState {
+        TuiState {
+            selected: self.selected_row().map(|r| r.name.clone()),
+            index: self.state.selected().unwrap_or(0),
+            sort: self.sort,
+        }
+    }
+
+    fn selected_row(&self) -> Option<&AliasRow> {
+        self.state
+            .selected()
+            .filter(|&i| i < self.filtered.len())
+            .map(|i| &self.aliases[self.filtered[i]])
+    }
+
+    fn copy_selected(&mut self) {
+        let Some(i) = self.state.selected().filter(|&i| i < self.filtered.len()) else {
+            return;
+        };
+        let row = &self.aliases[self.filtered[i]];
+
+        if self.clipboard.is_none() {
+            match Clipboard::new() {
+                Ok(c) => self.clipboard = Some(c),
+                Err(e) => {
+                    self.status = Some(format!("Clipboard unavailable: {}", e));
+                    return;
+                }
+            }
+        }
+        let result = self.clipboard.as_mut().map(|c| c.set_text(row.command.clone()));
+        self.status = Some(match result {
+            Some(Ok(())) => format!("Copied '{}' to the clipboard", row.name),
+            Some(Err(e)) => format!("Couldn't copy: {}", e),
+            None => "Clipboard unavailable".to_string(),
+        });
+    }
+
+    fn toggle_checked(&mut self) {
+        let Some(name) = self.selected_row().map(|r| r.name.clone()) else {
+            return;
+        };
+        match self.checked.iter().position(|n| *n == name) {
+            Some(i) => {
+                self.checked.remove(i);
+            }
+            None => self.checked.push(name),
+        }
+    }
+
+    // Enter: the checked aliases if there are any, the highlighted one otherwise
+    fn pick(&self, parallel: bool) -> Option<Picked> {
+        if !self.checked.is_empty() {
+            return Some(Picked { aliases: self.checked.clone(), parallel });
+        }
+        self.selected_row().map(|r| Picked { aliases: vec![r.name.clone()], parallel: false })
+    }
+
+    // run it straight away, or hold on to it until the popup gets a y
+    fn submit(&mut self, picked: Picked) -> Option<Picked> {
+        let ask = self.confirm_all
+            || picked.aliases.iter().any(|name| self.aliases.iter().any(|r| r.name == *name && r.confirm));
+        if ask {
+            self.confirming = Some(picked);
+            return None;
+        }
+        Some(picked)
+    }
+
+    fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+        // always start from name order so ties stay alphabetical
+        self.aliases.sort_by(|a, b| a.name.cmp(&b.name));
+        self.stats.sort_names(&mut self.aliases, self.sort, |a| &a.name);
+        self.apply_filter();
     }
 
     fn apply_filter(&mut self) {
         let q = self.filter.to_lowercase();
-        self.filtered = self
+        // an empty query matches every name, so everything shows in the current order
+        let mut hits: Vec<(usize, MatchField)> = self
             .aliases
             .iter()
             .enumerate()
-            .filter(|(_, (name, _, _, _))| name.to_lowercase().contains(&q))
-            .map(|(i, _)| i)
+            .filter_map(|(i, row)| score(row, &q).map(|m| (i, m)))
             .collect();
+        // better matches first; stable, so the sort order still breaks ties
+        hits.sort_by_key(|&(_, m)| m);
+        (self.filtered, self.matched) = hits.into_iter().unzip();
 
         // reset selection so we don't point at a now-invisible row
         if self.filtered.is_empty() {
@@ -97,6 +467,36 @@ impl App {
         }
     }
 
+    // runs started with `o` since the last look should show up, so the log is
+    // read again each time. a missing or empty log just leaves the tables empty
+    fn switch_to(&mut self, view: View) {
+        self.view = view;
+        if view == View::Aliases {
+            return;
+        }
+        let mut history = load_history();
+        self.stats = UsageStats::from_history(&history);
+        history.reverse();
+        self.history = history;
+        self.stats_rows = build_stats(&self.stats);
+        self.history_state.select((!self.history.is_empty()).then_some(0));
+        self.stats_state.select((!self.stats_rows.is_empty()).then_some(0));
+    }
+
+    // up/down in the history and stats tables
+    fn scroll(&mut self, down: bool) {
+        let (state, len) = match self.view {
+            View::History => (&mut self.history_state, self.history.len()),
+            View::Stats => (&mut self.stats_state, self.stats_rows.len()),
+            View::Aliases => return,
+        };
+        if len == 0 {
+            return;
+        }
+        let i = state.selected().unwrap_or(0);
+        state.select(Some(if down { (i + 1) % len } else { (i + len - 1) % len }));
+    }
+
     fn next(&mut self) {
         if self.filtered.is_empty() {
             return;
@@ -124,60 +524,106 @@ impl App {
     }
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<Option<String>> {
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    detach: &dyn Fn(&str) -> String,
+) -> io::Result<Option<Picked>> {
     loop {
+        app.reload_if_changed();
         terminal
-            .draw(|f| ui(f, &mut app))
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-
-        if event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    if app.search_active {
-                        match key.code {
-                            KeyCode::Esc => {
-                                // exit search mode and clear the filter
-                                app.search_active = false;
-                                app.filter.clear();
-                                app.apply_filter();
-                            }
-                            KeyCode::Backspace => {
-                                app.filter.pop();
-                                app.apply_filter();
-                            }
-                            KeyCode::Up => app.previous(),
-                            KeyCode::Down => app.next(),
-                            KeyCode::Enter => {
-                                if let Some(i) = app.state.selected() {
-                                    if i < app.filtered.len() {
-                                        return Ok(Some(app.aliases[app.filtered[i]].0.clone()));
-                                    }
-                                }
-                            }
-                            KeyCode::Char(c) => {
-                                app.filter.push(c);
-                                app.apply_filter();
-                            }
-                            _ => {}
+            .draw(|f| ui(f, app))
+            .map_err(|e| io::Error::other(e.to_string()))?;
+
+        if event::poll(Duration::from_millis(250))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            app.status = None;
+            // the popup takes every key until it's answered. only an explicit y runs:
+            // Enter is what opened it, so a double press mustn't count as a yes
+            if app.confirming.is_some() {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        let picked = app.confirming.take();
+                        if !std::mem::take(&mut app.detaching) {
+                            return Ok(picked);
                         }
-                    } else {
-                        match key.code {
-                            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
-                            KeyCode::Char('/') => {
-                                app.search_active = true;
-                            }
-                            KeyCode::Up | KeyCode::Char('k') => app.previous(),
-                            KeyCode::Down | KeyCode::Char('j') => app.next(),
-                            KeyCode::Enter => {
-                                if let Some(i) = app.state.selected() {
-                                    if i < app.filtered.len() {
-                                        return Ok(Some(app.aliases[app.filtered[i]].0.clone()));
-                                    }
-                                }
+                        if let Some(name) = picked.and_then(|p| p.aliases.into_iter().next()) {
+                            app.status = Some(detach(&name));
+                        }
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc | KeyCode::Char('q') => {
+                        app.confirming = None;
+                        app.detaching = false;
+                    }
+                    _ => {}
+                }
+            } else if app.search_active {
+                match key.code {
+                    KeyCode::Esc => {
+                        // exit search mode and clear the filter
+                        app.search_active = false;
+                        app.filter.clear();
+                        app.apply_filter();
+                    }
+                    KeyCode::Backspace => {
+                        app.filter.pop();
+                        app.apply_filter();
+                    }
+                    KeyCode::Up => app.previous(),
+                    KeyCode::Down => app.next(),
+                    KeyCode::Enter => {
+                        let parallel = key.modifiers.contains(KeyModifiers::ALT);
+                        if let Some(picked) = app.pick(parallel).and_then(|p| app.submit(p)) {
+                            return Ok(Some(picked));
+                        }
+                    }
+                    KeyCode::Char(c) => {
+                        app.filter.push(c);
+                        app.apply_filter();
+                    }
+                    _ => {}
+                }
+            } else {
+                match key.code {
+                    // Esc lets go of the checked aliases before it quits
+                    KeyCode::Esc if !app.checked.is_empty() => app.checked.clear(),
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                    KeyCode::Tab => app.switch_to(View::ALL[(app.view.index() + 1) % View::ALL.len()]),
+                    KeyCode::BackTab => {
+                        app.switch_to(View::ALL[(app.view.index() + View::ALL.len() - 1) % View::ALL.len()])
+                    }
+                    KeyCode::Char(c @ '1'..='3') => app.switch_to(View::ALL[c as usize - '1' as usize]),
+                    // the other views only scroll
+                    KeyCode::Up | KeyCode::Char('k') if app.view != View::Aliases => app.scroll(false),
+                    KeyCode::Down | KeyCode::Char('j') if app.view != View::Aliases => app.scroll(true),
+                    _ if app.view != View::Aliases => {}
+                    KeyCode::Char('/') => {
+                        app.search_active = true;
+                    }
+                    KeyCode::Char(' ') => app.toggle_checked(),
+                    KeyCode::Char('s') => app.cycle_sort(),
+                    KeyCode::Char('y') => app.copy_selected(),
+                    // start it in the background and stay here to pick the next one, asking
+                    // first the same as Enter would
+                    KeyCode::Char('o') => {
+                        if let Some(name) = app.selected_row().map(|r| r.name.clone()) {
+                            match app.submit(Picked { aliases: vec![name.clone()], parallel: false }) {
+                                Some(_) => app.status = Some(detach(&name)),
+                                None => app.detaching = true,
                             }
-                            _ => {}
                         }
                     }
+                    KeyCode::Up | KeyCode::Char('k') => app.previous(),
+                    KeyCode::Down | KeyCode::Char('j') => app.next(),
+                    KeyCode::Enter => {
+                        let parallel = key.modifiers.contains(KeyModifiers::ALT);
+                        if let Some(picked) = app.pick(parallel).and_then(|p| app.submit(p)) {
+                            return Ok(Some(picked));
+                        }
+                    }
+                    _ => {}
                 }
             }
         }
@@ -185,22 +631,87 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<O
 }
 
 fn ui(f: &mut ratatui::Frame, app: &mut App) {
-    let chunks = Layout::default()
+    let outer = Layout::default()
         .direction(Direction::Vertical)
-        // bottom panel is 4 lines: 2 borders + 2 content rows (description + search or hints)
-        .constraints([Constraint::Min(0), Constraint::Length(4)].as_ref())
+        // tabs on a line of their own, then the view, then the bottom panel: 2 borders
+        // + 2 content rows (description + search or hints)
+        .constraints([Constraint::Length(1), Constraint::Min(0), Constraint::Length(4)].as_ref())
         .split(f.area());
 
+    let tabs = Tabs::new(View::ALL.iter().map(|v| v.title()))
+        .select(app.view.index())
+        .style(Style::default().fg(Color::Gray))
+        .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    f.render_widget(tabs, outer[0]);
+
+    match app.view {
+        View::Aliases => aliases_view(f, app, &outer[1..]),
+        View::History => history_view(f, app, &outer[1..]),
+        View::Stats => stats_view(f, app, &outer[1..]),
+    }
+    if let Some(picked) = &app.confirming {
+        confirm_popup(f, &app.aliases, picked, app.detaching);
+    }
+}
+
+// drawn over the list: every command that's about to run, in full
+fn confirm_popup(f: &mut ratatui::Frame, rows: &[AliasRow], picked: &Picked, detaching: bool) {
+    let mut lines = Vec::new();
+    for name in &picked.aliases {
+        let command = rows.iter().find(|r| r.name == *name).map(|r| r.command.as_str()).unwrap_or("");
+        lines.push(Line::from(Span::styled(name.clone(), Style::default().add_modifier(Modifier::BOLD))));
+        for cmd in command.lines() {
+            lines.push(Line::from(Span::styled(format!("  {}", cmd), Style::default().fg(Color::Cyan))));
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("y: Run • n/Esc: Cancel", Style::default().fg(Color::Gray))));
+
+    let title = match picked.aliases.len() {
+        1 if detaching => " Start this in the background? ".to_string(),
+        1 => " Run this? ".to_string(),
+        n if picked.parallel => format!(" Run these {} in parallel? ", n),
+        n => format!(" Run these {} in order? ", n),
+    };
+    let area = centered(f.area(), 70, lines.len() as u16 + 2);
+    let popup = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)).title(title));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+// a box `percent` of the width wide and `height` rows high (at most the whole
+// screen) in the middle of `area`
+fn centered(area: Rect, percent: u16, height: u16) -> Rect {
+    let [row] = Layout::vertical([Constraint::Length(height.min(area.height))]).flex(Flex::Center).areas(area);
+    let [rect] = Layout::horizontal([Constraint::Percentage(percent)]).flex(Flex::Center).areas(row);
+    rect
+}
+
+// the list of aliases, the view the tui has always had
+fn aliases_view(f: &mut ratatui::Frame, app: &mut App, chunks: &[ratatui::layout::Rect]) {
+
     // inner width minus the highlight symbol so long commands don't get clipped silently
     let available_width = (chunks[0].width as usize).saturating_sub(7);
 
     let items: Vec<ListItem> = app
         .filtered
         .iter()
-        .map(|&idx| {
-            let (name, cmd, is_parallel, _desc) = &app.aliases[idx];
-            let prefix = format!("{}  ➜  ", name);
-            let reserved = prefix.len() + if *is_parallel { 11 } else { 0 };
+        .zip(&app.matched)
+        .map(|(&idx, m)| {
+            let AliasRow { name, display: cmd, is_parallel, enabled, .. } = &app.aliases[idx];
+            // checkboxes only show up once something's been checked
+            let mark = match app.checked.iter().position(|n| n == name) {
+                Some(i) => format!("[{}] ", i + 1),
+                None if !app.checked.is_empty() => "[ ] ".to_string(),
+                None => String::new(),
+            };
+            let prefix = match m.label() {
+                Some(field) if !app.filter.is_empty() => format!("{} ({})  ➜  ", name, field),
+                _ => format!("{}  ➜  ", name),
+            };
+            let reserved = mark.len() + prefix.len() + if *is_parallel { 11 } else { 0 };
             let max_cmd = available_width.saturating_sub(reserved).max(8);
             let truncated = if cmd.len() > max_cmd {
                 format!("{}…", &cmd[..max_cmd.saturating_sub(1)])
@@ -208,27 +719,40 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
                 cmd.clone()
             };
 
-            if *is_parallel {
-                let line = Line::from(vec![
+            let mark = Span::styled(mark, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD));
+            let mut line = if *is_parallel {
+                Line::from(vec![
+                    mark,
                     Span::raw(prefix),
                     Span::styled("[parallel] ", Style::default().fg(Color::Yellow)),
                     Span::styled(truncated, Style::default().fg(Color::Cyan)),
-                ]);
-                ListItem::new(line)
+                ])
             } else {
-                let line = Line::from(vec![
-                    Span::raw(prefix),
-                    Span::styled(truncated, Style::default().fg(Color::Cyan)),
-                ]);
-                ListItem::new(line)
+                Line::from(vec![mark, Span::raw(prefix), Span::styled(truncated, Style::default().fg(Color::Cyan))])
+            };
+            if !enabled {
+                for span in &mut line.spans {
+                    span.style = Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT);
+                }
             }
+            ListItem::new(line)
         })
         .collect();
 
+    let mut sort_note = if app.sort != SortOrder::Name {
+        format!(" · by {}", app.sort.label())
+    } else {
+        String::new()
+    };
+    if !app.checked.is_empty() {
+        sort_note.push_str(&format!(" · {} checked", app.checked.len()));
+    }
+    let logo = logo();
+    let tag_note = app.tag.as_ref().map(|t| format!(" #{}", t)).unwrap_or_default();
     let list_title = if !app.filter.is_empty() {
-        format!(" 🐙 CAWA Aliases ({} matches) ", app.filtered.len())
+        format!(" {}CAWA Aliases{} ({} matches){} ", logo, tag_note, app.filtered.len(), sort_note)
     } else {
-        " 🐙 CAWA Aliases ".to_string()
+        format!(" {}CAWA Aliases{}{} ", logo, tag_note, sort_note)
     };
 
     let aliases_list = List::new(items)
@@ -241,7 +765,7 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
     // build the bottom panel: description on top, search input or key hints below
     let desc_line = match app.state.selected() {
         Some(i) if i < app.filtered.len() => {
-            let desc = app.aliases[app.filtered[i]].3.as_deref().unwrap_or("");
+            let desc = app.aliases[app.filtered[i]].description.as_deref().unwrap_or("");
             Line::from(Span::styled(desc, Style::default().fg(Color::Gray)))
         }
         _ => Line::from(""),
@@ -257,6 +781,20 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
                 Style::default().fg(Color::DarkGray),
             ),
         ])
+    } else if let Some(status) = &app.status {
+        Line::from(Span::styled(status.as_str(), Style::default().fg(Color::Green)))
+    } else if let Some(warning) = &app.warning {
+        Line::from(Span::styled(warning.as_str(), Style::default().fg(Color::Yellow)))
+    } else if !app.checked.is_empty() {
+        Line::from(Span::styled(
+            "Space: Check/uncheck • Enter: Run checked in order • Alt+Enter: Run checked in parallel • Esc: Uncheck all",
+            Style::default().fg(Color::Gray),
+        ))
+    } else if let (true, Some(tag)) = (app.aliases.is_empty(), &app.tag) {
+        Line::from(Span::styled(
+            format!("No aliases are tagged '{}'. Tag one with `cs add --tag {} ...`, or run `cs tui` without --tag.", tag, tag),
+            Style::default().fg(Color::Gray),
+        ))
     } else if app.aliases.is_empty() {
         Line::from(Span::styled(
             "No aliases defined. Use `cs add` to create one.",
@@ -264,7 +802,7 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
         ))
     } else {
         Line::from(Span::styled(
-            "↑/↓: Navigate • Enter: Execute • /: Search • q: Quit",
+            "↑/↓: Navigate • Enter: Execute • Space: Check • o: Background • /: Search • s: Sort • y: Copy • Tab: Views • q: Quit",
             Style::default().fg(Color::Gray),
         ))
     };
@@ -274,3 +812,117 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
 
     f.render_widget(bottom, chunks[1]);
 }
+
+fn logo() -> &'static str {
+    if crate::output::emoji() { "🐙 " } else { "" }
+}
+
+// the bottom panel of the history and stats views; they only scroll
+fn table_hints(f: &mut ratatui::Frame, area: ratatui::layout::Rect, summary: String) {
+    let hints = Paragraph::new(Text::from(vec![
+        Line::from(Span::styled(summary, Style::default().fg(Color::Gray))),
+        Line::from(Span::styled(
+            "↑/↓: Scroll • Tab/1-3: Views • q: Quit",
+            Style::default().fg(Color::Gray),
+        )),
+    ]))
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(hints, area);
+}
+
+// shown instead of an empty table
+fn no_runs(f: &mut ratatui::Frame, area: ratatui::layout::Rect, title: String) {
+    let text = Paragraph::new("No runs recorded yet. Runs show up here once an alias has been run.")
+        .style(Style::default().fg(Color::Gray))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(text, area);
+}
+
+fn duration_text(ms: u64) -> String {
+    format_duration(Duration::from_millis(ms), timing_format())
+}
+
+// recent runs from the history log, newest first
+fn history_view(f: &mut ratatui::Frame, app: &mut App, chunks: &[ratatui::layout::Rect]) {
+    let title = format!(" {}Run History ({} runs) ", logo(), app.history.len());
+    let summary = match app.history_state.selected().and_then(|i| app.history.get(i)) {
+        Some(rec) => format!("{} ran {}", rec.alias, time_ago(unix_now().saturating_sub(rec.timestamp))),
+        None => String::new(),
+    };
+    if app.history.is_empty() {
+        no_runs(f, chunks[0], title);
+    } else {
+        let now = unix_now();
+        let rows = app.history.iter().map(|rec| {
+            let (result, color) = if rec.skipped {
+                ("↷ skipped", Color::Yellow)
+            } else if rec.timed_out {
+                ("⏱ timed out", Color::Red)
+            } else if rec.success {
+                ("✓ ok", Color::Green)
+            } else {
+                ("✗ failed", Color::Red)
+            };
+            Row::new(vec![
+                Cell::from(time_ago(now.saturating_sub(rec.timestamp))),
+                Cell::from(rec.alias.clone()),
+                Cell::from(Span::styled(result, Style::default().fg(color))),
+                Cell::from(duration_text(rec.duration_ms)),
+            ])
+        });
+        let widths = [Constraint::Length(10), Constraint::Min(12), Constraint::Length(12), Constraint::Length(10)];
+        let table = Table::new(rows, widths)
+            .header(Row::new(["When", "Alias", "Result", "Took"]).style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .row_highlight_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(table, chunks[0], &mut app.history_state);
+    }
+    table_hints(f, chunks[1], summary);
+}
+
+// per-alias success rate and timing over the whole log
+fn stats_view(f: &mut ratatui::Frame, app: &mut App, chunks: &[ratatui::layout::Rect]) {
+    let title = format!(" {}Alias Stats ({} aliases) ", logo(), app.stats_rows.len());
+    let summary = match app.stats_state.selected().and_then(|i| app.stats_rows.get(i)) {
+        Some(row) => format!("{}: {} of {} runs failed", row.name, row.failures, row.runs),
+        None => String::new(),
+    };
+    if app.stats_rows.is_empty() {
+        no_runs(f, chunks[0], title);
+    } else {
+        let now = unix_now();
+        let rows = app.stats_rows.iter().map(|row| {
+            let rate = 100 * (row.runs - row.failures) / row.runs.max(1);
+            let color = match rate {
+                100 => Color::Green,
+                50.. => Color::Yellow,
+                _ => Color::Red,
+            };
+            Row::new(vec![
+                Cell::from(row.name.clone()),
+                Cell::from(row.runs.to_string()),
+                Cell::from(Span::styled(format!("{}%", rate), Style::default().fg(color))),
+                Cell::from(duration_text(row.avg_ms)),
+                Cell::from(time_ago(now.saturating_sub(row.last_run))),
+            ])
+        });
+        let widths = [
+            Constraint::Min(12),
+            Constraint::Length(6),
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(["Alias", "Runs", "Success", "Avg", "Last run"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .row_highlight_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Cyan))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(table, chunks[0], &mut app.stats_state);
+    }
+    table_hints(f, chunks[1], summary);
+}
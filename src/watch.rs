@@ -0,0 +1,247 @@
+use anyhow::Result;
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::output::prefix;
+use crate::runner;
+
+// `cs watch`: rerun an alias whenever something under the watched paths changes.
+// files are polled rather than subscribed to, which is slower to notice but works
+// the same everywhere and needs no extra crate. the poller and the SIGHUP
+// listener send Triggers down one channel; keys are read by the loop itself,
+// since only it knows when the terminal is free to be raw
+
+const POLL: Duration = Duration::from_millis(500);
+// how often the loop looks up from the channel to check the keyboard
+const TICK: Duration = Duration::from_millis(100);
+// build output, vcs data and cs's own files would make every run trigger the next
+const IGNORED_DIRS: [&str; 4] = [".git", "target", "node_modules", ".cawa_logs"];
+
+pub enum Trigger {
+    // something under the watched paths, the first one noticed
+    Changed(PathBuf),
+    // r or SIGHUP: run now, no debounce
+    Manual(&'static str),
+    Quit,
+}
+
+pub struct Watch {
+    pub paths: Vec<PathBuf>,
+    pub debounce: Duration,
+}
+
+type Snapshot = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+// what the command writes under the watched paths itself mustn't trigger the next
+// run, or a build that touches its own inputs would loop forever. so the poller
+// throws away scans that overlap a run, and picks up from the files as the run
+// left them
+#[derive(Default)]
+struct Runs {
+    // bumped as each run starts and again as it ends, so it's odd while one is going
+    count: u64,
+    // taken as the last run finished, for the poller to compare against next
+    after: Option<Snapshot>,
+}
+
+impl Watch {
+    // runs `run` once straight away, then on every trigger until q, ctrl-c or the
+    // sources all go away. stopping is what the user asked for, so how the last
+    // run went doesn't matter by then
+    pub fn run(&self, mut run: impl FnMut()) -> Result<()> {
+        runner::interrupt_ends_watch();
+        let (tx, rx) = mpsc::channel();
+        let runs = Arc::new(Mutex::new(Runs::default()));
+        let mut run = || {
+            runs.lock().unwrap().count += 1;
+            run();
+            let after = snapshot(&self.paths);
+            let mut runs = runs.lock().unwrap();
+            runs.count += 1;
+            runs.after = Some(after);
+        };
+        self.spawn_poller(tx.clone(), runs.clone());
+        #[cfg(unix)]
+        spawn_hangup_listener(tx.clone());
+        drop(tx);
+
+        let keys = std::io::stdin().is_terminal();
+        let paths: Vec<String> = self.paths.iter().map(|p| p.display().to_string()).collect();
+        run();
+        loop {
+            println!(
+                "{} {}",
+                prefix(),
+                format!(
+                    "Watching {}. {}",
+                    paths.join(", "),
+                    if keys { "r reruns, q quits." } else { "SIGHUP reruns, ctrl-c quits." }
+                )
+                .dimmed()
+            );
+            match self.wait(&rx, keys)? {
+                Trigger::Changed(path) => {
+                    println!("{} {} changed, rerunning", prefix(), path.display().to_string().cyan());
+                }
+                Trigger::Manual(why) => println!("{} Rerunning ({})", prefix(), why),
+                Trigger::Quit => return Ok(()),
+            }
+            run();
+        }
+    }
+
+    // the next trigger worth a run. changes keep coming in while files are being
+    // saved, so a change only counts once the paths have been quiet for the
+    // debounce; a manual trigger doesn't wait
+    fn wait(&self, rx: &Receiver<Trigger>, keys: bool) -> Result<Trigger> {
+        let mut pending: Option<(PathBuf, Instant)> = None;
+        // the keyboard is only raw in here, so the command's own output and
+        // anything it reads from the terminal are left alone
+        let _raw = keys.then(RawMode::enable).transpose()?;
+        loop {
+            if let Some((path, at)) = &pending
+                && at.elapsed() >= self.debounce
+            {
+                return Ok(Trigger::Changed(path.clone()));
+            }
+            if keys && let Some(trigger) = read_key()? {
+                return Ok(trigger);
+            }
+            match rx.recv_timeout(if keys { Duration::ZERO } else { TICK }) {
+                Ok(Trigger::Changed(path)) => {
+                    let first = pending.map(|(p, _)| p).unwrap_or(path);
+                    pending = Some((first, Instant::now()));
+                }
+                Ok(trigger) => return Ok(trigger),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(Trigger::Quit),
+            }
+        }
+    }
+
+    fn spawn_poller(&self, tx: Sender<Trigger>, runs: Arc<Mutex<Runs>>) {
+        let paths = self.paths.clone();
+        // taken here rather than on the thread, which could otherwise start after
+        // the first run and count its writes as changes
+        let mut seen = snapshot(&paths);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(POLL);
+                let before = runs.lock().unwrap().count;
+                let now = snapshot(&paths);
+                {
+                    let mut runs = runs.lock().unwrap();
+                    if let Some(after) = runs.after.take() {
+                        seen = after;
+                    }
+                    if runs.count % 2 == 1 || runs.count != before {
+                        continue;
+                    }
+                }
+                // added, changed or removed, whichever sorts first
+                let changed = now
+                    .iter()
+                    .find(|(path, stamp)| seen.get(*path) != Some(stamp))
+                    .or_else(|| seen.iter().find(|(path, _)| !now.contains_key(*path)))
+                    .map(|(path, _)| path.clone());
+                seen = now;
+                if let Some(path) = changed
+                    && tx.send(Trigger::Changed(path)).is_err()
+                {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+// every file under the paths with its mtime and size. a path that doesn't exist
+// (yet) is just empty, so creating it counts as a change
+fn snapshot(paths: &[PathBuf]) -> Snapshot {
+    let mut files = BTreeMap::new();
+    for path in paths {
+        walk(path, &mut files);
+    }
+    files
+}
+
+fn walk(path: &Path, files: &mut Snapshot) {
+    let Ok(meta) = fs::metadata(path) else { return };
+    if !meta.is_dir() {
+        files.insert(path.to_path_buf(), (meta.modified().ok(), meta.len()));
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else { return };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(".cawa_") || IGNORED_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+        walk(&entry.path(), files);
+    }
+}
+
+fn read_key() -> Result<Option<Trigger>> {
+    if !event::poll(TICK)? {
+        return Ok(None);
+    }
+    Ok(match event::read()? {
+        Event::Key(KeyEvent { code, modifiers, kind: KeyEventKind::Press, .. }) => match code {
+            KeyCode::Char('r') | KeyCode::Enter => Some(Trigger::Manual("r pressed")),
+            KeyCode::Char('q') => Some(Trigger::Quit),
+            // raw mode swallows ctrl-c, so it arrives here instead of as SIGINT
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Some(Trigger::Quit),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+// SIGHUP only sets a flag, the handler can't do much more than that safely; a
+// thread turns it into a trigger
+#[cfg(unix)]
+fn spawn_hangup_listener(tx: Sender<Trigger>) {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    static HANGUP: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_hangup(_: libc::c_int) {
+        HANGUP.store(true, Ordering::SeqCst);
+    }
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGHUP, on_hangup as *const () as libc::sighandler_t);
+    }
+    thread::spawn(move || {
+        loop {
+            thread::sleep(TICK);
+            if HANGUP.swap(false, Ordering::SeqCst) && tx.send(Trigger::Manual("SIGHUP")).is_err() {
+                return;
+            }
+        }
+    });
+}
@@ -0,0 +1,59 @@
+use anyhow::{Result, bail};
+use colored::Colorize;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::output::prefix;
+
+// what's POSTed to webhook_url after each run, for CI dashboards and the like
+#[derive(Serialize)]
+pub struct RunSummary<'a> {
+    pub alias: &'a str,
+    // false only for a failure, a skipped run has skipped set instead
+    pub success: bool,
+    pub skipped: bool,
+    pub duration_ms: u64,
+    pub exit_code: i32,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    summary: &'a RunSummary<'a>,
+    // unix time in milliseconds, same as the --output json events
+    timestamp: u64,
+}
+
+// a webhook that's down or slow must never fail the run it's reporting on, so
+// problems are only warned about
+pub fn post(url: &str, summary: &RunSummary) {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    if let Err(e) = send(url, &Payload { summary, timestamp }) {
+        eprintln!("{} {} Couldn't post to the webhook: {:#}", prefix(), "⚠".yellow(), e);
+    }
+}
+
+#[cfg(not(feature = "webhook"))]
+fn send(url: &str, _payload: &Payload) -> Result<()> {
+    bail!("this cs was built without the webhook feature, not posting to {}", url)
+}
+
+// ureq with rustls, so https endpoints work out of the box. a slow endpoint
+// gets a few seconds, not the whole run's patience
+#[cfg(feature = "webhook")]
+fn send(url: &str, payload: &Payload) -> Result<()> {
+    use anyhow::Context;
+    use std::time::Duration;
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(5)))
+        .user_agent(concat!("cawa/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .into();
+    let body = serde_json::to_vec(payload)?;
+    match agent.post(url).content_type("application/json").send(&body[..]) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::StatusCode(code)) => bail!("{} answered {}", url, code),
+        Err(e) => Err(e).with_context(|| format!("couldn't reach {}", url)),
+    }
+}
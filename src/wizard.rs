@@ -3,7 +3,8 @@ use colored::*;
 use std::io::{self, Write};
 use std::path::Path;
 
-use crate::config::{AliasConfig, AliasEntry, Config, save_config, CONFIG_FILE};
+use crate::config::{AliasConfig, AliasEntry, Config, rfc3339_now, save_config};
+use crate::output::prefix;
 
 // read a line from stdin, stripping the trailing newline
 fn prompt(label: &str) -> Result<String> {
@@ -25,15 +26,16 @@ fn confirm(label: &str, default_yes: bool) -> Result<bool> {
     })
 }
 
-pub fn run_init() -> Result<()> {
-    let oct = "🐙".truecolor(80, 80, 80);
+pub fn run_init(config_path: &Path) -> Result<()> {
+    let config_name = config_path.display().to_string();
+    let oct = prefix();
 
     println!("{} Setting up cawa for this project.", oct);
     println!();
 
     // warn if a config already exists so the user doesn't accidentally blow it away
-    if Path::new(CONFIG_FILE).exists() {
-        println!("{} {} already exists.", oct, CONFIG_FILE.yellow());
+    if config_path.exists() {
+        println!("{} {} already exists.", oct, config_name.yellow());
         if !confirm("Overwrite it?", false)? {
             println!("{} Aborted.", oct);
             return Ok(());
@@ -65,10 +67,12 @@ pub fn run_init() -> Result<()> {
         if d.is_empty() { None } else { Some(d) }
     };
 
+    let now = rfc3339_now();
     aliases.insert(name.clone(), AliasConfig {
-        entry: AliasEntry::Single(cmd),
         description: desc,
-        timeout_secs: None,
+        created_at: Some(now.clone()),
+        updated_at: Some(now),
+        ..AliasConfig::new(AliasEntry::Single(cmd))
     });
 
     println!();
@@ -87,19 +91,20 @@ pub fn run_init() -> Result<()> {
 
     // timing — explain what it does so the y/n isn't a guess
     println!("  {}", "Optional: print how long each command took after it finishes.".dimmed());
-    println!("  {}", "Useful for slow builds. Can be toggled in .cawa_cfg.json later.".dimmed());
+    println!("  {}", format!("Useful for slow builds. Can be toggled in {} later.", config_name).dimmed());
     let enable_timing = confirm("  Enable execution timing?", false)?;
 
     let config = Config {
         identifier,
         enable_timing: if enable_timing { Some(true) } else { None },
         aliases,
+        ..Default::default()
     };
 
-    save_config(&config)?;
+    save_config(&config, config_path)?;
 
     println!();
-    println!("{} {} created. Run {} to try your first alias.", oct, CONFIG_FILE.cyan(), format!("cs {}", name).bold());
+    println!("{} {} created. Run {} to try your first alias.", oct, config_name.cyan(), format!("cs {}", name).bold());
 
     Ok(())
 }
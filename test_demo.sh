@@ -9,7 +9,7 @@ CS="./target/debug/cs"
 CLI_JSON=".cawa_cfg.json"
 
 # fresh start - wipe old config
-rm -f "$CLI_JSON"
+rm -f "$CLI_JSON" .cawa_state.json .cawa_last_run.json .cawa_history.jsonl
 
 echo "🐙 Testing cs functionality..."
 
@@ -34,15 +34,143 @@ else
     exit 1
 fi
 
+# --format man is plain troff; compared against a known-good page
+echo -n "  Testing 'list man'..."
+cp "$CLI_JSON" man_backup.json
+echo '{ "aliases": { "build": { "run": "cargo build --target {target}", "params": ["target"], "description": "Build the project." }, "b": { "alias_of": "build" }, "checks": { "parallel": ["make lint", "make test"] }, "helper": { "run": "true", "hidden": true } } }' > "$CLI_JSON"
+MAN=$($CS list --format man)
+mv man_backup.json "$CLI_JSON"
+EXPECTED=$(cat << 'EOF'
+.TH CS 1 "" "cs" "Project Aliases"
+.SH NAME
+cs \- aliases for this project
+.SH SYNOPSIS
+.B cs
+.I alias
+[\fIargs\fR...]
+.SH ALIASES
+.TP
+\fBcs b\fR \fB\-\-target\fR \fItarget\fR [\fIargs\fR...]
+Build the project.
+.br
+Another name for \fBbuild\fR.
+.br
+Runs: \fBcargo build \-\-target {target}\fR
+.TP
+\fBcs build\fR \fB\-\-target\fR \fItarget\fR [\fIargs\fR...]
+Build the project.
+.br
+Runs: \fBcargo build \-\-target {target}\fR
+.TP
+\fBcs checks\fR [\fIargs\fR...]
+No description.
+.br
+Runs in parallel:
+.br
+\(bu \fBmake lint\fR
+.br
+\(bu \fBmake test\fR
+EOF
+)
+if [[ "$MAN" != "$EXPECTED" ]]; then
+    echo "❌"
+    diff <(echo "$EXPECTED") <(echo "$MAN")
+    exit 1
+fi
+echo "✅"
+
+# tags are stored by cs add and shown by cs list
+echo -n "  Testing 'tags'..."
+cp "$CLI_JSON" tags_backup.json
+$CS add --tag deploy --tag prod tagged_ship "echo ship" > /dev/null
+OUTPUT=$($CS list --no-status)
+STORED=$(tr -d ' \n' < "$CLI_JSON")
+mv tags_backup.json "$CLI_JSON"
+if [[ "$STORED" != *'"tags":["deploy","prod"]'* ]] || [[ "$OUTPUT" != *"tagged_ship #deploy #prod"* ]]; then
+    echo "❌ (Config: $STORED / List: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# cs export --format make, compared against a known-good Makefile
+echo -n "  Testing 'export make'..."
+cp "$CLI_JSON" make_backup.json
+echo '{ "variables": { "profile": "release" }, "aliases": { "build": { "run": "cargo build --profile ${profile} --target-dir $TARGET", "description": "Build the project." }, "b": { "alias_of": "build" }, "checks": { "parallel": ["make lint", "make test"], "append_args": false }, "ci": { "steps": [ { "name": "build", "command": "cargo build" }, { "name": "test", "command": "cargo test" } ] }, "deploy": { "run": "./deploy.sh {env}", "params": ["env"], "append_args": false }, "db:reset": "./reset.sh", "script": "echo a\necho b" } }' > "$CLI_JSON"
+MAKEFILE=$($CS export --format make 2> export_err.txt)
+WARNINGS=$(cat export_err.txt)
+$CS export -o export_test.mk > /dev/null 2>&1
+mv make_backup.json "$CLI_JSON"
+EXPECTED=$(cat << 'EOF'
+# Generated by `cs export --format make`. Change the cs config and export
+# again rather than editing this file.
+# Runtime arguments go in ARGS (make test ARGS=--verbose), parameters by
+# name (make deploy env=prod).
+
+.DEFAULT_GOAL := help
+.PHONY: help b build checks ci db_reset deploy
+
+help: ## Show the targets
+	@grep -E '^[A-Za-z0-9_.+-]+:.*## ' $(MAKEFILE_LIST) | awk 'BEGIN {FS = ":.*## "}; {printf "  %-20s %s\n", $$1, $$2}'
+
+b: ## Build the project.
+	cargo build --profile release --target-dir $$TARGET $(ARGS)
+
+build: ## Build the project.
+	cargo build --profile release --target-dir $$TARGET $(ARGS)
+
+checks: ## No description.
+	status=0; \
+	( make lint ) & pid0=$$!; \
+	( make test ) & pid1=$$!; \
+	wait $$pid0 || status=1; \
+	wait $$pid1 || status=1; \
+	exit $$status
+
+ci: ## No description.
+	@echo '▶ build'
+	cargo build $(ARGS)
+	@echo '▶ test'
+	cargo test $(ARGS)
+
+db_reset: ## No description. (cs db:reset)
+	./reset.sh $(ARGS)
+
+deploy: ## No description.
+	@test -n "$(env)" || { echo "make $@ needs env=..." >&2; exit 2; }
+	./deploy.sh '$(env)'
+EOF
+)
+if [[ "$MAKEFILE" != "$EXPECTED" ]] || [[ "$(cat export_test.mk)" != "$EXPECTED" ]]; then
+    echo "❌"
+    diff <(echo "$EXPECTED") <(echo "$MAKEFILE")
+    rm -f export_err.txt export_test.mk
+    exit 1
+fi
+if [[ "$WARNINGS" != *"Left out 'script': a multi-line script"* ]]; then
+    echo "❌ (Expected a warning about the multi-line script: $WARNINGS)"
+    rm -f export_err.txt export_test.mk
+    exit 1
+fi
+rm -f export_err.txt export_test.mk
+echo "✅"
+
 # nuke it
 echo -n "  Testing 'remove'..."
 $CS remove hello > /dev/null
 if grep -q "hello" "$CLI_JSON"; then
     echo "❌"
     exit 1
-else
-    echo "✅"
 fi
+# removing it again has nothing to remove, which scripts need to see in the exit code
+set +e
+$CS remove hello > /dev/null 2>&1
+REMOVE_CODE=$?
+set -e
+if [[ $REMOVE_CODE -ne 3 ]]; then
+    echo "❌ (Removing a missing alias exited $REMOVE_CODE, expected 3)"
+    exit 1
+fi
+echo "✅"
 
 # parallel toggle test
 echo -n "  Testing 'parallel'..."
@@ -63,17 +191,1756 @@ if [[ "$OUTPUT" == *"finished_1"* && "$OUTPUT" == *"finished_2"* ]]; then
     echo '{ "enable_timing": true, "aliases": { "par": ["sleep 1 && echo finished_1", "sleep 1 && echo finished_2"] } }' > "$CLI_JSON"
     
     OUTPUT_TIMED=$($CS par)
-    if [[ "$OUTPUT_TIMED" == *"⏱️"* ]]; then
-          echo "✅ (Default hidden, Enabled visible)"
-    else
+    if [[ "$OUTPUT_TIMED" != *"⏱️"* ]]; then
           echo "❌ (Timing failed to show after enabling)"
           exit 1
     fi
 
+    # fast runs under the threshold stay quiet
+    echo '{ "enable_timing": true, "timing_threshold_ms": 60000, "aliases": { "quick": "true" } }' > "$CLI_JSON"
+    OUTPUT_QUICK=$($CS quick)
+    if [[ "$OUTPUT_QUICK" == *"⏱️"* ]]; then
+          echo "❌ (Timing shown for a run under the threshold)"
+          exit 1
+    fi
+
+    # timing_format changes the unit
+    echo '{ "enable_timing": true, "timing_format": "ms", "aliases": { "nap": "sleep 0.2" } }' > "$CLI_JSON"
+    OUTPUT_MS=$($CS nap)
+    echo '{ "enable_timing": true, "timing_format": "s", "aliases": { "nap": "sleep 0.2" } }' > "$CLI_JSON"
+    OUTPUT_S=$($CS nap)
+    if ! grep -qE '[0-9]+ms$' <<< "$OUTPUT_MS" || ! grep -qE '0\.[0-9]{3}s$' <<< "$OUTPUT_S"; then
+          echo "❌ (timing_format ignored: '$OUTPUT_MS' / '$OUTPUT_S')"
+          exit 1
+    fi
+    # minutes and hours read as such; --verbose shows retry waits without sitting through them
+    echo '{ "aliases": { "slow": { "run": "true", "retries": 2, "retry_delay_ms": 90000, "retry_backoff": "exponential", "retry_max_delay_ms": 99999999 }, "slower": { "run": "true", "retries": 1, "retry_delay_ms": 5430000, "retry_max_delay_ms": 99999999 } } }' > "$CLI_JSON"
+    WAITS_MIN=$($CS --verbose --dry-run slow 2>&1 | grep -o 'waits [^,]*, [^,]*,')
+    WAITS_HOUR=$($CS --verbose --dry-run slower 2>&1 | grep -o 'waits [^)]*')
+    if [[ "$WAITS_MIN" != "waits 1m 30s, 3m," ]] || [[ "$WAITS_HOUR" != "waits 1h 30m 30s" ]]; then
+          echo "❌ (Long durations: '$WAITS_MIN' / '$WAITS_HOUR')"
+          exit 1
+    fi
+    # the banner lists the commands one per line, numbered; --quiet-out leaves the list out
+    echo '{ "aliases": { "pair": ["echo one", "echo two"] } }' > "$CLI_JSON"
+    LISTED=$($CS pair | sed 's/\x1b\[[0-9;]*m//g')
+    QUIETED=$($CS --quiet-out pair)
+    if [[ "$LISTED" != *$'\n    1. echo one\n    2. echo two'* ]] || [[ "$QUIETED" == *"1."* ]]; then
+          echo "❌ (Parallel listing: '$LISTED' / with --quiet-out: '$QUIETED')"
+          exit 1
+    fi
+    echo "✅ (Default hidden, Enabled visible, Threshold respected, Format applied)"
+
 else
     echo "❌ (Output: $OUTPUT)"
     exit 1
 fi
 
-echo "🎉 All manual tests passed!"
+# variables - nested refs expand, unknown ones refuse to run
+echo -n "  Testing 'variables'..."
+rm -f "$CLI_JSON"
+$CS set-var base hello > /dev/null
+$CS set-var greet '${base} world' > /dev/null
+$CS add greet 'echo ${greet}' > /dev/null
+$CS add broken 'echo ${missing_var}' > /dev/null
+OUTPUT=$($CS greet)
+if [[ "$OUTPUT" != *"hello world"* ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+if $CS broken > /dev/null 2>&1; then
+    echo "❌ (Unknown variable should fail)"
+    exit 1
+fi
+echo "✅"
+
+# {{template:name}} pastes in shared fragments, nested ones too; cycles refuse to run
+echo -n "  Testing 'templates'..."
+rm -f "$CLI_JSON"
+$CS set-var who world > /dev/null
+$CS template add hi 'echo hello' > /dev/null
+$CS template add greet '{{template:hi}} ${who}' > /dev/null
+$CS add greet '{{template:greet}} again' > /dev/null
+$CS template add ping '{{template:pong}}' > /dev/null 2>&1
+$CS template add pong '{{template:ping}}' > /dev/null 2>&1
+$CS add loop '{{template:ping}}' > /dev/null
+OUTPUT=$($CS greet)
+set +e
+LOOP=$($CS loop 2>&1)
+LOOP_CODE=$?
+set -e
+if [[ "$OUTPUT" != *"hello world again"* ]] || [[ $LOOP_CODE -ne 2 ]] || [[ "$LOOP" != *"Template cycle"* ]]; then
+    echo "❌ (Greet: $OUTPUT / Loop: $LOOP_CODE $LOOP)"
+    exit 1
+fi
+# a {param} that only appears in a template is still asked for
+echo '{ "templates": { "deploy_to": "echo deploying to {env}" }, "aliases": { "ship": { "run": "{{template:deploy_to}}", "params": ["env"] } } }' > "$CLI_JSON"
+OUTPUT=$($CS ship --env staging 2>&1)
+set +e
+MISSING=$($CS ship 2>&1)
+MISSING_CODE=$?
+set -e
+if [[ "$OUTPUT" != *"deploying to staging"* ]] || [[ $MISSING_CODE -ne 4 ]] || [[ "$MISSING" == *"deploying"* ]]; then
+    echo "❌ (Template params: $OUTPUT / Missing: $MISSING_CODE $MISSING)"
+    exit 1
+fi
+echo "✅"
+
+# $env:NAME reads the whole command from the environment
+echo -n "  Testing 'env command'..."
+rm -f "$CLI_JSON"
+$CS add from_env '$env:CAWA_DEMO_CMD' > /dev/null
+if [[ "$(CAWA_DEMO_CMD='echo from_the_env' $CS from_env)" != *"from_the_env"* ]]; then
+    echo "❌ (Command wasn't read from the environment)"
+    exit 1
+fi
+if $CS from_env > /dev/null 2>&1; then
+    echo "❌ (Unset variable should fail)"
+    exit 1
+fi
+echo "✅"
+
+# _comment fields are ignored but survive a rewrite of the config
+echo -n "  Testing 'comments'..."
+echo '{ "_comment": "top_note", "aliases": { "noted": { "_comment": "alias_note", "run": "echo noted_ran" } } }' > "$CLI_JSON"
+$CS add another "true" > /dev/null
+if [[ "$($CS noted)" != *"noted_ran"* ]] || ! grep -q top_note "$CLI_JSON" || ! grep -q alias_note "$CLI_JSON"; then
+    echo "❌ (Comments lost or broke parsing: $(cat "$CLI_JSON"))"
+    exit 1
+fi
+echo "✅"
+
+# pipe_to goes after runtime args
+echo -n "  Testing 'pipe_to'..."
+rm -f "$CLI_JSON"
+$CS add --pipe-to "tr a-z A-Z" shout "echo" > /dev/null
+OUTPUT=$($CS --dry-run shout hello)
+if [[ "$OUTPUT" != *"echo hello | tr a-z A-Z"* ]] || [[ "$($CS shout hello)" != *"HELLO"* ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# everything after the alias name is the command's, even flags cs also has
+echo -n "  Testing 'alias args'..."
+echo '{ "aliases": { "args": "printf \"[%s]\"" } }' > "$CLI_JSON"
+for args in "--output file.txt -v --env X=1 --dry-run" "-- --output file.txt -v --env X=1 --dry-run"; do
+    OUTPUT=$($CS args $args 2>&1 | tail -1)
+    if [[ "$OUTPUT" != "[--output][file.txt][-v][--env][X=1][--dry-run]" ]]; then
+        echo "❌ ('cs args $args' should pass every arg on: $OUTPUT)"
+        exit 1
+    fi
+done
+if [[ "$($CS --dry-run args -v)" != *"Would run"* ]]; then
+    echo "❌ (cs flags before the alias name should still count)"
+    exit 1
+fi
+echo "✅"
+
+# {item} templates expand to one command per item
+echo -n "  Testing 'items'..."
+echo '{ "aliases": { "per_item": { "parallel": ["echo fixed", "echo got_{item}"], "items": ["x", "y", "z"] } } }' > "$CLI_JSON"
+OUTPUT=$($CS --dry-run per_item)
+if [[ $(grep -c "echo" <<< "$OUTPUT") -ne 4 || "$OUTPUT" != *"echo got_y"* ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+if [[ $($CS --jobs 1 per_item | grep -c "^got_") -ne 3 ]]; then
+    echo "❌ (--jobs 1 didn't run every item)"
+    exit 1
+fi
+echo "✅"
+
+# env files: quoting and comments, the real environment beats the file, --env beats both
+echo -n "  Testing 'env file'..."
+cat > test.env <<'ENVEOF'
+# a comment
+export PLAIN=plain value # trailing comment
+DOUBLE="say \"hi\" # kept"
+SINGLE='$HOME stays literal'
+OVERRIDDEN=from_file
+ENVEOF
+echo '{ "env_file": "test.env", "aliases": { "show_env": "echo \"[$PLAIN][$DOUBLE][$SINGLE][$OVERRIDDEN]\"" } }' > "$CLI_JSON"
+OUTPUT=$(OVERRIDDEN=from_shell $CS show_env)
+if [[ "$OUTPUT" != *'[plain value][say "hi" # kept][$HOME stays literal][from_shell]'* ]]; then
+    rm -f test.env
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+if [[ "$(OVERRIDDEN=from_shell $CS --env OVERRIDDEN=from_flag show_env)" != *"[from_flag]"* ]]; then
+    rm -f test.env
+    echo "❌ (--env should win over the environment)"
+    exit 1
+fi
+rm -f test.env
+# a missing env file only stops aliases, the rest of cs still works
+set +e
+$CS show_env > /dev/null 2>&1
+run_code=$?
+$CS list > /dev/null 2>&1
+list_code=$?
+DOCTOR=$($CS doctor 2>&1)
+set -e
+if [[ $run_code -ne 2 ]] || [[ $list_code -ne 0 ]] || [[ "$DOCTOR" != *"env file loads — Failed to read env file"* ]]; then
+    echo "❌ (Missing env file: run exited $run_code, list $list_code, doctor said: $DOCTOR)"
+    exit 1
+fi
+echo "✅"
+
+# --verbose and --dump-resolved say where each variable came from and mask secrets
+echo -n "  Testing 'env sources'..."
+printf 'API_TOKEN=hunter2\nFROM_FILE=file\nSHADOWED=file\nFLAGGED=file\n' > test.env
+echo '{ "env_file": "test.env", "secret_env": ["*_TOKEN", "FLAGGED"], "aliases": { "show_env": "echo \"[$API_TOKEN]\"", "sudo_env": { "run": "echo \"[$API_TOKEN]\"", "run_as": "root" } } }' > "$CLI_JSON"
+VERBOSE=$(SHADOWED=shell $CS -v --env FLAGGED=flag show_env 2>&1)
+DUMP=$(SHADOWED=shell $CS --dump-resolved --env FLAGGED=flag show_env)
+SUDO_DUMP=$($CS --dump-resolved sudo_env)
+rm -f test.env
+if [[ "$VERBOSE" != *"FROM_FILE=file ("*"test.env)"* ]] || [[ "$VERBOSE" != *"SHADOWED=shell (environment (overrides "*"test.env))"* ]] \
+    || [[ "$VERBOSE" != *"FLAGGED=*** (--env)"* ]] || [[ "$VERBOSE" != *"API_TOKEN=*** ("*"test.env)"* ]] || [[ "$VERBOSE" != *"[hunter2]"* ]]; then
+    echo "❌ (Verbose output: $VERBOSE)"
+    exit 1
+fi
+if [[ "$DUMP" != *'"API_TOKEN": "***"'* ]] || [[ "$DUMP" != *'"SHADOWED": "shell"'* ]] || [[ "$DUMP" == *hunter2* ]] \
+    || [[ "$DUMP" != *'"FLAGGED": "--env"'* ]]; then
+    echo "❌ (Dump: $DUMP)"
+    exit 1
+fi
+# the sudo launcher in the dump names the variables but never shows a value
+if [[ "$SUDO_DUMP" == *hunter2* ]] || [[ "$SUDO_DUMP" != *'"--preserve-env='*API_TOKEN* ]]; then
+    echo "❌ (run_as dump leaked a secret: $SUDO_DUMP)"
+    exit 1
+fi
+echo "✅"
+
+# login_shell sources the profile first (skipped when sh has no -l)
+echo -n "  Testing 'login shell'..."
+if HOME=/nonexistent sh -l -c true 2> /dev/null; then
+    LOGIN_HOME=$(mktemp -d)
+    echo 'export CAWA_LOGIN_PROBE=from_profile' > "$LOGIN_HOME/.profile"
+    echo '{ "aliases": { "login": { "run": "echo probe=$CAWA_LOGIN_PROBE", "login_shell": true }, "plain": "echo probe=$CAWA_LOGIN_PROBE" } }' > "$CLI_JSON"
+    if [[ "$(HOME=$LOGIN_HOME $CS login)" != *"probe=from_profile"* ]] || [[ "$(HOME=$LOGIN_HOME $CS plain)" == *"from_profile"* ]]; then
+        rm -rf "$LOGIN_HOME"
+        echo "❌ (login_shell didn't source the profile, or plain aliases did)"
+        exit 1
+    fi
+    rm -rf "$LOGIN_HOME"
+    echo "✅"
+else
+    echo "⏭️  (sh doesn't support -l)"
+fi
+
+# nice lowers the priority the command runs at (unix only)
+echo -n "  Testing 'nice'..."
+if command -v nice > /dev/null; then
+    base=$(nice)
+    want=$(( base + 7 > 19 ? 19 : base + 7 ))
+    echo '{ "aliases": { "niced": { "run": "nice", "nice": 7 } } }' > "$CLI_JSON"
+    OUTPUT=$($CS niced 2> /dev/null | tail -n 1)
+    if [[ "$OUTPUT" != "$want" ]]; then
+        echo "❌ (Expected niceness $want, got $OUTPUT)"
+        exit 1
+    fi
+    echo '{ "aliases": { "bad_nice": { "run": "true", "nice": 42 } } }' > "$CLI_JSON"
+    set +e
+    $CS bad_nice > /dev/null 2>&1
+    code=$?
+    set -e
+    if [[ $code -ne 2 ]]; then
+        echo "❌ (Out-of-range nice should be a config error, got $code)"
+        exit 1
+    fi
+    echo "✅"
+else
+    echo "⏭️  (no nice command)"
+fi
+
+# run_as goes through sudo; only actually run when sudo works without a password
+echo -n "  Testing 'run as'..."
+echo '{ "aliases": { "as_root": { "run": "id -u", "run_as": "root" }, "as_nobody": { "run": "id -u", "run_as": "nobody" } } }' > "$CLI_JSON"
+DUMP=$($CS --dump-resolved as_nobody)
+if [[ "$(echo "$DUMP" | tr -d ' \n')" != *'"shell":["sudo","-u","nobody",'* ]]; then
+    echo "❌ (run_as should go through sudo -u: $DUMP)"
+    exit 1
+fi
+# extra variables go over by name, their values stay off sudo's command line
+DUMP=$($CS --env API_TOKEN=hunter2 --dump-resolved as_nobody)
+if [[ "$DUMP" != *'"--preserve-env=API_TOKEN"'* ]] || [[ "$DUMP" == *hunter2* ]]; then
+    echo "❌ (run_as should pass variables by name only: $DUMP)"
+    exit 1
+fi
+if command -v sudo > /dev/null && sudo -n true 2> /dev/null; then
+    OUTPUT=$($CS as_root 2>&1)
+    if [[ "$OUTPUT" != *$'\n0'* ]]; then
+        echo "❌ (Expected to run as uid 0: $OUTPUT)"
+        exit 1
+    fi
+    echo "✅"
+else
+    echo "⏭️  (no passwordless sudo)"
+fi
+
+# --stagger starts parallel commands one after another, in config order
+echo -n "  Testing 'stagger'..."
+echo '{ "aliases": { "staggered": { "parallel": ["echo 1 >> stagger_order.txt", "echo 2 >> stagger_order.txt", "echo 3 >> stagger_order.txt"] } } }' > "$CLI_JSON"
+rm -f stagger_order.txt
+$CS --stagger 150 staggered > /dev/null
+if [[ "$(tr -d '\n' < stagger_order.txt)" != "123" ]]; then
+    echo "❌ (Started out of order: $(tr -d '\n' < stagger_order.txt))"
+    exit 1
+fi
+rm -f stagger_order.txt
+echo "✅"
+
+# --group prints each parallel command's output as one block, in config order
+echo -n "  Testing 'group'..."
+rm -f "$CLI_JSON"
+$CS add -p grp "sleep 0.5; echo slow_1; echo slow_2" "echo fast_1" > /dev/null
+OUTPUT=$($CS --group grp | grep -E '^(slow|fast)_' | tr '\n' ' ')
+if [[ "$OUTPUT" != "slow_1 slow_2 fast_1 " ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+# large output still comes out whole and in order, and the temp files go away
+rm -f "$CLI_JSON"
+$CS add -p grp_big "sleep 0.3; seq 1 100000" "echo after_big; exit 3" > /dev/null
+mkdir -p group_tmp
+set +e
+OUTPUT=$(TMPDIR=$PWD/group_tmp $CS --group grp_big 2> /dev/null)
+set -e
+if [[ $(grep -c '^[0-9]*$' <<< "$OUTPUT") -ne 100000 ]] || [[ "$(grep -E '^(100000|after_big)$' <<< "$OUTPUT" | tr '\n' ' ')" != "100000 after_big " ]]; then
+    echo "❌ (Large grouped output was cut short or out of order)"
+    exit 1
+fi
+if ! rmdir group_tmp; then
+    echo "❌ (--group left temp files behind)"
+    exit 1
+fi
+# temp names carry a random suffix rather than the pid, so they can't be planted ahead of time
+rm -f "$CLI_JSON"
+$CS add -p grp_names "ls \"\$TMPDIR\"" "true" > /dev/null
+mkdir -p group_tmp
+NAMES=$(TMPDIR=$PWD/group_tmp $CS --group grp_names 2> /dev/null | grep '^cawa_group_' | sort | tr '\n' ' ')
+rmdir group_tmp
+if [[ ! "$NAMES" =~ ^(cawa_group_[0-9a-f]{16}\.(out|err)\ ){4}$ ]]; then
+    echo "❌ (Unexpected --group temp names: $NAMES)"
+    exit 1
+fi
+echo "✅"
+
+# require_files / condition skip the run (exit 0) unless --force
+echo -n "  Testing 'conditions'..."
+echo '{ "aliases": { "needs_file": { "run": "echo ran_file", "require_files": ["no_such_file"] }, "needs_cond": { "run": "echo ran_cond", "condition": "false" } } }' > "$CLI_JSON"
+if [[ "$($CS needs_file 2>&1)" == *"ran_file"* ]] || [[ "$($CS needs_cond 2>&1)" == *"ran_cond"* ]]; then
+    echo "❌ (Alias ran despite an unmet precondition)"
+    exit 1
+fi
+if ! $CS needs_cond > /dev/null 2>&1 || [[ "$($CS --force needs_file)" != *"ran_file"* ]]; then
+    echo "❌ (Skip should exit 0 and --force should run)"
+    exit 1
+fi
+echo "✅"
+
+# requires refuses to start when a program is missing
+echo -n "  Testing 'requires'..."
+echo '{ "aliases": { "needs_bin": { "run": "echo ran_bin", "requires": ["sh", "cawa-missing-binary"] } } }' > "$CLI_JSON"
+set +e
+out=$($CS needs_bin 2>&1)
+code=$?
+set -e
+if [[ $code -ne 4 ]] || [[ "$out" == *"ran_bin"* ]] || [[ "$out" != *"cawa-missing-binary"* ]]; then
+    echo "❌ (Missing required program should stop the run with exit 4)"
+    exit 1
+fi
+echo "✅"
+
+# alias_of gives an alias another name; loops are refused
+echo -n "  Testing 'alias_of'..."
+rm -f "$CLI_JSON"
+$CS add long_name "echo via_pointer" > /dev/null
+$CS add --alias-of long_name ln > /dev/null
+if [[ "$($CS ln)" != *"via_pointer"* ]]; then
+    echo "❌ (Pointer didn't run its target)"
+    exit 1
+fi
+if $CS add --alias-of self_loop self_loop > /dev/null 2>&1; then
+    echo "❌ (Self-referencing alias was accepted)"
+    exit 1
+fi
+echo "✅"
+
+# batch runs aliases in order and stops at the first failure
+echo -n "  Testing 'batch'..."
+echo '{ "aliases": { "one": "echo 1 >> batch_order.txt", "bad": "echo 2 >> batch_order.txt; exit 6", "three": "echo 3 >> batch_order.txt" } }' > "$CLI_JSON"
+rm -f batch_order.txt
+set +e
+$CS batch one bad three > /dev/null 2>&1
+code=$?
+set -e
+if [[ $code -ne 6 ]] || [[ "$(tr -d '\n' < batch_order.txt)" != "12" ]]; then
+    echo "❌ (Expected exit 6 after running one, bad; got $code and $(tr -d '\n' < batch_order.txt))"
+    exit 1
+fi
+rm -f batch_order.txt
+set +e
+$CS batch --parallel bad three > /dev/null 2>&1
+code=$?
+set -e
+if [[ $code -ne 6 ]] || ! grep -q 3 batch_order.txt; then
+    echo "❌ (--parallel should run everything and still fail)"
+    exit 1
+fi
+rm -f batch_order.txt
+echo "✅"
+
+# what actually reaches the shell: a "shell" that only writes down the command
+# it's given stands in for running it, so single, parallel, steps and batch runs
+# are checked string by string
+echo -n "  Testing 'dispatched commands'..."
+echo 'printf "%s\n" "$1" >> dispatched.txt' > record.sh
+echo '{ "shell": "sh ./record.sh", "aliases": { "greet": { "run": "echo hi {who}", "params": ["who"], "pipe_to": "tr a-z A-Z" }, "both": { "parallel": ["echo a", "echo b"] }, "ordered": { "steps": [{ "name": "first", "command": "echo 1" }, { "name": "second", "command": "echo 2" }] }, "one": "echo one", "two": "echo two", "off": { "run": "echo off", "enabled": false } } }' > "$CLI_JSON"
+rm -f dispatched.txt
+$CS greet --who me -x > /dev/null
+$CS both > /dev/null
+$CS ordered > /dev/null
+$CS batch two one > /dev/null
+if $CS bench --runs 1 off > /dev/null 2>&1; then
+    echo "❌ (Benchmarking a disabled alias should fail)"
+    exit 1
+fi
+EXPECTED=$'echo hi me -x | tr a-z A-Z\necho 1\necho 2\necho two\necho one'
+# the parallel pair can start in either order
+if [[ "$(grep -v '^echo [ab]$' dispatched.txt)" != "$EXPECTED" ]] || [[ "$(grep -c '^echo [ab]$' dispatched.txt)" -ne 2 ]]; then
+    echo "❌ (Dispatched: $(cat dispatched.txt))"
+    exit 1
+fi
+rm -f dispatched.txt record.sh
+echo "✅"
+
+# a shell that can't be started is reported once, even with debug logging on
+echo -n "  Testing 'missing shell'..."
+echo '{ "shell": "no-such-shell-xyz -c", "aliases": { "hi": "echo hi" } }' > "$CLI_JSON"
+set +e
+OUTPUT=$($CS --log-level debug hi 2>&1)
+code=$?
+set -e
+if [[ $code -ne 1 ]] || [[ $(grep -c "os error" <<< "$OUTPUT") -ne 1 ]] || [[ "$OUTPUT" != *"Couldn't start"* ]]; then
+    echo "❌ (Expected one 'Couldn't start' and exit 1, got $code: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# suppress_stdout / suppress_stderr on the alias, --quiet-out / --quiet-err for one run
+echo -n "  Testing 'quiet'..."
+# the command prints out_y and err_y, which its banner line doesn't contain
+NOISY='echo out_x | tr x y; echo err_x | tr x y >&2'
+rm -f "$CLI_JSON"
+$CS add --quiet-err noisy "$NOISY" > /dev/null
+$CS add both "$NOISY" > /dev/null
+STORED=$(grep -c '"suppress_stderr": true' "$CLI_JSON")
+ALIAS_ERR=$($CS noisy 2>&1 > /dev/null)
+ALIAS_OUT=$($CS noisy 2> /dev/null)
+FLAG_OUT=$($CS --quiet-out both 2> /dev/null)
+FLAG_ERR=$($CS --quiet-err both 2>&1 > /dev/null)
+if [[ $STORED -ne 1 ]] || [[ "$ALIAS_ERR" == *err_y* ]] || [[ "$ALIAS_OUT" != *out_y* ]] \
+    || [[ "$FLAG_OUT" == *out_y* ]] || [[ "$FLAG_ERR" == *err_y* ]]; then
+    echo "❌ (stored $STORED / alias: '$ALIAS_OUT' '$ALIAS_ERR' / flags: '$FLAG_OUT' '$FLAG_ERR')"
+    exit 1
+fi
+echo "✅"
+
+# append_args off: runtime args are an error, or dropped with --ignore-args
+echo -n "  Testing 'append args'..."
+rm -f "$CLI_JSON"
+$CS add --no-append-args fixed "echo fixed_ran" > /dev/null
+set +e
+REFUSED=$($CS fixed extra 2>&1)
+code=$?
+set -e
+IGNORED=$($CS --ignore-args fixed extra 2> /dev/null)
+if ! grep -q '"append_args": false' "$CLI_JSON" || [[ $code -ne 4 ]] || [[ "$REFUSED" != *"doesn't take arguments (got: extra)"* ]] \
+    || [[ "$REFUSED" == *fixed_ran* ]] || [[ "$IGNORED" != *"Executing: echo fixed_ran"* ]] || [[ "$IGNORED" == *extra* ]]; then
+    echo "❌ (exit $code: $REFUSED / with --ignore-args: $IGNORED)"
+    exit 1
+fi
+echo "✅"
+
+# bench times --runs runs after --warmup untimed ones, and stops at a failure
+echo -n "  Testing 'bench'..."
+echo '{ "aliases": { "tick": "echo t >> bench_ticks.txt", "bad": "exit 5" } }' > "$CLI_JSON"
+rm -f bench_ticks.txt
+OUTPUT=$($CS bench --runs 3 --warmup 1 tick)
+set +e
+$CS bench bad > /dev/null 2>&1
+bad_code=$?
+$CS bench --runs 0 tick > /dev/null 2>&1
+zero_code=$?
+set -e
+if [[ $(wc -l < bench_ticks.txt) -ne 4 ]] || [[ "$OUTPUT" != *"(3 runs, 1 warmup)"* ]] || [[ "$OUTPUT" != *"median"* ]] \
+    || [[ $bad_code -ne 5 ]] || [[ $zero_code -ne 4 ]]; then
+    echo "❌ ($(wc -l < bench_ticks.txt) runs, failing exit $bad_code, --runs 0 exit $zero_code: $OUTPUT)"
+    rm -f bench_ticks.txt
+    exit 1
+fi
+rm -f bench_ticks.txt
+echo "✅"
+
+# batch names can be globs: matches run sorted, hidden ones are left out
+echo -n "  Testing 'batch globs'..."
+echo '{ "aliases": { "test-b": "echo b >> batch_order.txt", "test-a": "echo a >> batch_order.txt", "test-x": { "run": "echo x >> batch_order.txt", "hidden": true }, "lint": "echo l >> batch_order.txt" } }' > "$CLI_JSON"
+rm -f batch_order.txt
+$CS batch 'test-*' lint > /dev/null 2>&1
+if [[ "$(tr -d '\n' < batch_order.txt)" != "abl" ]]; then
+    echo "❌ (Expected test-a, test-b, lint; got $(tr -d '\n' < batch_order.txt))"
+    exit 1
+fi
+rm -f batch_order.txt
+set +e
+$CS batch 'nope-*' > /dev/null 2>&1
+code=$?
+set -e
+if [[ $code -ne 4 ]] || [[ -f batch_order.txt ]]; then
+    echo "❌ (A pattern matching nothing should exit 4 without running anything, got $code)"
+    exit 1
+fi
+# ? is any one character, [a-z] one of a range and [!x] anything but x
+echo '{ "aliases": { "t1": "echo t1 >> batch_order.txt", "t22": "echo t22 >> batch_order.txt", "ta": "echo ta >> batch_order.txt", "tb": "echo tb >> batch_order.txt", "tx": "echo tx >> batch_order.txt" } }' > "$CLI_JSON"
+for case in 't?:t1 ta tb tx' 't[a-b]:ta tb' 't[!x]:t1 ta tb' 't[!a-z]*:t1 t22'; do
+    rm -f batch_order.txt
+    $CS batch "${case%%:*}" > /dev/null 2>&1
+    if [[ "$(tr '\n' ' ' < batch_order.txt)" != "${case#*:} " ]]; then
+        echo "❌ ('${case%%:*}' should run ${case#*:}, ran $(tr '\n' ' ' < batch_order.txt))"
+        exit 1
+    fi
+done
+rm -f batch_order.txt
+echo "✅"
+
+# --no-emoji / "emoji": false keep the octopus out of cs's own output
+echo -n "  Testing 'no emoji'..."
+echo '{ "enable_timing": true, "aliases": { "plain": "echo plain" } }' > "$CLI_JSON"
+if [[ "$($CS --no-emoji plain 2>&1; $CS --no-emoji list 2>&1)" == *"🐙"* ]]; then
+    echo "❌ (--no-emoji still printed the emoji)"
+    exit 1
+fi
+echo '{ "emoji": false, "enable_timing": true, "aliases": { "plain": "echo plain" } }' > "$CLI_JSON"
+if [[ "$($CS plain 2>&1; $CS nope 2>&1)" == *"🐙"* ]]; then
+    echo "❌ (\"emoji\": false still printed the emoji)"
+    exit 1
+fi
+echo "✅"
+
+# each parallel command is a full sh -c chain, and split-up operators get a warning
+echo -n "  Testing 'parallel chains'..."
 rm -f "$CLI_JSON"
+mkdir -p chain_dir
+WARN=$($CS add -p chained "cd chain_dir && pwd" "&&" "pwd" 2>&1 > /dev/null)
+set +e
+OUTPUT=$($CS chained 2> /dev/null)
+set -e
+rmdir chain_dir
+if [[ "$WARN" != *"'&&' is a separate parallel command"* ]] || [[ "$OUTPUT" != *"/chain_dir"* ]]; then
+    echo "❌ (Warning: $WARN / Output: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# with pty the command sees a terminal even though --group captures its output
+echo -n "  Testing 'pty'..."
+echo '{ "aliases": { "colors": { "parallel": ["[ -t 1 ] && printf \"\\033[31mred\\033[0m\\n\" || echo plain", "true"], "pty": true }, "nocolors": { "parallel": ["[ -t 1 ] && echo tty || echo plain", "true"] } } }' > "$CLI_JSON"
+OUTPUT=$($CS --group colors 2> /dev/null)
+PLAIN=$($CS --group nocolors 2> /dev/null)
+if [[ "$OUTPUT" != *$'\e[31mred'* ]] || [[ "$PLAIN" != *"plain"* ]]; then
+    echo "❌ (Colors lost under pty: $(cat -v <<< "$OUTPUT") / $PLAIN)"
+    exit 1
+fi
+echo "✅"
+
+# --detach returns at once and logs to .cawa_logs; cs ps lists it, cs stop ends it
+echo -n "  Testing 'detach'..."
+rm -rf .cawa_logs .cawa_detached.json
+echo '{ "aliases": { "server": "echo serving; sleep 30" } }' > "$CLI_JSON"
+START=$(date +%s)
+$CS --detach server > /dev/null
+if [[ $(( $(date +%s) - START )) -gt 5 ]]; then
+    echo "❌ (--detach waited for the command)"
+    exit 1
+fi
+sleep 0.5
+PS=$($CS ps)
+STOPPED=$($CS stop server)
+set +e
+$CS stop server > /dev/null 2>&1
+again=$?
+set -e
+if [[ "$(cat .cawa_logs/server.log)" != "serving" ]] || [[ "$PS" != *"server pid"* ]] || [[ "$STOPPED" != *"Stopped 'server'"* ]] \
+    || [[ $again -ne 3 ]] || [[ "$($CS ps)" != *"No detached aliases running."* ]]; then
+    echo "❌ (ps: $PS / stop: $STOPPED / stopping again: $again)"
+    exit 1
+fi
+rm -rf .cawa_logs .cawa_detached.json
+echo "✅"
+
+# cs last shows the end of the latest detached log, or all of it in $PAGER
+echo -n "  Testing 'last'..."
+rm -rf .cawa_logs .cawa_detached.json
+echo '{ "aliases": { "counting": "seq 1 100", "never": "true" } }' > "$CLI_JSON"
+$CS --detach counting > /dev/null
+for _ in $(seq 50); do [[ $(wc -l < .cawa_logs/counting.log 2> /dev/null || echo 0) -ge 100 ]] && break; sleep 0.1; done
+TAIL=$($CS last -n 3 | tail -n 3 | tr '\n' ' ')
+PAGED=$(PAGER=cat $CS last counting --pager | wc -l)
+set +e
+NONE=$($CS last never 2>&1)
+none_code=$?
+set -e
+if [[ "$TAIL" != "98 99 100 " ]] || [[ $PAGED -ne 100 ]] || [[ $none_code -ne 1 ]] || [[ "$NONE" != *"No log for 'never'"* ]]; then
+    echo "❌ (tail: $TAIL / paged: $PAGED lines / no log: $none_code $NONE)"
+    exit 1
+fi
+rm -rf .cawa_logs .cawa_detached.json
+echo "✅"
+
+# cs ps drops entries whose process has exited, and cs stop takes the whole
+# process group down, background jobs included
+echo -n "  Testing 'ps liveness'..."
+rm -rf .cawa_logs .cawa_detached.json bg_job.pid
+echo '{ "aliases": { "quick": "true", "forks": "sleep 30 & echo $! > bg_job.pid; wait" } }' > "$CLI_JSON"
+$CS --detach quick > /dev/null
+$CS --detach forks > /dev/null
+for _ in $(seq 50); do [[ -s bg_job.pid ]] && break; sleep 0.1; done
+sleep 0.3
+PS=$($CS ps)
+$CS stop forks > /dev/null
+for _ in $(seq 30); do kill -0 "$(cat bg_job.pid)" 2> /dev/null || break; sleep 0.1; done
+if [[ "$PS" != *"pruned 1 exited process(es)"* ]] || [[ "$PS" == *"quick pid"* ]] || [[ "$PS" != *"forks pid"* ]] \
+    || kill -0 "$(cat bg_job.pid)" 2> /dev/null; then
+    echo "❌ (ps: $PS / background job still alive: $(cat bg_job.pid))"
+    exit 1
+fi
+rm -rf .cawa_logs .cawa_detached.json bg_job.pid
+echo "✅"
+
+# detached parallel commands each get their own log, so neither is interleaved
+echo -n "  Testing 'detached logs'..."
+rm -rf .cawa_logs .cawa_detached.json
+echo '{ "aliases": { "bg_pair": { "parallel": ["seq 1 5000", "seq 1 5000 | sed s/^/b/"], "detached": true } } }' > "$CLI_JSON"
+$CS bg_pair > /dev/null
+for _ in 1 2 3 4 5 6 7 8 9 10; do
+    [[ -f .cawa_logs/bg_pair.2.log ]] && [[ $(wc -l < .cawa_logs/bg_pair.1.log) -eq 5000 ]] \
+        && [[ $(wc -l < .cawa_logs/bg_pair.2.log) -eq 5000 ]] && break
+    sleep 0.2
+done
+if [[ $(grep -c '^[0-9]*$' .cawa_logs/bg_pair.1.log) -ne 5000 ]] || [[ $(grep -c '^b[0-9]*$' .cawa_logs/bg_pair.2.log) -ne 5000 ]] \
+    || [[ -e .cawa_logs/bg_pair.log ]]; then
+    echo "❌ (Per-command logs missing or mixed: $(ls .cawa_logs))"
+    exit 1
+fi
+rm -rf .cawa_logs .cawa_detached.json
+echo "✅"
+
+# info counts what's in the config
+echo -n "  Testing 'info'..."
+echo '{ "aliases": { "one": "true", "two": { "run": "true", "description": "d" }, "par": ["true", "true"], "p": { "alias_of": "par" } } }' > "$CLI_JSON"
+OUTPUT=$(HOME=/nonexistent $CS info)
+if [[ "$OUTPUT" != *"2 single, 1 parallel, 0 steps, 1 other names"* ]] || [[ "$OUTPUT" != *"1 of 4"* ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+# aliases pulled in with "include" are counted apart from global ones
+echo '{ "aliases": { "extra_a": "true", "extra_b": "true" } }' > info_included.json
+echo '{ "include": ["info_included.json"], "aliases": { "one": "true" } }' > "$CLI_JSON"
+OUTPUT=$(HOME=/nonexistent $CS info)
+rm -f info_included.json
+if [[ "$OUTPUT" != *"3 (1 local, 0 global, 2 included)"* ]]; then
+    echo "❌ (Included aliases miscounted: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# stdin_file feeds a file to the command, and a missing one is refused
+echo -n "  Testing 'stdin file'..."
+printf 'cherry\napple\nbanana\n' > stdin_input.txt
+echo '{ "aliases": { "sorted": { "run": "sort", "stdin_file": "stdin_input.txt" } } }' > "$CLI_JSON"
+OUTPUT=$($CS sorted 2> /dev/null | tr '\n' ' ')
+rm -f stdin_input.txt
+set +e
+$CS sorted > /dev/null 2>&1
+code=$?
+set -e
+if [[ "$OUTPUT" != *"apple banana cherry "* ]] || [[ $code -ne 4 ]]; then
+    echo "❌ (Output: $OUTPUT, missing file exit code: $code)"
+    exit 1
+fi
+echo "✅"
+
+# ~ and $VAR / ${VAR} expand in paths; unset ones and ~user are left as written
+echo -n "  Testing 'path expansion'..."
+PATH_HOME=$(mktemp -d)
+mkdir "$PATH_HOME/sub"
+printf 'from_home\n' > "$PATH_HOME/sub/in.txt"
+echo '{ "aliases": { "tilde": "echo tilde_config" } }' > "$PATH_HOME/sub/cfg.json"
+echo '{ "aliases": { "braced": { "run": "cat", "stdin_file": "${PATH_SUB}/in.txt" }, "unset": { "run": "cat", "stdin_file": "$CAWA_NOPE/in.txt" }, "other_user": { "run": "cat", "stdin_file": "~cawa_nosuchuser/in.txt" } } }' > "$CLI_JSON"
+TILDE=$(HOME=$PATH_HOME $CS --config '~/sub/cfg.json' tilde 2>&1)
+BRACED=$(PATH_SUB=$PATH_HOME/sub $CS braced 2>&1)
+set +e
+UNSET=$(env -u CAWA_NOPE $CS unset 2>&1)
+unset_code=$?
+OTHER=$($CS other_user 2>&1)
+other_code=$?
+set -e
+rm -rf "$PATH_HOME"
+if [[ "$TILDE" != *"tilde_config"* ]] || [[ "$BRACED" != *"from_home"* ]]; then
+    echo "❌ (~ or \${VAR} didn't expand: $TILDE / $BRACED)"
+    exit 1
+fi
+if [[ $unset_code -ne 4 ]] || [[ "$UNSET" != *'$CAWA_NOPE/in.txt'* ]] || [[ $other_code -ne 4 ]] || [[ "$OTHER" != *"~cawa_nosuchuser/in.txt"* ]]; then
+    echo "❌ (Unset variables and ~user should be a clear error: $unset_code $UNSET / $other_code $OTHER)"
+    exit 1
+fi
+echo "✅"
+
+# color: off through a pipe or with NO_COLOR in auto, "always" beats both
+echo -n "  Testing 'color'..."
+ESC=$'\e['
+echo '{ "aliases": { "plain": "true" } }' > "$CLI_JSON"
+AUTO=$($CS plain)
+echo '{ "color": "always", "aliases": { "plain": "true" } }' > "$CLI_JSON"
+ALWAYS=$(NO_COLOR=1 $CS plain)
+QUIET=$(NO_COLOR=1 $CS --quiet-out plain)
+echo '{ "color": "never", "aliases": { "plain": "true" } }' > "$CLI_JSON"
+NEVER=$($CS plain)
+if [[ "$AUTO" == *"$ESC"* ]] || [[ "$ALWAYS" != *"$ESC"* ]] || [[ "$QUIET" != *"$ESC"* ]] || [[ "$NEVER" == *"$ESC"* ]]; then
+    echo "❌ (auto: $AUTO / always: $ALWAYS / quiet: $QUIET / never: $NEVER)"
+    exit 1
+fi
+echo "✅"
+
+# a custom config name is used for both reading and writing
+echo -n "  Testing 'config name'..."
+rm -f custom_name.json
+CAWA_CONFIG_NAME=custom_name.json $CS add named_cfg "echo from_custom" > /dev/null
+OUTPUT=$($CS --config-name custom_name.json named_cfg 2> /dev/null)
+if [[ ! -f custom_name.json ]] || grep -q named_cfg "$CLI_JSON" 2> /dev/null || [[ "$OUTPUT" != *"from_custom"* ]]; then
+    rm -f custom_name.json custom_name.lock
+    echo "❌ (Custom config name wasn't used: $OUTPUT)"
+    exit 1
+fi
+rm -f custom_name.json custom_name.lock
+echo "✅"
+
+# disabled aliases are refused with exit 4 unless asked for, other names for them included
+echo -n "  Testing 'disable'..."
+rm -f "$CLI_JSON"
+$CS add flaky "echo flaky_ran" > /dev/null
+$CS add --alias-of flaky fl > /dev/null
+$CS disable flaky > /dev/null
+set +e
+SKIPPED=$($CS fl 2>&1)
+code=$?
+set -e
+FORCED=$($CS --include-disabled flaky 2> /dev/null)
+$CS enable flaky > /dev/null
+ENABLED=$($CS flaky 2> /dev/null)
+if [[ $code -ne 4 ]] || [[ "$SKIPPED" != *"disabled"* ]] || [[ "$SKIPPED" == *"flaky_ran"* ]] \
+    || [[ "$FORCED" != *"flaky_ran"* ]] || [[ "$ENABLED" != *"flaky_ran"* ]]; then
+    echo "❌ (Skipped: $SKIPPED / Forced: $FORCED / Enabled: $ENABLED)"
+    exit 1
+fi
+echo "✅"
+
+# a bare cs runs the default alias; a dangling default falls back to help
+echo -n "  Testing 'default alias'..."
+rm -f "$CLI_JSON"
+$CS add fallback "echo default_ran" > /dev/null
+$CS set-default fallback > /dev/null
+BARE=$($CS 2> /dev/null)
+$CS remove fallback > /dev/null
+DANGLING=$($CS 2>&1)
+if [[ "$BARE" != *"default_ran"* ]] || [[ "$DANGLING" != *"default_alias 'fallback' doesn't exist"* ]] \
+    || [[ "$DANGLING" != *"Usage"* ]]; then
+    echo "❌ (Bare: $BARE / Dangling: $DANGLING)"
+    exit 1
+fi
+echo "✅"
+
+# hidden aliases run by name but only list with --all
+echo -n "  Testing 'hidden'..."
+rm -f "$CLI_JSON"
+$CS add --hidden helper "echo helper_ran" > /dev/null
+$CS add shown "true" > /dev/null
+LISTED=$($CS list)
+ALL=$($CS list --all)
+RAN=$($CS helper 2> /dev/null)
+if [[ "$LISTED" == *"helper"* ]] || [[ "$LISTED" != *"shown"* ]] || [[ "$ALL" != *"helper"* ]] \
+    || [[ "$RAN" != *"helper_ran"* ]]; then
+    echo "❌ (List: $LISTED / All: $ALL / Ran: $RAN)"
+    exit 1
+fi
+echo "✅"
+
+# re-adding an identical alias doesn't touch the file
+echo -n "  Testing 'no-op add'..."
+rm -f "$CLI_JSON"
+$CS add same "echo same" > /dev/null
+touch -r "$CLI_JSON" noop_marker
+sleep 0.1
+OUTPUT=$($CS add same "echo same")
+if [[ "$CLI_JSON" -nt noop_marker ]] || [[ "$OUTPUT" != *"no changes"* ]]; then
+    rm -f noop_marker
+    echo "❌ (Identical add rewrote the config: $OUTPUT)"
+    exit 1
+fi
+rm -f noop_marker
+echo "✅"
+
+# aliases are written sorted, so saving the same config twice gives the same bytes
+echo -n "  Testing 'stable save'..."
+echo '{ "aliases": { "zeta": "echo z", "alpha": "echo a", "mid": "echo m" } }' > "$CLI_JSON"
+$CS set-var v 1 > /dev/null
+cp "$CLI_JSON" stable_first.json
+$CS set-var v 2 > /dev/null
+$CS set-var v 1 > /dev/null
+order=$(grep -oE '"(alpha|mid|zeta)"' "$CLI_JSON" | tr -d '"' | tr '\n' ' ')
+if ! cmp -s stable_first.json "$CLI_JSON" || [[ "$order" != "alpha mid zeta " ]]; then
+    rm -f stable_first.json
+    echo "❌ (Saved config isn't stable/sorted: $order)"
+    exit 1
+fi
+rm -f stable_first.json
+echo "✅"
+
+# edit bumps updated_at and leaves created_at alone
+echo -n "  Testing 'timestamps'..."
+rm -f "$CLI_JSON"
+$CS add stamped "echo before" > /dev/null
+created=$(grep -o '"created_at": "[^"]*"' "$CLI_JSON")
+updated=$(grep -o '"updated_at": "[^"]*"' "$CLI_JSON")
+sleep 1
+printf '#!/bin/sh\necho "echo after" > "$1"\n' > fake_editor.sh
+chmod +x fake_editor.sh
+EDITOR=./fake_editor.sh $CS edit stamped > /dev/null
+rm -f fake_editor.sh
+if [[ -z "$created" ]] || [[ "$(grep -o '"created_at": "[^"]*"' "$CLI_JSON")" != "$created" ]] \
+    || [[ "$(grep -o '"updated_at": "[^"]*"' "$CLI_JSON")" == "$updated" ]] || ! grep -q "echo after" "$CLI_JSON"; then
+    echo "❌ (Edit should only bump updated_at: $(cat "$CLI_JSON"))"
+    exit 1
+fi
+echo "✅"
+
+# config edit only saves a config that parses
+echo -n "  Testing 'config edit'..."
+echo '{ "aliases": { "keep": "echo kept" } }' > "$CLI_JSON"
+printf '#!/bin/sh\necho "{ not json" > "$1"\n' > fake_editor.sh
+chmod +x fake_editor.sh
+set +e
+EDITOR=./fake_editor.sh $CS config edit > /dev/null 2> config_edit.err
+code=$?
+set -e
+rm -f "$(grep -o '/[^ ]*cawa_config_[0-9]*\.json' config_edit.err)" config_edit.err
+if [[ $code -ne 2 ]] || ! grep -q '"keep"' "$CLI_JSON"; then
+    echo "❌ (Broken edit should be refused, got $code: $(cat "$CLI_JSON"))"
+    exit 1
+fi
+printf '#!/bin/sh\necho "{ \\"aliases\\": { \\"fresh\\": \\"echo fresh\\" } }" > "$1"\n' > fake_editor.sh
+EDITOR=./fake_editor.sh $CS config edit > /dev/null
+rm -f fake_editor.sh
+if [[ "$($CS fresh 2> /dev/null)" != *"fresh"* ]]; then
+    echo "❌ (Valid edit wasn't saved: $(cat "$CLI_JSON"))"
+    exit 1
+fi
+echo "✅"
+
+# included files are merged under the base one; loops are refused
+echo -n "  Testing 'includes'..."
+mkdir -p inc_test
+echo '{ "include": ["inc_test/more.json"], "aliases": { "shared": "echo from_base" } }' > "$CLI_JSON"
+echo '{ "aliases": { "shared": "echo from_include", "extra": "echo extra_ran" } }' > inc_test/more.json
+if [[ "$($CS shared 2> /dev/null)" != *"from_base"* ]] || [[ "$($CS extra 2> /dev/null)" != *"extra_ran"* ]]; then
+    echo "❌ (Included aliases weren't merged under the base ones)"
+    exit 1
+fi
+$CS add added "true" > /dev/null
+if grep -q '"extra"' "$CLI_JSON"; then
+    echo "❌ (Saving copied included aliases into the base file)"
+    exit 1
+fi
+echo '{ "include": ["../'"$CLI_JSON"'"] }' > inc_test/more.json
+set +e
+OUTPUT=$($CS shared 2>&1)
+code=$?
+set -e
+rm -rf inc_test
+if [[ $code -ne 2 ]] || [[ "$OUTPUT" != *"Include loop"* ]]; then
+    echo "❌ (Circular include should fail with exit 2, got $code: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# webhook_url gets a JSON summary of each run; a dead one only warns
+echo -n "  Testing 'webhook'..."
+if command -v python3 > /dev/null; then
+    rm -f hook_port hook_body
+    python3 -c '
+import http.server
+class H(http.server.BaseHTTPRequestHandler):
+    def do_POST(self):
+        open("hook_body", "wb").write(self.rfile.read(int(self.headers["Content-Length"])))
+        self.send_response(204)
+        self.end_headers()
+    def log_message(self, *a):
+        pass
+s = http.server.HTTPServer(("127.0.0.1", 0), H)
+open("hook_port", "w").write(str(s.server_port))
+s.handle_request()
+' &
+    hook_pid=$!
+    for _ in $(seq 50); do [[ -s hook_port ]] && break; sleep 0.1; done
+    echo '{ "webhook_url": "http://127.0.0.1:'"$(cat hook_port)"'/runs", "aliases": { "hooked": "exit 3" } }' > "$CLI_JSON"
+    set +e
+    $CS hooked > /dev/null 2>&1
+    code=$?
+    set -e
+    wait $hook_pid
+    BODY=$(cat hook_body)
+    rm -f hook_port hook_body
+    if [[ $code -ne 3 ]] || [[ "$BODY" != *'"alias":"hooked"'* ]] || [[ "$BODY" != *'"success":false'* ]] || [[ "$BODY" != *'"exit_code":3'* ]]; then
+        echo "❌ (Webhook got: $BODY)"
+        exit 1
+    fi
+    # nothing listens here, the run still succeeds (set -e) and only warns
+    echo '{ "webhook_url": "http://127.0.0.1:9/runs", "aliases": { "hooked": "true" } }' > "$CLI_JSON"
+    OUTPUT=$($CS hooked 2>&1 > /dev/null)
+    if [[ "$OUTPUT" != *"webhook"* ]]; then
+        echo "❌ (Unreachable webhook should warn: $OUTPUT)"
+        exit 1
+    fi
+    # https is a real option, it gets as far as trying to connect
+    echo '{ "webhook_url": "https://127.0.0.1:9/runs", "aliases": { "hooked": "true" } }' > "$CLI_JSON"
+    OUTPUT=$($CS hooked 2>&1 > /dev/null)
+    if [[ "$OUTPUT" != *"couldn't reach https://127.0.0.1:9/runs"* ]]; then
+        echo "❌ (https webhook should be attempted: $OUTPUT)"
+        exit 1
+    fi
+    echo "✅"
+else
+    echo "⏭️  (no python3 for the mock server)"
+fi
+
+# exit 75 means "nothing to do": reported as skipped and not a batch failure
+echo -n "  Testing 'skip exit code'..."
+echo '{ "aliases": { "nothing": "exit 75", "after": "echo after_ran" } }' > "$CLI_JSON"
+set +e
+SKIP_OUT=$($CS nothing 2>&1)
+SKIP_CODE=$?
+BATCH_OUT=$($CS batch nothing after 2>&1)
+BATCH_CODE=$?
+set -e
+if [[ $SKIP_CODE -ne 75 ]] || [[ "$SKIP_OUT" != *"Skipped"* ]] || [[ $BATCH_CODE -ne 0 ]] \
+    || [[ "$BATCH_OUT" != *"after_ran"* ]] || [[ "$BATCH_OUT" != *"(skipped)"* ]]; then
+    echo "❌ (Skip: $SKIP_CODE $SKIP_OUT / Batch: $BATCH_CODE $BATCH_OUT)"
+    exit 1
+fi
+# a custom skip code becomes 75; the command's other codes, 75 among them, come through as they are
+echo '{ "skip_exit_code": 3, "aliases": { "custom": "exit 3", "own75": "exit 75", "other": "exit 5" } }' > "$CLI_JSON"
+set +e
+$CS custom > /dev/null 2>&1
+CUSTOM_CODE=$?
+$CS own75 > /dev/null 2>&1
+OWN_CODE=$?
+$CS other > /dev/null 2>&1
+OTHER_CODE=$?
+set -e
+if [[ $CUSTOM_CODE -ne 75 ]] || [[ $OWN_CODE -ne 75 ]] || [[ $OTHER_CODE -ne 5 ]]; then
+    echo "❌ (Custom skip code: $CUSTOM_CODE, own 75: $OWN_CODE, other: $OTHER_CODE)"
+    exit 1
+fi
+echo "✅"
+
+# an empty parallel list is refused when added and when run, never a silent success
+echo -n "  Testing 'empty parallel'..."
+rm -f "$CLI_JSON"
+set +e
+$CS add -p nothing "" > /dev/null 2>&1
+code=$?
+set -e
+if [[ $code -ne 4 ]] || [[ -f "$CLI_JSON" ]]; then
+    echo "❌ (Adding an empty parallel alias should fail with exit 4, got $code)"
+    exit 1
+fi
+# one blank command among real ones is refused too
+set +e
+OUTPUT=$($CS add -p half "echo a" " " 2>&1)
+code=$?
+set -e
+if [[ $code -ne 4 ]] || [[ "$OUTPUT" != *"command 2 is blank"* ]] || [[ -f "$CLI_JSON" ]]; then
+    echo "❌ (Adding a parallel alias with a blank command should fail with exit 4, got $code: $OUTPUT)"
+    exit 1
+fi
+echo '{ "aliases": { "nothing": [], "half": ["echo a", ""] } }' > "$CLI_JSON"
+set +e
+OUTPUT=$($CS nothing 2>&1)
+code=$?
+HALF=$($CS half 2>&1)
+HALF_CODE=$?
+set -e
+if [[ $code -ne 2 ]] || [[ "$OUTPUT" != *"no commands"* ]]; then
+    echo "❌ (Running an empty parallel alias should fail with exit 2, got $code: $OUTPUT)"
+    exit 1
+fi
+if [[ $HALF_CODE -ne 2 ]] || [[ "$HALF" != *"is blank"* ]]; then
+    echo "❌ (Running a parallel alias with a blank command should fail with exit 2, got $HALF_CODE: $HALF)"
+    exit 1
+fi
+echo "✅"
+
+# cs config validate reports every problem in the file without running anything
+echo -n "  Testing 'config validate'..."
+echo '{ "aliases": { "nothing": [], "half": ["echo a", ""], "docker_thing": { "run": "touch ran.txt", "requires": ["definitely-not-a-real-program-xyz"] }, "fine": "echo ok" } }' > "$CLI_JSON"
+rm -f ran.txt
+set +e
+OUTPUT=$($CS config validate 2>&1)
+code=$?
+set -e
+if [[ $code -ne 2 ]] || [[ "$OUTPUT" != *"'nothing'"* ]] || [[ "$OUTPUT" != *"'half'"* ]] \
+    || [[ "$OUTPUT" != *"definitely-not-a-real-program-xyz"* ]] || [[ "$OUTPUT" == *"'fine'"* ]] || [[ -f ran.txt ]]; then
+    echo "❌ (Expected exit 2 naming nothing, half and the missing program, got $code: $OUTPUT)"
+    exit 1
+fi
+echo '{ "aliases": { "fine": "echo ok" } }' > "$CLI_JSON"
+OUTPUT=$($CS config validate 2>&1)
+if [[ "$OUTPUT" != *"is valid"* ]]; then
+    echo "❌ (A clean config should validate: $OUTPUT)"
+    exit 1
+fi
+echo '{ "aliases": { "broken": ' > "$CLI_JSON"
+set +e
+$CS config validate > /dev/null 2>&1
+code=$?
+set -e
+if [[ $code -ne 2 ]]; then
+    echo "❌ (An unparsable config should fail validation with exit 2, got $code)"
+    exit 1
+fi
+echo "✅"
+
+# {name} parameters: substituted when given, refused when missing or unknown
+echo -n "  Testing 'named params'..."
+echo '{ "aliases": { "greet": { "run": "echo hello {who} from {place}", "params": ["who", "place"] } } }' > "$CLI_JSON"
+OUTPUT=$($CS greet --who "big world" --place=here)
+if [[ "$OUTPUT" != *"hello big world from here"* ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+expect_params_error() {
+    set +e
+    out=$($CS greet "$@" 2>&1)
+    code=$?
+    set -e
+    if [[ $code -ne 4 ]] || [[ "$out" == *"hello"* ]]; then
+        echo "❌ ('cs greet $*' should fail with exit 4 without running: $out)"
+        exit 1
+    fi
+}
+expect_params_error --who me                       # missing --place
+expect_params_error --who me --place x --extra y   # unknown parameter
+# braces nobody declared belong to the command
+$CS add fields "echo 'a b' | awk '{print}'" > /dev/null
+OUTPUT=$($CS fields 2>&1)
+if [[ "$OUTPUT" != *$'\na b'* ]] || [[ "$OUTPUT" == *"missing"* ]]; then
+    echo "❌ (An undeclared {print} should reach awk untouched: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# misspelled config keys are warned about with a suggestion, but still run
+echo -n "  Testing 'unknown fields'..."
+echo '{ "timing": true, "aliases": { "typo": { "run": "echo typo_ran", "timeout": 5 } } }' > "$CLI_JSON"
+OUTPUT=$($CS typo 2>&1)
+if [[ "$OUTPUT" != *'"timing" (did you mean "enable_timing"?)'* ]] \
+    || [[ "$OUTPUT" != *'"aliases.typo.timeout" (did you mean "timeout_secs"?)'* ]] || [[ "$OUTPUT" != *"typo_ran"* ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+if [[ "$($CS --allow-unknown-fields typo 2>&1)" == *"unknown field"* ]]; then
+    echo "❌ (--allow-unknown-fields should silence the warnings)"
+    exit 1
+fi
+echo "✅"
+
+# --dump-resolved prints what would run as JSON and runs nothing
+echo -n "  Testing 'dump resolved'..."
+echo '{ "variables": { "v": "var" }, "aliases": { "dumped": { "run": "echo {who} ${v} > dump_ran", "params": ["who"], "timeout_secs": 5 }, "dp": { "alias_of": "dumped" } } }' > "$CLI_JSON"
+OUTPUT=$($CS --dump-resolved --env DUMP=1 dp --who me)
+if [[ -f dump_ran ]] || [[ "$OUTPUT" != *'"target": "dumped"'* ]] || [[ "$OUTPUT" != *'"echo me var > dump_ran"'* ]] \
+    || [[ "$OUTPUT" != *'"DUMP": "1"'* ]] || [[ "$OUTPUT" != *'"timeout_secs": 5'* ]]; then
+    rm -f dump_ran
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# how runtime args become the final command: parameters by --name value or
+# --name=value, the rest appended before pipe_to, everything after -- passed on
+echo -n "  Testing 'resolution'..."
+echo '{ "aliases": { "dep": { "run": "deploy {env}", "params": ["env"], "pipe_to": "tee log" }, "plain": "ls" } }' > "$CLI_JSON"
+resolved() {
+    $CS --dump-resolved "$@" | sed -n '/"commands"/,/]/p' | sed -n 2p | sed 's/^ *"//; s/",\{0,1\}$//'
+}
+for case in 'dep --env prod -v|deploy prod -v | tee log' 'dep --env=prod|deploy prod | tee log' \
+    'dep --env prod -- --env x|deploy prod --env x | tee log' 'plain -- -la|ls -la' 'plain -- --|ls --'; do
+    # shellcheck disable=SC2086
+    GOT=$(resolved ${case%%|*})
+    if [[ "$GOT" != "${case#*|}" ]]; then
+        echo "❌ (${case%%|*} resolved to '$GOT', expected '${case#*|}')"
+        exit 1
+    fi
+done
+for case in 'dep|is missing --env' 'dep --nope x|has no parameter --nope' 'dep --env|--env needs a value'; do
+    set +e
+    # shellcheck disable=SC2086
+    OUTPUT=$($CS --dump-resolved ${case%%|*} 2>&1)
+    code=$?
+    set -e
+    if [[ $code -ne 4 ]] || [[ "$OUTPUT" != *"${case#*|}"* ]]; then
+        echo "❌ (${case%%|*} should be a usage error mentioning '${case#*|}', got $code: $OUTPUT)"
+        exit 1
+    fi
+done
+echo "✅"
+
+# built-in tokens are filled in without being asked for
+echo -n "  Testing 'built-in tokens'..."
+echo '{ "aliases": { "tokens": "echo cwd={cwd} date={date} time={time} alias={alias} host={host}", "tk": { "alias_of": "tokens" } } }' > "$CLI_JSON"
+OUTPUT=$($CS tk 2> /dev/null)
+for want in "cwd=$PWD" "date=$(date -u +%Y-%m-%d)" "alias=tk" "host=$(hostname 2> /dev/null || uname -n)"; do
+    if [[ "$OUTPUT" != *"$want"* ]]; then
+        echo "❌ (Expected $want in: $OUTPUT)"
+        exit 1
+    fi
+done
+if ! grep -qE 'time=[0-9]{2}:[0-9]{2}:[0-9]{2}' <<< "$OUTPUT"; then
+    echo "❌ (No time in: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# captured output stops at max_capture_bytes with a marker for the rest
+echo -n "  Testing 'capture limit'..."
+echo '{ "max_capture_bytes": 1000, "aliases": { "flood": "seq 1 100000" } }' > "$CLI_JSON"
+OUTPUT=$($CS --output json flood 2> /dev/null)
+if [[ "$OUTPUT" != *'...[truncated 587895 bytes]'* ]] || [[ "$OUTPUT" == *'"line":"100000"'* ]]; then
+    echo "❌ (Output wasn't truncated)"
+    exit 1
+fi
+if [[ $($CS flood 2> /dev/null | grep -c .) -lt 100000 ]]; then
+    echo "❌ (Plain runs shouldn't be capped)"
+    exit 1
+fi
+echo "✅"
+
+# banners use the name we were invoked as, without a path or .exe
+echo -n "  Testing 'program name'..."
+rm -f "$CLI_JSON"
+ln -sf "$PWD/target/debug/cs" cs_link.exe
+OUTPUT=$(./cs_link.exe add named_prog "true")
+rm -f cs_link.exe
+if [[ "$OUTPUT" != *" cs_link now stores"* ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# --output json turns a run into one JSON event per line
+echo -n "  Testing 'json output'..."
+echo '{ "enable_timing": true, "aliases": { "evented": ["echo from_a", "echo from_b >&2; exit 2"] } }' > "$CLI_JSON"
+set +e
+OUTPUT=$($CS --output json evented 2> /dev/null)
+code=$?
+set -e
+if [[ $code -ne 2 ]] || [[ $(grep -vc '^{"event":"' <<< "$OUTPUT") -ne 0 ]] \
+    || [[ "$OUTPUT" != *'"stream":"stdout","line":"from_a"'* ]] || [[ "$OUTPUT" != *'"stream":"stderr","line":"from_b"'* ]] \
+    || [[ "$(head -1 <<< "$OUTPUT")" != *'"run_start"'* ]] || [[ "$(tail -1 <<< "$OUTPUT")" != *'"run_end","alias":"evented","exit_code":2'* ]]; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+echo "✅"
+
+# failing alias must exit non-zero with or without timing/notify
+echo -n "  Testing 'failure exit'..."
+rm -f "$CLI_JSON"
+$CS add fails "exit 3" > /dev/null
+if $CS fails > /dev/null 2>&1 || $CS --notify fails > /dev/null 2>&1; then
+    echo "❌ (Failing alias exited zero)"
+    exit 1
+fi
+echo "✅"
+
+# --notify=terminal prints the notification on stderr instead of popping it up
+echo -n "  Testing 'terminal notify'..."
+echo '{ "aliases": { "fine": "true", "fails": "exit 3" } }' > "$CLI_JSON"
+OK_NOTE=$($CS --notify=terminal fine 2>&1 > /dev/null)
+set +e
+FAIL_NOTE=$($CS --notify=terminal fails 2>&1 > /dev/null)
+FAIL_CODE=$?
+set -e
+if [[ "$OK_NOTE" != *"Alias 'fine' finished successfully."* ]] || [[ "$FAIL_NOTE" != *"Alias 'fails' failed."* ]] \
+    || [[ $FAIL_CODE -ne 3 ]]; then
+    echo "❌ (Success: $OK_NOTE / Failure: $FAIL_CODE $FAIL_NOTE)"
+    exit 1
+fi
+# notify_mode in the config does the same for a bare --notify
+echo '{ "notify_mode": "terminal", "aliases": { "fine": "true" } }' > "$CLI_JSON"
+if [[ "$($CS --notify fine 2>&1 > /dev/null)" != *"finished successfully."* ]]; then
+    echo "❌ (notify_mode terminal wasn't used)"
+    exit 1
+fi
+# notify_min_interval_secs: a failure right after a notification is held back and
+# counted in the next one that goes out
+echo '{ "notify_min_interval_secs": 2, "aliases": { "fails": "exit 3" } }' > "$CLI_JSON"
+rm -f .cawa_notify_state.json
+NOTES=$( { $CS --notify=terminal fails; $CS --notify=terminal fails; } 2>&1 > /dev/null || true)
+sleep 3
+LATER=$($CS --notify=terminal fails 2>&1 > /dev/null || true)
+rm -f .cawa_notify_state.json
+if [[ $(grep -c "🔔" <<< "$NOTES") -ne 1 ]] || [[ "$LATER" != *"2 runs of alias 'fails' failed."* ]]; then
+    echo "❌ (Throttled: $NOTES / next: $LATER)"
+    exit 1
+fi
+# a single held-back failure isn't hidden by the success that comes next
+echo '{ "notify_min_interval_secs": 2, "aliases": { "fails": "exit 3", "works": "true" } }' > "$CLI_JSON"
+rm -f .cawa_notify_state.json
+$CS --notify=terminal works > /dev/null 2>&1
+$CS --notify=terminal fails > /dev/null 2>&1 || true
+sleep 3
+LATER=$($CS --notify=terminal works 2>&1 > /dev/null)
+rm -f .cawa_notify_state.json
+if [[ "$LATER" != *"1 run of alias 'works' failed."* ]]; then
+    echo "❌ (One throttled failure went unreported: $LATER)"
+    exit 1
+fi
+echo "✅"
+
+# on macos the desktop notification is an applescript; quotes and backslashes in
+# the alias name are escaped so they can't end its string early
+echo -n "  Testing 'osascript notify'..."
+if [[ "$(uname)" == Darwin ]]; then
+    FAKE_BIN=$(mktemp -d)
+    printf '#!/bin/sh\nprintf "%%s" "$2" > "%s/script.txt"\n' "$FAKE_BIN" > "$FAKE_BIN/osascript"
+    chmod +x "$FAKE_BIN/osascript"
+    echo '{ "aliases": { "say \"hi\\": "exit 1" } }' > "$CLI_JSON"
+    PATH="$FAKE_BIN:$PATH" $CS --notify=desktop 'say "hi\' > /dev/null 2>&1 || true
+    SCRIPT=$(cat "$FAKE_BIN/script.txt")
+    rm -rf "$FAKE_BIN"
+    if [[ "$SCRIPT" != 'display notification "Alias '"'"'say \"hi\\'"'"' failed." with title '* ]]; then
+        echo "❌ (Script: $SCRIPT)"
+        exit 1
+    fi
+    echo "✅"
+else
+    echo "⏭️  (osascript is macOS only)"
+fi
+
+# the config is read once per run: an alias that rewrites it doesn't change the
+# settings the rest of that run uses
+echo -n "  Testing 'config snapshot'..."
+SNAPSHOT='{ "notify_mode": "terminal", "aliases": { "rewrite": "printf %s '"'"'{ \"aliases\": {} }'"'"' > '"$CLI_JSON"'" } }'
+echo "$SNAPSHOT" > "$CLI_JSON"
+READS=$($CS --log-level debug --notify rewrite 2>&1 | grep -c "loading config $CLI_JSON" || true)
+echo "$SNAPSHOT" > "$CLI_JSON"
+OUTPUT=$($CS --notify rewrite 2>&1)
+if [[ "$OUTPUT" != *"finished successfully."* ]] || [[ "$(cat "$CLI_JSON")" != '{ "aliases": {} }' ]]; then
+    echo "❌ (Output: $OUTPUT / Config: $(cat "$CLI_JSON"))"
+    exit 1
+fi
+if [[ $READS -gt 1 ]]; then
+    echo "❌ (Config was read $READS times in one run)"
+    exit 1
+fi
+echo "✅"
+
+# cs clean keeps the newest runs, drops old logs and forgets exited processes
+echo -n "  Testing 'clean'..."
+echo '{ "history_keep": 3, "aliases": { "noop": "true" } }' > "$CLI_JSON"
+rm -f .cawa_history.jsonl .cawa_detached.json
+for i in 1 2 3 4 5; do $CS noop > /dev/null; done
+$CS --detach noop > /dev/null
+sleep 0.3
+mkdir -p .cawa_logs
+echo old > .cawa_logs/ancient.log
+touch -d '30 days ago' .cawa_logs/ancient.log 2> /dev/null || touch -t 200001010000 .cawa_logs/ancient.log
+PREVIEW=$($CS clean --dry-run)
+if [[ $(wc -l < .cawa_history.jsonl) -ne 6 ]] || [[ ! -f .cawa_logs/ancient.log ]] || [[ "$PREVIEW" != *"would remove 3 of 6 runs"* ]]; then
+    echo "❌ (--dry-run shouldn't change anything: $PREVIEW)"
+    exit 1
+fi
+OUTPUT=$($CS clean)
+LAST=$(tail -n 1 .cawa_history.jsonl)
+if [[ $(wc -l < .cawa_history.jsonl) -ne 3 ]] || [[ -f .cawa_logs/ancient.log ]] || [[ ! -f .cawa_logs/noop.log ]] \
+    || [[ "$OUTPUT" != *"removed 1 exited process(es): noop"* ]] || grep -q noop .cawa_detached.json; then
+    echo "❌ (Output: $OUTPUT)"
+    exit 1
+fi
+$CS clean --keep 1 > /dev/null
+if [[ "$(cat .cawa_history.jsonl)" != "$LAST" ]]; then
+    echo "❌ (--keep 1 should leave only the newest run, got $(cat .cawa_history.jsonl))"
+    exit 1
+fi
+rm -rf .cawa_logs .cawa_detached.json
+echo "✅"
+
+# steps run in order under their names and stop at the first failing one
+echo -n "  Testing 'steps'..."
+echo '{ "aliases": { "pipeline": { "steps": [ { "name": "first", "command": "sleep 0.3; echo 1 >> steps_out.txt" }, { "name": "second", "command": "echo 2 >> steps_out.txt; exit 5" }, { "name": "third", "command": "echo 3 >> steps_out.txt" } ] } } }' > "$CLI_JSON"
+rm -f steps_out.txt
+set +e
+OUTPUT=$($CS pipeline 2>&1)
+code=$?
+set -e
+RAN=$(tr -d '\n' < steps_out.txt)
+rm -f steps_out.txt
+if [[ $code -ne 5 ]] || [[ "$RAN" != "12" ]] || [[ "$OUTPUT" != *"▶ first"* ]] || [[ "$OUTPUT" != *"✗ second failed"* ]] \
+    || ! grep -qE '✓ first +[0-9]{3}ms' <<< "$OUTPUT" || ! grep -qE '– third +\(not run\)' <<< "$OUTPUT"; then
+    echo "❌ (Expected exit 5 after first and second, got $code, ran '$RAN': $OUTPUT)"
+    exit 1
+fi
+# each step's time is also in the json events
+EVENTS=$($CS --output json pipeline 2> /dev/null || true)
+rm -f steps_out.txt
+if [[ $(grep -c '"event":"command_end"' <<< "$EVENTS") -ne 2 ]] || ! grep -qE '"index":0,"exit_code":0,"duration_ms":[1-9][0-9]{2}' <<< "$EVENTS"; then
+    echo "❌ (Events: $EVENTS)"
+    exit 1
+fi
+echo "✅"
+
+# .cawa_cfg.toml is used when there's no json config, and stays toml when cs writes it
+echo -n "  Testing 'toml config'..."
+rm -f "$CLI_JSON"
+cat > .cawa_cfg.toml <<'TOMLEOF'
+# shared settings
+[variables]
+who = "toml" # who to greet
+
+[aliases]
+# greets once
+single = "echo single ${who} >> toml_out.txt"
+both = ["echo left >> toml_out.txt", "echo right >> toml_out.txt"]
+TOMLEOF
+rm -f toml_out.txt
+$CS single > /dev/null
+$CS both > /dev/null
+$CS add script --file <(printf 'echo "quoted \\ line" >> toml_out.txt\necho second >> toml_out.txt\n') > /dev/null
+$CS add -p pair "echo a" "echo b" > /dev/null
+ADD_AGAIN=$($CS add -p pair "echo a" "echo b")
+$CS script > /dev/null
+$CS both > /dev/null
+OUT=$(sort toml_out.txt | tr '\n' ' ')
+rm -f toml_out.txt
+if [[ "$OUT" != 'left left quoted \ line right right second single toml ' ]] || [[ "$ADD_AGAIN" != *"no changes"* ]] \
+    || [[ -f "$CLI_JSON" ]] || ! grep -q '^\[aliases.pair\]' .cawa_cfg.toml || ! grep -q '^parallel = \["echo a", "echo b"\]' .cawa_cfg.toml; then
+    echo "❌ (Output: $OUT / $ADD_AGAIN / Config: $(cat .cawa_cfg.toml))"
+    rm -f .cawa_cfg.toml
+    exit 1
+fi
+# adding and removing aliases leaves the comments alone
+$CS remove both > /dev/null
+for comment in '# shared settings' 'who = "toml" # who to greet' '# greets once'; do
+    if ! grep -qF "$comment" .cawa_cfg.toml; then
+        echo "❌ (Lost '$comment' on rewrite: $(cat .cawa_cfg.toml))"
+        rm -f .cawa_cfg.toml
+        exit 1
+    fi
+done
+rm -f .cawa_cfg.toml
+echo "✅"
+
+# concurrent changes to the config all land
+echo -n "  Testing 'config lock'..."
+rm -f "$CLI_JSON"
+for i in $(seq 1 12); do
+    $CS add "racer$i" "echo $i" > /dev/null &
+done
+$CS add -p racer_par "echo a" "echo b" > /dev/null &
+wait
+COUNT=$(grep -c '"racer' "$CLI_JSON" || true)
+if [[ "$COUNT" -ne 13 ]]; then
+    echo "❌ (Expected all 13 aliases to be saved, found $COUNT: $(cat "$CLI_JSON"))"
+    exit 1
+fi
+rm -f "$CLI_JSON" .cawa_cfg.lock
+echo "✅"
+
+# resource limits stop a command that goes past them (linux with python3 only)
+echo -n "  Testing 'resource limits'..."
+if [[ "$(uname)" != Linux ]] || ! command -v python3 > /dev/null; then
+    echo "⏭️  (needs Linux and python3)"
+else
+    echo '{ "aliases": { "hog": { "run": "python3 -c \"x = bytearray(512 * 1024 * 1024)\"", "memory_limit_mb": 256 }, "small": { "run": "python3 -c \"x = bytearray(1024 * 1024)\"", "memory_limit_mb": 256 }, "spin": { "run": "while :; do :; done", "cpu_seconds": 1, "timeout_secs": 20 }, "crash": { "run": "kill -SEGV $$", "memory_limit_mb": 256 }, "plain_fail": { "run": "exit 1", "memory_limit_mb": 256 } } }' > "$CLI_JSON"
+    set +e
+    HOG=$($CS hog 2>&1); HOG_CODE=$?
+    $CS small > /dev/null 2>&1; SMALL_CODE=$?
+    SPIN=$($CS spin 2>&1); SPIN_CODE=$?
+    CRASH=$($CS crash 2>&1)
+    PLAIN=$($CS plain_fail 2>&1)
+    set -e
+    # python's MemoryError is an ordinary exit 1, so there's no guess; a crash is
+    # blamed on the limit, a plain failure isn't
+    if [[ $HOG_CODE -eq 0 ]] || [[ "$HOG" != *"MemoryError"* ]] || [[ $SMALL_CODE -ne 0 ]] \
+        || [[ $SPIN_CODE -eq 0 ]] || [[ $SPIN_CODE -eq 124 ]] || [[ "$SPIN" != *"cpu_seconds"* ]] \
+        || [[ "$CRASH" != *"out of memory under its 256 MB limit"* ]] || [[ "$PLAIN" == *"memory_limit_mb"* ]]; then
+        echo "❌ (hog $HOG_CODE: $HOG / small $SMALL_CODE / spin $SPIN_CODE: $SPIN / crash: $CRASH / plain: $PLAIN)"
+        exit 1
+    fi
+    rm -f "$CLI_JSON"
+    echo "✅"
+fi
+
+# output patterns overrule the exit code in both directions
+echo -n "  Testing 'output patterns'..."
+echo '{ "aliases": { "lying": { "run": "echo ERROR: disk full", "failure_pattern": "^ERROR" }, "rescued": { "run": "echo all good; exit 3", "success_pattern": "all good", "success_overrides_exit": true }, "unrescued": { "run": "echo all good; exit 3", "success_pattern": "all good" }, "silent": { "run": "echo nothing", "success_pattern": "all good" } } }' > "$CLI_JSON"
+codes=""
+for a in lying rescued unrescued silent; do
+    set +e
+    $CS $a > /dev/null 2>&1
+    codes="$codes$? "
+    set -e
+done
+NOTE=$($CS lying 2>&1 > /dev/null || true)
+if [[ "$codes" != "1 0 3 1 " ]] || [[ "$NOTE" != *"matched failure_pattern: ERROR: disk full"* ]]; then
+    echo "❌ (Exit codes: $codes / Note: $NOTE)"
+    exit 1
+fi
+echo '{ "aliases": { "broken": { "run": "true", "failure_pattern": "(" } } }' > "$CLI_JSON"
+set +e
+$CS broken > /dev/null 2>&1
+code=$?
+set -e
+if [[ $code -ne 2 ]]; then
+    echo "❌ (An invalid regex should be a config error, got $code)"
+    exit 1
+fi
+rm -f "$CLI_JSON"
+echo "✅"
+
+# retries wait longer each time, up to the cap, and stop at the first success
+echo -n "  Testing 'retries'..."
+rm -f retry_tries
+echo '{ "aliases": { "flaky": { "run": "echo x >> retry_tries; [ $(wc -l < retry_tries) -ge 3 ]", "retries": 4, "retry_delay_ms": 50 }, "never": { "run": "exit 5", "retries": 3, "retry_delay_ms": 100, "retry_backoff": "exponential", "retry_max_delay_ms": 250 }, "jittery": { "run": "exit 5", "retries": 3, "retry_delay_ms": 200, "retry_jitter": true } } }' > "$CLI_JSON"
+$CS flaky > /dev/null 2>&1
+if [[ $(wc -l < retry_tries) -ne 3 ]]; then
+    echo "❌ (flaky should have stopped after its third try, ran $(wc -l < retry_tries) times)"
+    exit 1
+fi
+set +e
+NEVER=$($CS never 2>&1)
+code=$?
+JITTERED=$($CS jittery 2>&1 | grep -o 'retrying in [0-9]*ms' | grep -o '[0-9]*')
+set -e
+WAITS=$(echo "$NEVER" | grep -o 'retrying in [0-9]*ms' | grep -o '[0-9]*' | tr '\n' ' ')
+if [[ "$WAITS" != "100 200 250 " ]] || [[ $code -ne 5 ]]; then
+    echo "❌ (Exponential waits capped at 250ms should be 100 200 250 then exit 5, got $WAITS/$code)"
+    exit 1
+fi
+for wait in $JITTERED; do
+    if (( wait < 100 || wait > 200 )); then
+        echo "❌ (A jittered 200ms wait should be 100-200ms, got $wait)"
+        exit 1
+    fi
+done
+if [[ $(echo "$JITTERED" | wc -w) -ne 3 ]]; then
+    echo "❌ (jittery should have retried 3 times: $JITTERED)"
+    exit 1
+fi
+# a fixed seed gives the same waits every time
+set +e
+SEEDED=$(CAWA_RETRY_SEED=42 $CS jittery 2>&1 | grep -o 'retrying in [0-9]*ms' | grep -o '[0-9]*' | tr '\n' ' ')
+set -e
+if [[ "$SEEDED" != "100 162 154 " ]]; then
+    echo "❌ (Seed 42 should wait 100 162 154, got $SEEDED)"
+    exit 1
+fi
+for broken in '"retry_backoff": "quadratic"' '"retry_delay_ms": "soon"' '"retry_max_delay_ms": -1'; do
+    echo "{ \"aliases\": { \"broken\": { \"run\": \"true\", $broken } } }" > "$CLI_JSON"
+    set +e
+    $CS broken > /dev/null 2>&1
+    code=$?
+    set -e
+    if [[ $code -ne 2 ]]; then
+        echo "❌ ($broken should be a config error, got $code)"
+        exit 1
+    fi
+done
+rm -f retry_tries "$CLI_JSON"
+echo "✅"
+
+# a parameter with a default can be left out, one without can't
+echo -n "  Testing 'param defaults'..."
+echo '{ "aliases": { "deploy": { "run": "echo {env} {region}", "params": ["env"], "defaults": { "region": "us-1" } } } }' > "$CLI_JSON"
+USED=$($CS deploy --env prod 2>/dev/null | tail -1)
+OVERRIDDEN=$($CS deploy --env prod --region eu 2>/dev/null | tail -1)
+if [[ "$USED" != "prod us-1" ]] || [[ "$OVERRIDDEN" != "prod eu" ]]; then
+    echo "❌ (Got '$USED' with the default, '$OVERRIDDEN' with --region eu)"
+    exit 1
+fi
+set +e
+MISSING=$($CS deploy 2>&1)
+code=$?
+set -e
+if [[ $code -ne 4 ]] || [[ "$MISSING" != *"[--region <region>, default us-1]"* ]]; then
+    echo "❌ (Leaving out --env should be a usage error showing the default, got $code: $MISSING)"
+    exit 1
+fi
+rm -f "$CLI_JSON"
+echo "✅"
+
+# watch reruns on a change, at once on SIGHUP, and ctrl-c ends it cleanly. what
+# the alias writes into the watched directory itself doesn't trigger another run
+echo -n "  Testing 'watch'..."
+echo '{ "aliases": { "count": "echo run >> watch_runs.txt; echo run >> watch_src/own.txt" } }' > "$CLI_JSON"
+rm -rf watch_src watch_runs.txt && mkdir watch_src
+wait_runs() {
+    for _ in $(seq 50); do
+        [[ -f watch_runs.txt && $(wc -l < watch_runs.txt) -ge $1 ]] && return 0
+        sleep 0.1
+    done
+    echo "❌ (Expected $1 runs, got $(cat watch_runs.txt 2>/dev/null | wc -l))"
+    kill $WATCH_PID 2>/dev/null
+    exit 1
+}
+# a change made before the run has fully finished is taken as its own write, so
+# wait until it's watching again
+wait_watching() {
+    for _ in $(seq 50); do
+        [[ $(grep -c Watching watch_out.txt) -ge $1 ]] && return 0
+        sleep 0.1
+    done
+    echo "❌ (Not watching again after run $1)"
+    kill $WATCH_PID 2>/dev/null
+    exit 1
+}
+$CS --notify=terminal watch -w watch_src count < /dev/null > watch_out.txt 2> watch_notes.txt &
+WATCH_PID=$!
+wait_runs 1
+sleep 1.5
+if [[ $(wc -l < watch_runs.txt) -ne 1 ]]; then
+    echo "❌ (The alias's own writes retriggered it: $(wc -l < watch_runs.txt) runs)"
+    kill $WATCH_PID
+    exit 1
+fi
+kill -HUP $WATCH_PID
+wait_runs 2
+wait_watching 2
+touch watch_src/changed
+wait_runs 3
+kill -INT $WATCH_PID
+set +e
+wait $WATCH_PID
+code=$?
+set -e
+if [[ $code -ne 0 ]]; then
+    echo "❌ (Ctrl-C should stop watching with 0, got $code)"
+    exit 1
+fi
+# --notify goes off once per run, not once when the watch ends
+if [[ $(grep -c "Alias 'count' finished successfully." watch_notes.txt) -ne 3 ]]; then
+    echo "❌ (Expected a notification per run: $(cat watch_notes.txt))"
+    exit 1
+fi
+rm -f watch_notes.txt watch_out.txt
+# r and q are read from a terminal; script gives it one
+if command -v script > /dev/null && script -qec true /dev/null > /dev/null 2>&1; then
+    rm -f watch_runs.txt
+    (sleep 1; printf r; sleep 1; printf q; sleep 1) | script -qec "$CS watch -w watch_src count" /dev/null > /dev/null
+    if [[ $(wc -l < watch_runs.txt) -ne 2 ]]; then
+        echo "❌ (r should rerun once and q quit, got $(wc -l < watch_runs.txt) runs)"
+        exit 1
+    fi
+fi
+rm -rf watch_src watch_runs.txt "$CLI_JSON"
+echo "✅"
+
+# space checks aliases in the tui and Enter runs them in the order they were checked
+echo -n "  Testing 'tui multi-select'..."
+if command -v script > /dev/null && script -qec true /dev/null > /dev/null 2>&1; then
+    rm -f picked.txt
+    echo '{ "aliases": { "a_one": "echo one >> picked.txt", "b_two": "echo two >> picked.txt", "c_three": "echo three >> picked.txt" } }' > "$CLI_JSON"
+    (sleep 1; printf 'j '; sleep 0.2; printf 'k '; sleep 0.2; printf '\r'; sleep 1) | script -qec "$CS tui --fresh" /dev/null > /dev/null
+    if [[ "$(tr '\n' ' ' < picked.txt 2> /dev/null)" != "two one " ]]; then
+        rm -f picked.txt
+        echo "❌ (Expected b_two then a_one, got $(cat picked.txt 2> /dev/null))"
+        exit 1
+    fi
+    rm -f picked.txt .cawa_tui_state.json
+    echo "✅"
+else
+    echo "⏭️  (no script for a terminal)"
+fi
+
+# tui search looks at descriptions and commands too, names first
+echo -n "  Testing 'tui search'..."
+if command -v script > /dev/null && script -qec true /dev/null > /dev/null 2>&1; then
+    rm -f searched.txt
+    echo '{ "aliases": { "a_build": { "run": "echo build >> searched.txt", "description": "compile everything" }, "b_ship": "echo ship >> searched.txt; echo rocket", "c_rocket": "echo rocket_name >> searched.txt" } }' > "$CLI_JSON"
+    for query in compile ship rocket; do
+        (sleep 1; printf '/%s' "$query"; sleep 0.3; printf '\r'; sleep 1) | script -qec "$CS tui --fresh" /dev/null > /dev/null
+    done
+    # rocket is in b_ship's command but c_rocket's name, and the name wins
+    if [[ "$(tr '\n' ' ' < searched.txt 2> /dev/null)" != "build ship rocket_name " ]]; then
+        echo "❌ (Search picked: $(cat searched.txt 2> /dev/null))"
+        rm -f searched.txt
+        exit 1
+    fi
+    rm -f searched.txt .cawa_tui_state.json
+    echo "✅"
+else
+    echo "⏭️  (no script for a terminal)"
+fi
+
+# --tag narrows the tui to tagged aliases and says so when there are none; / finds tags too
+echo -n "  Testing 'tui tag'..."
+if command -v script > /dev/null && script -qec true /dev/null > /dev/null 2>&1; then
+    rm -f tagged.txt
+    echo '{ "aliases": { "a_plain": "echo plain >> tagged.txt", "b_ship": { "run": "echo shipped >> tagged.txt", "tags": ["deploy"] } } }' > "$CLI_JSON"
+    # script's terminal starts out 0x0, too small to draw anything
+    SHOWN=$( (sleep 1; printf 'q'; sleep 0.5) | script -qec "stty cols 200 rows 30; $CS tui --fresh --tag deploy" /dev/null | grep -aoE '#deploy|a_plain|b_ship' | sort -u | tr '\n' ' ')
+    EMPTY=$( (sleep 1; printf 'q'; sleep 0.5) | script -qec "stty cols 200 rows 30; $CS tui --fresh --tag nothing" /dev/null | grep -ao "No aliases are tagged 'nothing'")
+    (sleep 1; printf '\r'; sleep 1) | script -qec "$CS tui --fresh --tag deploy" /dev/null > /dev/null
+    (sleep 1; printf '/deploy'; sleep 0.3; printf '\r'; sleep 1) | script -qec "$CS tui --fresh" /dev/null > /dev/null
+    if [[ "$SHOWN" != "#deploy b_ship " ]] || [[ -z "$EMPTY" ]] || [[ "$(tr '\n' ' ' < tagged.txt 2> /dev/null)" != "shipped shipped " ]]; then
+        echo "❌ (Shown: $SHOWN / Empty state: $EMPTY / Ran: $(cat tagged.txt 2> /dev/null))"
+        rm -f tagged.txt
+        exit 1
+    fi
+    rm -f tagged.txt .cawa_tui_state.json
+    echo "✅"
+else
+    echo "⏭️  (no script for a terminal)"
+fi
+
+# the tui reopens on the alias it was left on, unless --fresh
+echo -n "  Testing 'tui state'..."
+if command -v script > /dev/null && script -qec true /dev/null > /dev/null 2>&1; then
+    rm -f resumed.txt .cawa_tui_state.json
+    echo '{ "aliases": { "a_first": "echo first >> resumed.txt", "b_second": "echo second >> resumed.txt" } }' > "$CLI_JSON"
+    (sleep 1; printf 'j'; sleep 0.3; printf 'q'; sleep 0.5) | script -qec "$CS tui" /dev/null > /dev/null
+    (sleep 1; printf '\r'; sleep 1) | script -qec "$CS tui" /dev/null > /dev/null
+    (sleep 1; printf '\r'; sleep 1) | script -qec "$CS tui --fresh" /dev/null > /dev/null
+    if [[ ! -f .cawa_tui_state.json ]] || [[ "$(tr '\n' ' ' < resumed.txt 2> /dev/null)" != "second first " ]]; then
+        echo "❌ (Expected to resume on b_second, then start over with --fresh: $(cat resumed.txt 2> /dev/null))"
+        rm -f resumed.txt
+        exit 1
+    fi
+    rm -f resumed.txt .cawa_tui_state.json
+    echo "✅"
+else
+    echo "⏭️  (no script for a terminal)"
+fi
+
+# confirm: by name it just runs; in the tui Enter opens the popup, a second Enter
+# doesn't count as a yes and only y runs it. tui_confirm asks for every alias
+echo -n "  Testing 'confirm'..."
+rm -f confirm_ran.txt
+$CS add --confirm nuke "echo nuke >> confirm_ran.txt" > /dev/null
+$CS nuke < /dev/null > /dev/null
+if ! grep -q '"confirm": true' "$CLI_JSON" || [[ "$(cat confirm_ran.txt)" != "nuke" ]]; then
+    echo "❌ (A confirm alias run by name should just run)"
+    exit 1
+fi
+echo '{ "tui_confirm": "yes", "aliases": {} }' > "$CLI_JSON"
+set +e
+$CS list > /dev/null 2>&1
+code=$?
+set -e
+if [[ $code -ne 2 ]]; then
+    echo "❌ (tui_confirm should only take true or false, got exit $code)"
+    exit 1
+fi
+# the tui needs a terminal; script gives it one
+if command -v script > /dev/null && script -qec true /dev/null > /dev/null 2>&1; then
+    rm -f confirm_ran.txt
+    echo '{ "aliases": { "a_one": "echo one >> confirm_ran.txt", "b_nuke": { "run": "echo nuke >> confirm_ran.txt", "confirm": true } } }' > "$CLI_JSON"
+    (sleep 1; printf 'j'; sleep 0.3; printf '\r'; sleep 0.3; printf '\r'; sleep 0.5; cp confirm_ran.txt confirm_early.txt 2> /dev/null || true; printf 'y'; sleep 1) \
+        | script -qec "$CS tui --fresh" /dev/null > /dev/null
+    if [[ -f confirm_early.txt ]] || [[ "$(cat confirm_ran.txt 2> /dev/null)" != "nuke" ]]; then
+        rm -f confirm_ran.txt confirm_early.txt
+        echo "❌ (confirm should run only after y)"
+        exit 1
+    fi
+    rm -f confirm_ran.txt
+    echo '{ "tui_confirm": true, "aliases": { "a_one": "echo one >> confirm_ran.txt" } }' > "$CLI_JSON"
+    (sleep 1; printf '\r'; sleep 0.3; printf 'n'; sleep 0.3; printf 'q'; sleep 0.5) | script -qec "$CS tui --fresh" /dev/null > /dev/null
+    if [[ -f confirm_ran.txt ]]; then
+        rm -f confirm_ran.txt
+        echo "❌ (tui_confirm should ask first, and n should cancel)"
+        exit 1
+    fi
+fi
+rm -f confirm_ran.txt .cawa_tui_state.json "$CLI_JSON"
+echo "✅"
+
+# documented exit codes
+echo -n "  Testing 'exit codes'..."
+expect_code() {
+    local want=$1; shift
+    set +e
+    "$@" > /dev/null 2>&1
+    local got=$?
+    set -e
+    if [[ $got -ne $want ]]; then
+        echo "❌ ('$*' exited $got, expected $want)"
+        exit 1
+    fi
+}
+rm -f "$CLI_JSON"
+$CS add fails "exit 7" > /dev/null
+$CS add ok "true" > /dev/null
+expect_code 0 $CS ok
+expect_code 7 $CS fails            # child's own code passed through
+expect_code 3 $CS no_such_alias
+if [[ "$($CS okk 2>&1)" != *"did you mean ok"* ]]; then
+    echo "❌ (Unknown alias should suggest the closest name)"
+    exit 1
+fi
+expect_code 4 $CS add
+$CS add --timeout 1 too_slow "sleep 5" > /dev/null
+expect_code 124 $CS too_slow       # timeouts get their own code
+# a timeout takes down what the command started too, not just its shell
+rm -f leaked.txt
+echo '{ "aliases": { "leaky": { "run": "(sleep 2; echo leaked > leaked.txt) & sleep 10", "timeout_secs": 1 } } }' > "$CLI_JSON"
+expect_code 124 $CS leaky
+sleep 2
+if [[ -f leaked.txt ]]; then
+    rm -f leaked.txt
+    echo "❌ (A timed-out alias's background job kept running)"
+    exit 1
+fi
+# only a timeout cs enforced is notified as one, not a command that exits 124 itself
+echo '{ "aliases": { "too_slow": { "run": "sleep 5", "timeout_secs": 1 }, "own124": "exit 124" } }' > "$CLI_JSON"
+set +e
+SLOW_NOTE=$($CS --notify=terminal too_slow 2>&1)
+OWN_NOTE=$($CS --notify=terminal own124 2>&1)
+set -e
+if [[ "$SLOW_NOTE" != *"timed out after 1s"* ]] || [[ "$OWN_NOTE" == *"timed out"* ]] || [[ "$OWN_NOTE" != *"'own124' failed"* ]]; then
+    echo "❌ (Timeout notifications: '$SLOW_NOTE' / '$OWN_NOTE')"
+    exit 1
+fi
+# ctrl-c reaches the whole tree as well, and cs exits 130
+echo '{ "aliases": { "leaky": "sh -c \"sleep 2; echo leaked > leaked.txt\"; true" } }' > "$CLI_JSON"
+$CS leaky < /dev/null > /dev/null 2>&1 &
+LEAKY_PID=$!
+sleep 0.5
+kill -INT $LEAKY_PID
+set +e
+wait $LEAKY_PID
+code=$?
+set -e
+sleep 2
+if [[ $code -ne 130 ]] || [[ -f leaked.txt ]]; then
+    rm -f leaked.txt
+    echo "❌ (Ctrl-C should stop the whole command and exit 130, got $code)"
+    exit 1
+fi
+echo '{ not json' > "$CLI_JSON"
+expect_code 2 $CS list
+echo "✅"
+
+echo "🎉 All manual tests passed!"
+rm -f "$CLI_JSON" .cawa_state.json .cawa_last_run.json .cawa_history.jsonl .cawa_cfg.lock